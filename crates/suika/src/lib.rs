@@ -24,7 +24,15 @@ pub mod templates {
 pub mod server {
   pub use suika_server::server::Server;
   pub use suika_server::router::Router;
+  pub use suika_server::host_router::HostRouter;
+  pub use suika_server::jwt;
+  pub use suika_server::request::Request;
+  pub use suika_server::resource::{Resource, ResourceAction};
+  pub use suika_server::response::Response;
+  pub use suika_server::cookie::{Cookie, SameSite};
+  pub use suika_server::into_response::{IntoResponse, Template};
   pub use suika_server::error::HttpError;
+  pub use suika_server::error_handler::{ErrorHandler, ErrorPages};
 }
 
 pub mod middleware {
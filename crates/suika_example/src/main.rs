@@ -1,16 +1,16 @@
 mod todos;
 
-use crate::todos::TodoStore;
+use crate::todos::{TodoResource, TodoStore};
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use suika::{
     json::JsonValue,
     middleware::{
-        CorsMiddleware, FaviconMiddleware, LoggerMiddleware, StaticFileMiddleware,
-        WasmFileMiddleware,
+        AccessLogMiddleware, CorsMiddleware, FaviconMiddleware, RequestIdMiddleware,
+        StaticFileMiddleware, WasmFileMiddleware,
     },
-    server::{Router, Server},
+    server::{ErrorPages, Router, Server, Template},
     templates::{TemplateEngine, TemplateValue},
 };
 
@@ -40,33 +40,23 @@ fn main() {
     );
 
     server.use_templates(template_engine);
+
+    let error_pages = ErrorPages::new().template("error.html");
+    server.use_error_handler(move |error, req, res| error_pages.render(error, req, res));
     server.use_module("todo_store", todo_store);
 
-    main_router.add_route(Some("GET"), r"/$", |_req, res| {
+    main_router.add_route(Some("GET"), "/", |_req, res| {
         Box::pin(async move {
             if let Err(e) = res.send_file("crates/suika_example/index.html").await {
-                res.error(e).await;
+                res.error(e);
             }
             Ok(())
         })
     });
 
-    main_router.add_route(Some("GET"), "/todo", |req, res| {
-        Box::pin(async move {
-            if let Some(store) = req.module::<TodoStore>("todo_store") {
-                let todos = store.to_json();
-
-                res.set_status(200).await;
-                res.body_json(todos).await;
-            } else {
-                res.set_status(404).await;
-                res.body("No todos found".to_string()).await;
-            }
-            Ok(())
-        })
-    });
+    main_router.resource("/todos", TodoResource);
 
-    main_router.add_route(Some("GET"), "json", |_req, res| {
+    main_router.add_route(Some("GET"), "/json", |_req, res| {
         Box::pin(async move {
             let json = JsonValue::Object(vec![
                 (
@@ -95,26 +85,19 @@ fn main() {
                 ),
             ]);
 
-            res.set_status(200).await;
-            res.body_json(json).await;
+            res.set_status(200);
+            res.body_json(json);
 
             Ok(())
         })
     });
 
-    main_router.add_route(Some("GET"), "/hello", |_req, res| {
+    main_router.get("/hello", |_req| {
         Box::pin(async move {
-            let mut context = HashMap::new();
-
-            context.insert(
-                "name".to_string(),
-                TemplateValue::String("World".to_string()),
-            );
-
-            res.set_status(200).await;
-            res.render_template("hello.html", &context).await?;
-
-            Ok(())
+            Ok(
+                Template::new("hello.html")
+                    .with("name", TemplateValue::String("World".to_string())),
+            )
         })
     });
 
@@ -127,8 +110,8 @@ fn main() {
                 TemplateValue::String("World".to_string()),
             );
 
-            res.set_status(200).await;
-            res.render_template("include.html", &context).await?;
+            res.set_status(200);
+            res.render_template("include.html", &context)?;
 
             Ok(())
         })
@@ -141,8 +124,8 @@ fn main() {
             context.insert("is_member".to_string(), TemplateValue::Boolean(true));
             context.insert("name".to_string(), TemplateValue::String("Bob".to_string()));
 
-            res.set_status(200).await;
-            res.render_template("conditional.html", &context).await?;
+            res.set_status(200);
+            res.render_template("conditional.html", &context)?;
 
             Ok(())
         })
@@ -161,8 +144,8 @@ fn main() {
                 ]),
             );
 
-            res.set_status(200).await;
-            res.render_template("loop.html", &context).await?;
+            res.set_status(200);
+            res.render_template("loop.html", &context)?;
 
             Ok(())
         })
@@ -185,37 +168,50 @@ fn main() {
             let mut context = HashMap::new();
             context.insert("user".to_string(), TemplateValue::Object(user));
 
-            res.set_status(200).await;
-            res.render_template("user.html", &context).await?;
+            res.set_status(200);
+            res.render_template("user.html", &context)?;
 
             Ok(())
         })
     });
 
-    main_router.add_route(Some("GET"), r"/items/(?P<id>\d+)$", |req, res| {
+    main_router.add_route(Some("GET"), r"/items/:id(\d+)", |req, res| {
         Box::pin(async move {
-            res.set_status(200).await;
+            res.set_status(200);
             let item_id = req.param("id").map(|s| s.to_string()).unwrap_or_default();
-            res.body(format!("You requested item with ID: {}", item_id))
-                .await;
+            res.body(format!("You requested item with ID: {}", item_id));
             Ok(())
         })
     });
 
     let mut user_router = Router::new("/users");
 
-    user_router.add_route(Some("POST"), r"/?$", |_req, res| {
+    user_router.add_route(Some("POST"), "/", |_req, res| {
         Box::pin(async move {
-            res.set_status(201).await;
-            res.body("New user created!".to_string()).await;
+            res.set_status(201);
+            res.body("New user created!".to_string());
             Ok(())
         })
     });
 
+    user_router
+        .fallback(|req| Box::pin(async move { Ok(format!("No user endpoint at {}", req.path())) }));
+
     main_router.mount(user_router);
 
-    server.use_middleware(Arc::new(CorsMiddleware));
-    server.use_middleware(Arc::new(LoggerMiddleware));
+    for conflict in main_router.conflicts() {
+        eprintln!("Route conflict: {}", conflict);
+    }
+
+    let route_table = main_router.route_table();
+    main_router.get("/_routes", move |_req| {
+        let route_table = route_table.clone();
+        Box::pin(async move { Ok(route_table) })
+    });
+
+    server.use_middleware(Arc::new(CorsMiddleware::new()));
+    server.use_middleware(Arc::new(RequestIdMiddleware::new()));
+    server.use_middleware(Arc::new(AccessLogMiddleware::new()));
 
     server.use_middleware(Arc::new(FaviconMiddleware::new(
         "crates/suika_example/public/favicon.ico",
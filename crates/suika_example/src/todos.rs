@@ -1,5 +1,9 @@
 use std::sync::{Arc, RwLock};
-use suika::json::JsonValue;
+use suika::{
+    json::JsonValue,
+    middleware::MiddlewareFuture,
+    server::{HttpError, Request, Resource, ResourceAction, Response},
+};
 
 #[derive(Debug, Clone)]
 pub struct Todo {
@@ -50,6 +54,11 @@ impl TodoStore {
         todo
     }
 
+    pub fn find_todo(&self, id: usize) -> Option<Todo> {
+        let todos = self.todos.read().unwrap();
+        todos.iter().find(|todo| todo.id == id).cloned()
+    }
+
     pub fn to_json(&self) -> JsonValue {
         let todos = self.todos.read().unwrap();
         JsonValue::Array(todos.iter().map(|todo| todo.to_json()).collect())
@@ -59,3 +68,65 @@ impl TodoStore {
         title.to_lowercase().replace(" ", "-")
     }
 }
+
+pub struct TodoResource;
+
+impl TodoResource {
+    fn store(req: &Request) -> Result<Arc<TodoStore>, HttpError> {
+        req.module::<TodoStore>("todo_store")
+            .ok_or_else(|| HttpError::InternalServerError("No todo store".to_string()))
+    }
+
+    fn field(json: Option<&JsonValue>, key: &str) -> Option<String> {
+        match json {
+            Some(JsonValue::Object(fields)) => {
+                fields.iter().find_map(|(name, value)| match value {
+                    JsonValue::String(value) if name == key => Some(value.clone()),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Resource for TodoResource {
+    fn actions(&self) -> Vec<ResourceAction> {
+        vec![
+            ResourceAction::Index,
+            ResourceAction::Show,
+            ResourceAction::Create,
+        ]
+    }
+
+    fn index<'a>(&'a self, req: &'a mut Request, res: &'a mut Response) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            res.body_json(Self::store(req)?.to_json());
+            Ok(())
+        })
+    }
+
+    fn show<'a>(&'a self, req: &'a mut Request, res: &'a mut Response) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let todo = req
+                .param("id")
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| Self::store(req).ok()?.find_todo(id))
+                .ok_or_else(|| HttpError::NotFound("No such todo".to_string()))?;
+            res.body_json(todo.to_json());
+            Ok(())
+        })
+    }
+
+    fn create<'a>(&'a self, req: &'a mut Request, res: &'a mut Response) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let title = Self::field(req.json_body(), "title")
+                .ok_or_else(|| HttpError::BadRequest("A title is required".to_string()))?;
+            let content = Self::field(req.json_body(), "content").unwrap_or_default();
+            let todo = Self::store(req)?.add_todo(title, content);
+            res.set_status(201);
+            res.body_json(todo.to_json());
+            Ok(())
+        })
+    }
+}
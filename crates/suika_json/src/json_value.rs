@@ -41,7 +41,7 @@ pub enum JsonValue {
     Null,
 }
 
-impl JsonValue {
+impl fmt::Display for JsonValue {
     /// Serializes the `JsonValue` to a JSON string.
     ///
     /// # Examples
@@ -73,34 +73,12 @@ impl JsonValue {
     /// ]);
     /// assert_eq!(json_object.to_string(), "{\"key\":\"value\"}");
     /// ```
-    pub fn to_string(&self) -> String {
-        match self {
-            JsonValue::Object(obj) => {
-                let entries: Vec<String> = obj
-                    .iter()
-                    .map(|(k, v)| format!("\"{}\":{}", k, v.to_string()))
-                    .collect();
-                format!("{{{}}}", entries.join(","))
-            }
-            JsonValue::Array(arr) => {
-                let entries: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
-                format!("[{}]", entries.join(","))
-            }
-            JsonValue::String(s) => format!("\"{}\"", s),
-            JsonValue::Number(n) => n.to_string(),
-            JsonValue::Boolean(b) => b.to_string(),
-            JsonValue::Null => "null".to_string(),
-        }
-    }
-}
-
-impl fmt::Display for JsonValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             JsonValue::Object(obj) => {
                 let entries: Vec<String> = obj
                     .iter()
-                    .map(|(k, v)| format!("\"{}\":{}", k, v))
+                    .map(|(k, v)| format!("\"{}\":{}", escape(k), v))
                     .collect();
                 write!(f, "{{{}}}", entries.join(","))
             }
@@ -108,7 +86,7 @@ impl fmt::Display for JsonValue {
                 let entries: Vec<String> = arr.iter().map(|v| v.to_string()).collect();
                 write!(f, "[{}]", entries.join(","))
             }
-            JsonValue::String(s) => write!(f, "\"{}\"", s),
+            JsonValue::String(s) => write!(f, "\"{}\"", escape(s)),
             JsonValue::Number(n) => write!(f, "{}", n),
             JsonValue::Boolean(b) => write!(f, "{}", b),
             JsonValue::Null => write!(f, "null"),
@@ -116,6 +94,25 @@ impl fmt::Display for JsonValue {
     }
 }
 
+/// Escapes quotes, backslashes and control characters in a JSON string.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0c' => escaped.push_str("\\f"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,6 +170,13 @@ mod tests {
             r#"{"user":{"name":"John Doe","age":30,"is_student":false},"courses":["Math","Science"]}"#
         );
 
+        // Test escaped strings
+        let escaped = JsonValue::Object(vec![(
+            "say \"hi\"".to_string(),
+            JsonValue::String("a\\b\nc\u{1}".to_string()),
+        )]);
+        assert_eq!(escaped.to_string(), r#"{"say \"hi\"":"a\\b\nc\u0001"}"#);
+
         // Test empty object
         let empty_object = JsonValue::Object(vec![]);
         assert_eq!(empty_object.to_string(), "{}");
@@ -164,6 +164,7 @@ impl<'a> JsonParser<'a> {
                         'n' => '\n',
                         'r' => '\r',
                         't' => '\t',
+                        'u' => self.parse_unicode_escape()?,
                         _ => return Err(format!("Invalid escape character: {}", escaped_char)),
                     });
                 } else {
@@ -178,6 +179,41 @@ impl<'a> JsonParser<'a> {
         Err("Unexpected end of input in string".to_string())
     }
 
+    /// Parses the hex digits of a `\u` escape, combining UTF-16 surrogate
+    /// pairs such as `\ud83c\udf49` into a single character.
+    fn parse_unicode_escape(&mut self) -> Result<char, String> {
+        let mut code = self.parse_hex4()?;
+
+        if (0xD800..0xDC00).contains(&code) {
+            self.next_char();
+            let backslash = self.current_char;
+            self.next_char();
+            if backslash != Some('\\') || self.current_char != Some('u') {
+                return Err("Expected low surrogate in unicode escape".to_string());
+            }
+            let low = self.parse_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(format!("Invalid low surrogate: {:04x}", low));
+            }
+            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+        }
+
+        char::from_u32(code).ok_or_else(|| format!("Invalid unicode escape: {:04x}", code))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            self.next_char();
+            let digit = self
+                .current_char
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| "Invalid unicode escape".to_string())?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
     fn parse_number(&mut self) -> Result<JsonValue, String> {
         let mut number_str = String::new();
 
@@ -187,7 +223,7 @@ impl<'a> JsonParser<'a> {
         }
 
         while let Some(c) = self.current_char {
-            if c.is_digit(10) || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-' {
+            if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-' {
                 number_str.push(c);
                 self.next_char();
             } else {
@@ -254,6 +290,20 @@ mod tests {
         assert_eq!(value, JsonValue::String("hello".to_string()));
     }
 
+    #[test]
+    fn test_parse_unicode_escapes() {
+        let json = r#""caf\u00e9 \ud83c\udf49 \"q\"""#;
+        let value = parse_json(json).unwrap();
+        assert_eq!(value, JsonValue::String("café 🍉 \"q\"".to_string()));
+
+        for invalid in [r#""\u00g0""#, r#""\ud83c""#, r#""\ud83cA""#, r#""\udf49""#] {
+            assert!(parse_json(invalid).is_err(), "{}", invalid);
+        }
+
+        let round_trip = JsonValue::String("tab\tquote\"\u{7}".to_string());
+        assert_eq!(parse_json(&round_trip.to_string()).unwrap(), round_trip);
+    }
+
     #[test]
     fn test_parse_number() {
         let json = "123.45";
@@ -230,6 +230,64 @@ pub fn get_mime_type_from_path(path: &str) -> String {
         .map_or("application/octet-stream".to_string(), get_mime_type)
 }
 
+/// MIME types whose payloads are already compressed and gain nothing from
+/// being compressed again.
+const COMPRESSED_MIME_TYPES: &[MimeType] = &[
+    MimeType::ApplicationPdf,
+    MimeType::ApplicationZip,
+    MimeType::ApplicationGzip,
+    MimeType::ApplicationXBzip2,
+    MimeType::ApplicationX7zCompressed,
+    MimeType::ApplicationVndRar,
+    MimeType::ApplicationVndOasisOdt,
+    MimeType::ApplicationVndOasisOds,
+    MimeType::ApplicationVndOasisOdp,
+    MimeType::ApplicationVndOpenxmlWord,
+    MimeType::ApplicationVndOpenxmlExcel,
+    MimeType::ApplicationVndOpenxmlPowerpoint,
+    MimeType::ImagePng,
+    MimeType::ImageJpeg,
+    MimeType::ImageGif,
+    MimeType::ImageWebp,
+];
+
+/// Returns `true` if the given MIME type is already compressed.
+///
+/// Parameters such as `; charset=utf-8` are ignored. Audio and video types
+/// are always treated as compressed, as are archive, office and most image
+/// formats. Text formats, `image/svg+xml` and `application/wasm` are not.
+///
+/// # Arguments
+///
+/// * `mime_type` - A string slice that holds the MIME type.
+///
+/// # Examples
+///
+/// ```
+/// use suika_mime::is_compressed_mime_type;
+///
+/// assert!(is_compressed_mime_type("image/png"));
+/// assert!(is_compressed_mime_type("video/mp4"));
+/// assert!(!is_compressed_mime_type("text/html; charset=utf-8"));
+/// assert!(!is_compressed_mime_type("image/svg+xml"));
+/// ```
+pub fn is_compressed_mime_type(mime_type: &str) -> bool {
+    let essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+
+    if essence.starts_with("audio/") || essence.starts_with("video/") {
+        return true;
+    }
+
+    COMPRESSED_MIME_TYPES
+        .iter()
+        .any(|mime| mime.as_str() == essence)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -308,4 +366,20 @@ mod tests {
         assert_eq!(get_mime_type_from_path("video.mp4"), "video/mp4".to_string());
         assert_eq!(get_mime_type_from_path("module.wasm"), "application/wasm".to_string()); // Test for WebAssembly MIME type from path
     }
+
+    #[test]
+    fn test_is_compressed_mime_type() {
+        assert!(is_compressed_mime_type("image/png"));
+        assert!(is_compressed_mime_type("image/jpeg"));
+        assert!(is_compressed_mime_type("application/gzip"));
+        assert!(is_compressed_mime_type("application/zip"));
+        assert!(is_compressed_mime_type("audio/mpeg"));
+        assert!(is_compressed_mime_type("video/webm"));
+        assert!(is_compressed_mime_type("IMAGE/WEBP"));
+        assert!(!is_compressed_mime_type("text/html"));
+        assert!(!is_compressed_mime_type("text/plain; charset=utf-8"));
+        assert!(!is_compressed_mime_type("application/json"));
+        assert!(!is_compressed_mime_type("application/wasm"));
+        assert!(!is_compressed_mime_type("image/svg+xml"));
+    }
 }
@@ -0,0 +1,110 @@
+//! Compares the radix tree used by `Router` with a linear scan over anchored
+//! regexes, the way routes were matched before the tree existed.
+//!
+//! Run with `cargo bench -p suika_server --bench router`.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use suika_server::path_pattern::PathPattern;
+use suika_server::router::Router;
+
+const ITERATIONS: u32 = 20_000;
+
+struct LinearRouter {
+    routes: Vec<(String, Regex)>,
+}
+
+impl LinearRouter {
+    fn find(&self, method: &str, path: &str) -> Option<HashMap<String, String>> {
+        for (route_method, pattern) in &self.routes {
+            if route_method != method {
+                continue;
+            }
+            if let Some(caps) = pattern.captures(path) {
+                return Some(
+                    pattern
+                        .capture_names()
+                        .flatten()
+                        .filter_map(|name| {
+                            caps.name(name)
+                                .map(|value| (name.to_string(), value.as_str().to_string()))
+                        })
+                        .collect(),
+                );
+            }
+        }
+        None
+    }
+}
+
+fn route_table(resources: usize) -> Vec<(&'static str, String)> {
+    let mut routes = Vec::new();
+    for i in 0..resources {
+        routes.push(("GET", format!("/api/v1/resource{}", i)));
+        routes.push(("POST", format!("/api/v1/resource{}", i)));
+        routes.push(("GET", format!("/api/v1/resource{}/search", i)));
+        routes.push(("GET", format!(r"/api/v1/resource{}/:id(\d+)", i)));
+        routes.push(("PUT", format!(r"/api/v1/resource{}/:id(\d+)", i)));
+        routes.push(("GET", format!("/api/v1/resource{}/:id/items/:item_id", i)));
+        routes.push(("GET", format!("/api/v1/resource{}/files/*path", i)));
+    }
+    routes
+}
+
+fn requests(resources: usize) -> Vec<(&'static str, String)> {
+    let last = resources - 1;
+    let middle = resources / 2;
+    vec![
+        ("GET", "/api/v1/resource0".to_string()),
+        ("GET", format!("/api/v1/resource{}/search", middle)),
+        ("PUT", format!("/api/v1/resource{}/42", middle)),
+        ("GET", format!("/api/v1/resource{}/42/items/7", last)),
+        ("GET", format!("/api/v1/resource{}/files/a/b/c.txt", last)),
+        ("GET", "/api/v1/missing".to_string()),
+    ]
+}
+
+fn measure(mut lookup: impl FnMut()) -> Duration {
+    for _ in 0..ITERATIONS / 10 {
+        lookup();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        lookup();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    for resources in [10, 50, 100] {
+        let table = route_table(resources);
+
+        let mut router = Router::new("/");
+        let mut linear = LinearRouter { routes: Vec::new() };
+        for (method, pattern) in &table {
+            router.route(Some(method), pattern, |_req| {
+                Box::pin(async move { Ok("") })
+            });
+            linear.routes.push((
+                method.to_string(),
+                PathPattern::parse(pattern).unwrap().to_regex(),
+            ));
+        }
+
+        println!("{} routes", table.len());
+        for (method, path) in requests(resources) {
+            let radix = measure(|| {
+                black_box(router.find(black_box(method), black_box(&path)).is_some());
+            });
+            let scan = measure(|| {
+                black_box(linear.find(black_box(method), black_box(&path)).is_some());
+            });
+            println!(
+                "  {:<4} {:<42} radix {:>9.2?}  linear {:>9.2?}",
+                method, path, radix, scan
+            );
+        }
+    }
+}
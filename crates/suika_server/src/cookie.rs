@@ -0,0 +1,171 @@
+use std::fmt;
+use std::time::Duration;
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    /// Sent only with same-site requests.
+    Strict,
+    /// Sent with same-site requests and top-level cross-site navigations.
+    Lax,
+    /// Sent with all requests; requires `Secure`.
+    None,
+}
+
+impl SameSite {
+    /// Returns the attribute value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// A cookie set by a response with `Response::set_cookie`.
+///
+/// Request cookies are read with `Request::cookie`.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::cookie::{Cookie, SameSite};
+/// use std::time::Duration;
+///
+/// let cookie = Cookie::new("theme", "dark")
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .same_site(SameSite::Lax)
+///     .http_only(true);
+///
+/// assert_eq!(
+///     cookie.to_string(),
+///     "theme=dark; Path=/; Max-Age=3600; HttpOnly; SameSite=Lax"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Creates a session cookie.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the cookie.
+    /// * `value` - The value of the cookie, which must not contain
+    ///   whitespace, quotes, commas, semicolons or backslashes.
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// Sets the path the cookie is sent for.
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Sets the domain the cookie is sent to.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Sets how long the cookie is kept. A zero duration removes it.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Sets whether the cookie is only sent over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Sets whether the cookie is hidden from scripts.
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Sets the `SameSite` attribute.
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    /// Returns the name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for Cookie {
+    /// Formats the cookie as a `Set-Cookie` header value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_display() {
+        assert_eq!(Cookie::new("a", "b").to_string(), "a=b");
+        assert_eq!(
+            Cookie::new("__Host-id", "xyz")
+                .path("/")
+                .domain("example.com")
+                .max_age(Duration::ZERO)
+                .secure(true)
+                .same_site(SameSite::None)
+                .to_string(),
+            "__Host-id=xyz; Path=/; Domain=example.com; Max-Age=0; Secure; SameSite=None"
+        );
+    }
+}
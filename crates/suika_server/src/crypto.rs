@@ -0,0 +1,30 @@
+//! Cryptographic helpers shared by the security middleware.
+
+/// Compares two byte strings in time independent of their contents.
+///
+/// Only the lengths of `a` and `b` can be learned from the time taken, so
+/// secrets such as passwords and tokens can be compared without leaking
+/// how many leading bytes an attacker guessed right.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let mut difference = a.len() ^ b.len();
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        difference |= (x ^ y) as usize;
+    }
+    difference == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"\0"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt;
 
 /// Represents various HTTP errors that can occur.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum HttpError {
     /// Bad Request (400) error with a specific message.
     BadRequest(String),
@@ -11,8 +11,16 @@ pub enum HttpError {
     Forbidden(String),
     /// Not Found (404) error with a specific message.
     NotFound(String),
+    /// Method Not Allowed (405) error with a specific message.
+    MethodNotAllowed(String),
+    /// Too Many Requests (429) error with a specific message.
+    TooManyRequests(String),
     /// Internal Server Error (500) with a specific message.
     InternalServerError(String),
+    /// Service Unavailable (503) error with a specific message.
+    ServiceUnavailable(String),
+    /// Gateway Timeout (504) error with a specific message.
+    GatewayTimeout(String),
 }
 
 impl fmt::Display for HttpError {
@@ -35,7 +43,11 @@ impl fmt::Display for HttpError {
             HttpError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
             HttpError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
             HttpError::NotFound(msg) => write!(f, "Not Found: {}", msg),
+            HttpError::MethodNotAllowed(msg) => write!(f, "Method Not Allowed: {}", msg),
+            HttpError::TooManyRequests(msg) => write!(f, "Too Many Requests: {}", msg),
             HttpError::InternalServerError(msg) => write!(f, "Internal Server Error: {}", msg),
+            HttpError::ServiceUnavailable(msg) => write!(f, "Service Unavailable: {}", msg),
+            HttpError::GatewayTimeout(msg) => write!(f, "Gateway Timeout: {}", msg),
         }
     }
 }
@@ -62,7 +74,11 @@ impl HttpError {
             HttpError::Unauthorized(msg) => (401, msg),
             HttpError::Forbidden(msg) => (403, msg),
             HttpError::NotFound(msg) => (404, msg),
+            HttpError::MethodNotAllowed(msg) => (405, msg),
+            HttpError::TooManyRequests(msg) => (429, msg),
             HttpError::InternalServerError(msg) => (500, msg),
+            HttpError::ServiceUnavailable(msg) => (503, msg),
+            HttpError::GatewayTimeout(msg) => (504, msg),
         }
     }
 }
@@ -77,17 +93,34 @@ mod tests {
         let unauthorized = HttpError::Unauthorized("No token".to_string());
         let forbidden = HttpError::Forbidden("Access denied".to_string());
         let not_found = HttpError::NotFound("Resource missing".to_string());
+        let method_not_allowed = HttpError::MethodNotAllowed("Use GET".to_string());
+        let too_many_requests = HttpError::TooManyRequests("Slow down".to_string());
         let internal_server_error =
             HttpError::InternalServerError("Server malfunction".to_string());
+        let service_unavailable = HttpError::ServiceUnavailable("Overloaded".to_string());
+        let gateway_timeout = HttpError::GatewayTimeout("Too slow".to_string());
 
         assert_eq!(format!("{}", bad_request), "Bad Request: Invalid data");
         assert_eq!(format!("{}", unauthorized), "Unauthorized: No token");
         assert_eq!(format!("{}", forbidden), "Forbidden: Access denied");
         assert_eq!(format!("{}", not_found), "Not Found: Resource missing");
+        assert_eq!(
+            format!("{}", method_not_allowed),
+            "Method Not Allowed: Use GET"
+        );
+        assert_eq!(
+            format!("{}", too_many_requests),
+            "Too Many Requests: Slow down"
+        );
         assert_eq!(
             format!("{}", internal_server_error),
             "Internal Server Error: Server malfunction"
         );
+        assert_eq!(
+            format!("{}", service_unavailable),
+            "Service Unavailable: Overloaded"
+        );
+        assert_eq!(format!("{}", gateway_timeout), "Gateway Timeout: Too slow");
     }
 
     #[test]
@@ -96,16 +129,24 @@ mod tests {
         let unauthorized = HttpError::Unauthorized("No token".to_string());
         let forbidden = HttpError::Forbidden("Access denied".to_string());
         let not_found = HttpError::NotFound("Resource missing".to_string());
+        let method_not_allowed = HttpError::MethodNotAllowed("Use GET".to_string());
+        let too_many_requests = HttpError::TooManyRequests("Slow down".to_string());
         let internal_server_error =
             HttpError::InternalServerError("Server malfunction".to_string());
+        let service_unavailable = HttpError::ServiceUnavailable("Overloaded".to_string());
+        let gateway_timeout = HttpError::GatewayTimeout("Too slow".to_string());
 
         assert_eq!(bad_request.to_tuple(), (400, "Invalid data"));
         assert_eq!(unauthorized.to_tuple(), (401, "No token"));
         assert_eq!(forbidden.to_tuple(), (403, "Access denied"));
         assert_eq!(not_found.to_tuple(), (404, "Resource missing"));
+        assert_eq!(method_not_allowed.to_tuple(), (405, "Use GET"));
+        assert_eq!(too_many_requests.to_tuple(), (429, "Slow down"));
         assert_eq!(
             internal_server_error.to_tuple(),
             (500, "Server malfunction")
         );
+        assert_eq!(service_unavailable.to_tuple(), (503, "Overloaded"));
+        assert_eq!(gateway_timeout.to_tuple(), (504, "Too slow"));
     }
 }
@@ -0,0 +1,256 @@
+use crate::error::HttpError;
+use crate::request::Request;
+use crate::response::Response;
+use std::collections::HashMap;
+use std::sync::Arc;
+use suika_json::JsonValue;
+use suika_templates::template_value::TemplateValue;
+
+/// A hook called by the server with the error of a failed request.
+///
+/// The response already carries the error's status code and a plain text
+/// body, which the hook can replace, e.g. with an error page.
+pub type ErrorHandler = Arc<dyn Fn(&HttpError, &Request, &mut Response) + Send + Sync>;
+
+/// The representation of an error page preferred by a client.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    Html,
+    Json,
+    Text,
+}
+
+/// Renders error responses as HTML, JSON or plain text depending on the
+/// request's `Accept` header.
+///
+/// Clients accepting `application/json` over `text/html` get a JSON body of
+/// the form `{"status":404,"error":"Not Found"}`. Clients accepting
+/// `text/html` get the configured template, rendered with the `status`,
+/// `message` and `path` variables, HTML-escaped since the template engine
+/// does not escape values, and every other client gets the plain text
+/// message. If the template cannot be rendered, the plain text message
+/// is kept.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::error_handler::ErrorPages;
+/// use suika_server::server::Server;
+///
+/// let pages = ErrorPages::new().template("error.html");
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_error_handler(move |error, req, res| pages.render(error, req, res));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ErrorPages {
+    template: Option<String>,
+}
+
+impl ErrorPages {
+    /// Creates new `ErrorPages` without a template.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the template rendered for clients accepting HTML.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template registered with the template engine.
+    pub fn template(mut self, name: &str) -> Self {
+        self.template = Some(name.to_string());
+        self
+    }
+
+    /// Writes the error page for an error into the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error the request failed with.
+    /// * `req` - The failed request.
+    /// * `res` - The response to write the error page into.
+    pub fn render(&self, error: &HttpError, req: &Request, res: &mut Response) {
+        let (status_code, message) = error.to_tuple();
+        res.set_status(status_code);
+
+        match preferred_format(req) {
+            ErrorFormat::Json => {
+                res.body_json(JsonValue::Object(vec![
+                    ("status".to_string(), JsonValue::Number(status_code as f64)),
+                    ("error".to_string(), JsonValue::String(message.to_string())),
+                ]));
+            }
+            ErrorFormat::Html if self.template.is_some() => {
+                let template = self.template.as_deref().unwrap_or_default();
+                let context = HashMap::from([
+                    (
+                        "status".to_string(),
+                        TemplateValue::String(status_code.to_string()),
+                    ),
+                    (
+                        "message".to_string(),
+                        TemplateValue::String(escape_html(message)),
+                    ),
+                    (
+                        "path".to_string(),
+                        TemplateValue::String(escape_html(req.path())),
+                    ),
+                ]);
+                if res.render_template(template, &context).is_ok() {
+                    res.header("Content-Type", "text/html; charset=utf-8");
+                } else {
+                    res.text(message.to_string());
+                }
+            }
+            _ => {
+                res.text(message.to_string());
+            }
+        }
+    }
+}
+
+/// Escapes the characters with a special meaning in HTML text and attribute
+/// values.
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Picks the error format from the quality values the `Accept` header gives
+/// to `text/html` and `application/json`.
+fn preferred_format(req: &Request) -> ErrorFormat {
+    let accept = req.get_header("Accept").unwrap_or_default();
+
+    let mut html = None;
+    let mut json = None;
+    for range in accept.split(',') {
+        let mut parts = range.split(';');
+        let media_type = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        let slot = match media_type.as_str() {
+            "text/html" => &mut html,
+            "application/json" => &mut json,
+            _ => continue,
+        };
+        *slot = Some(slot.map_or(quality, |q: f32| q.max(quality)));
+    }
+
+    match (html, json) {
+        (_, Some(json)) if json > 0.0 && json > html.unwrap_or(0.0) => ErrorFormat::Json,
+        (Some(html), _) if html > 0.0 => ErrorFormat::Html,
+        _ => ErrorFormat::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Body;
+    use std::sync::Mutex;
+    use suika_templates::TemplateEngine;
+
+    fn request(accept: Option<&str>) -> Request {
+        let header = accept
+            .map(|value| format!("Accept: {}\r\n", value))
+            .unwrap_or_default();
+        Request::new(
+            &format!("GET /missing HTTP/1.1\r\n{}\r\n", header),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_preferred_format() {
+        let cases = [
+            (None, ErrorFormat::Text),
+            (Some("*/*"), ErrorFormat::Text),
+            (
+                Some("text/html,application/xhtml+xml,*/*;q=0.8"),
+                ErrorFormat::Html,
+            ),
+            (Some("application/json"), ErrorFormat::Json),
+            (Some("text/html;q=0.5, application/json"), ErrorFormat::Json),
+            (Some("text/html, application/json;q=0.9"), ErrorFormat::Html),
+            (Some("application/json;q=0"), ErrorFormat::Text),
+        ];
+        for (accept, format) in cases {
+            assert_eq!(preferred_format(&request(accept)), format, "{:?}", accept);
+        }
+    }
+
+    #[test]
+    fn test_error_pages_render() {
+        let mut engine = TemplateEngine::new();
+        engine.add_template("error.html", "<h1><%= status %></h1><p><%= message %></p>");
+        let pages = ErrorPages::new().template("error.html");
+        let error = HttpError::NotFound("Not Found".to_string());
+
+        let mut res = Response::new(Some(Arc::new(engine)));
+        res.error(error.clone());
+        pages.render(&error, &request(Some("text/html")), &mut res);
+        assert_eq!(res.status(), Some(404));
+        assert_eq!(
+            res.get_header("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text("<h1>404</h1><p>Not Found</p>".to_string()))
+        );
+
+        let mut res = Response::default();
+        pages.render(&error, &request(Some("application/json")), &mut res);
+        assert_eq!(res.get_header("Content-Type"), Some("application/json"));
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text(
+                r#"{"status":404,"error":"Not Found"}"#.to_string()
+            ))
+        );
+
+        let mut res = Response::default();
+        pages.render(&error, &request(Some("text/html")), &mut res);
+        assert_eq!(res.status(), Some(404));
+        assert_eq!(res.get_body(), Some(&Body::Text("Not Found".to_string())));
+    }
+
+    #[test]
+    fn test_error_pages_escape_template_values() {
+        let mut engine = TemplateEngine::new();
+        engine.add_template("error.html", "<p><%= message %></p><p><%= path %></p>");
+        let pages = ErrorPages::new().template("error.html");
+        let error = HttpError::BadRequest("<script>alert('x')</script>".to_string());
+        let req = Request::new(
+            "GET /a\"<b>&c HTTP/1.1\r\nAccept: text/html\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+
+        let mut res = Response::new(Some(Arc::new(engine)));
+        pages.render(&error, &req, &mut res);
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text(
+                "<p>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;</p>\
+                 <p>/a&quot;&lt;b&gt;&amp;c</p>"
+                    .to_string()
+            ))
+        );
+    }
+}
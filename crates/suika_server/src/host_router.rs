@@ -0,0 +1,433 @@
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::path_pattern::PatternError;
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::Router;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A single `.`-separated label of a host pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostLabel {
+    /// A literal label, e.g. `example` in `api.example.com`.
+    Static(String),
+    /// A named parameter matching one label, e.g. `:tenant`.
+    Param(String),
+    /// A wildcard matching any one label, e.g. `*`.
+    Wildcard,
+}
+
+/// A parsed host pattern, such as `api.example.com`, `*.example.com` or
+/// `:tenant.example.com`.
+///
+/// Hosts are matched case-insensitively, label by label, and any port in the
+/// `Host` header is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::host_router::HostPattern;
+///
+/// let pattern = HostPattern::parse(":tenant.example.com").unwrap();
+/// let params = pattern.matches("Acme.Example.com").unwrap();
+/// assert_eq!(params.get("tenant"), Some(&"acme".to_string()));
+///
+/// assert!(pattern.matches("example.com").is_none());
+/// assert!(pattern.matches("a.b.example.com").is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostPattern {
+    source: String,
+    labels: Vec<HostLabel>,
+}
+
+impl HostPattern {
+    /// Parses a host pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to parse.
+    ///
+    /// # Returns
+    ///
+    /// The parsed `HostPattern`, or a `PatternError` describing why the
+    /// pattern is invalid.
+    pub fn parse(pattern: &str) -> Result<Self, PatternError> {
+        let source = pattern.to_ascii_lowercase();
+        let mut labels = Vec::new();
+        for label in source.split('.') {
+            let label = match label {
+                "" => return Err(PatternError::new(&source, "empty host label")),
+                "*" => HostLabel::Wildcard,
+                _ => match label.strip_prefix(':') {
+                    Some(name) => {
+                        if name.is_empty()
+                            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                        {
+                            return Err(PatternError::new(
+                                &source,
+                                format!("invalid parameter name {:?}", name),
+                            ));
+                        }
+                        if labels.contains(&HostLabel::Param(name.to_string())) {
+                            return Err(PatternError::new(
+                                &source,
+                                format!("duplicate parameter name {:?}", name),
+                            ));
+                        }
+                        HostLabel::Param(name.to_string())
+                    }
+                    None => HostLabel::Static(label.to_string()),
+                },
+            };
+            labels.push(label);
+        }
+        Ok(Self { source, labels })
+    }
+
+    /// Returns the pattern as it was parsed, lower-cased.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the labels of the pattern.
+    pub fn labels(&self) -> &[HostLabel] {
+        &self.labels
+    }
+
+    /// Returns `true` if the pattern only contains literal labels.
+    pub fn is_exact(&self) -> bool {
+        self.labels
+            .iter()
+            .all(|label| matches!(label, HostLabel::Static(_)))
+    }
+
+    /// Matches a host against the pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host, without a port.
+    ///
+    /// # Returns
+    ///
+    /// The captured, lower-cased parameters, or `None` if the host does not match.
+    pub fn matches(&self, host: &str) -> Option<HashMap<String, String>> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let parts: Vec<&str> = host.split('.').collect();
+        if parts.len() != self.labels.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (label, part) in self.labels.iter().zip(parts) {
+            match label {
+                _ if part.is_empty() => return None,
+                HostLabel::Static(text) if text != part => return None,
+                HostLabel::Param(name) => {
+                    params.insert(name.clone(), part.to_string());
+                }
+                _ => {}
+            }
+        }
+        Some(params)
+    }
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// A middleware dispatching requests to routers by the `Host` header.
+///
+/// Exact host names are checked first, then patterns with wildcards or
+/// parameters in registration order. Parameters captured from the host are
+/// available through `req.param`, alongside the path parameters of the
+/// matched route.
+///
+/// Requests whose host matches no pattern, or that have no `Host` header,
+/// go to the default router. When the selected router has no route for the
+/// request path, the fallback router is tried, and otherwise the request is
+/// passed to the next middleware.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::host_router::HostRouter;
+/// use suika_server::router::Router;
+///
+/// let mut api = Router::new("/");
+/// api.get("/status", |_req| Box::pin(async move { Ok("api") }));
+///
+/// let mut tenants = Router::new("/");
+/// tenants.get("/", |req| {
+///     Box::pin(async move { Ok(format!("Hello, {}", req.param("tenant").unwrap_or_default())) })
+/// });
+///
+/// let mut shared = Router::new("/");
+/// shared.get("/health", |_req| Box::pin(async move { Ok("ok") }));
+///
+/// let mut hosts = HostRouter::new();
+/// hosts
+///     .host("api.example.com", api)
+///     .host(":tenant.example.com", tenants)
+///     .fallback(shared);
+/// ```
+#[derive(Default)]
+pub struct HostRouter {
+    hosts: Vec<(HostPattern, Router)>,
+    default_router: Option<Router>,
+    fallback: Option<Router>,
+}
+
+impl HostRouter {
+    /// Creates a new `HostRouter` without any hosts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a router for the hosts matching a pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The host pattern, e.g. `api.example.com`,
+    ///   `*.example.com` or `:tenant.example.com`.
+    /// * `router` - The router handling requests for matching hosts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is not a valid `HostPattern`.
+    pub fn host(&mut self, pattern: &str, router: Router) -> &mut Self {
+        let pattern = HostPattern::parse(pattern).unwrap_or_else(|e| panic!("{}", e));
+        self.hosts.push((pattern, router));
+        self
+    }
+
+    /// Sets the router for requests whose host matches no pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `router` - The router handling requests for unknown hosts.
+    pub fn default_router(&mut self, router: Router) -> &mut Self {
+        self.default_router = Some(router);
+        self
+    }
+
+    /// Sets the router tried when the router selected by host has no route
+    /// for the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `router` - The router handling requests left over by every host.
+    pub fn fallback(&mut self, router: Router) -> &mut Self {
+        self.fallback = Some(router);
+        self
+    }
+
+    /// Selects the router for a host, with the parameters captured from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - The host, without a port, or `None` if it is unknown.
+    ///
+    /// # Returns
+    ///
+    /// The router registered for the host, or the default router, or `None`
+    /// if neither exists.
+    pub fn select(&self, host: Option<&str>) -> Option<(&Router, HashMap<String, String>)> {
+        let matched = host.and_then(|host| {
+            let exact = self.hosts.iter().filter(|(pattern, _)| pattern.is_exact());
+            let patterns = self.hosts.iter().filter(|(pattern, _)| !pattern.is_exact());
+            exact
+                .chain(patterns)
+                .find_map(|(pattern, router)| pattern.matches(host).map(|params| (router, params)))
+        });
+        matched.or_else(|| {
+            self.default_router
+                .as_ref()
+                .map(|router| (router, HashMap::new()))
+        })
+    }
+}
+
+impl Middleware for HostRouter {
+    /// Handles an incoming HTTP request by routing it to the router of its host.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            if let Some((router, host_params)) = self.select(req.host()) {
+                if router.handles(req.method(), req.path()) {
+                    let mut params = req.params().clone();
+                    params.extend(host_params);
+                    req.set_params(params);
+                    return router.handle(req, res, next).await;
+                }
+            }
+
+            match &self.fallback {
+                Some(fallback) => fallback.handle(req, res, next).await,
+                None => next.run(req, res).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Body;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::Mutex as TokioMutex;
+
+    #[derive(Clone)]
+    struct MockNextMiddleware {
+        called: Arc<TokioMutex<bool>>,
+    }
+
+    impl Middleware for MockNextMiddleware {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            _res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            let called = Arc::clone(&self.called);
+            Box::pin(async move {
+                *called.lock().await = true;
+                Ok(())
+            })
+        }
+    }
+
+    async fn dispatch(hosts: &HostRouter, host: Option<&str>, path: &str) -> (Response, bool) {
+        let host_header = host.map(|h| format!("Host: {}\r\n", h)).unwrap_or_default();
+        let mut req = Request::new(
+            &format!("GET {} HTTP/1.1\r\n{}\r\n", path, host_header),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+
+        let next_middleware = MockNextMiddleware {
+            called: Arc::new(TokioMutex::new(false)),
+        };
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(next_middleware.clone())];
+        let next = Next::new(middleware_stack.as_slice());
+
+        hosts.handle(&mut req, &mut res, next).await.unwrap();
+
+        let next_called = *next_middleware.called.lock().await;
+        (res, next_called)
+    }
+
+    fn text(value: &str) -> Option<Body> {
+        Some(Body::Text(value.to_string()))
+    }
+
+    fn router(body: &'static str) -> Router {
+        let mut router = Router::new("/");
+        router.get("/", move |_req| Box::pin(async move { Ok(body) }));
+        router
+    }
+
+    #[test]
+    fn test_host_patterns() {
+        let exact = HostPattern::parse("API.example.com").unwrap();
+        assert!(exact.is_exact());
+        assert_eq!(exact.as_str(), "api.example.com");
+        assert!(exact.matches("api.example.com").is_some());
+        assert!(exact.matches("api.example.com.").is_some());
+        assert!(exact.matches("admin.example.com").is_none());
+
+        let wildcard = HostPattern::parse("*.example.com").unwrap();
+        assert!(!wildcard.is_exact());
+        assert_eq!(wildcard.matches("shop.example.com"), Some(HashMap::new()));
+        assert!(wildcard.matches("example.com").is_none());
+        assert!(wildcard.matches(".example.com").is_none());
+
+        let param = HostPattern::parse(":tenant.:region.example.com").unwrap();
+        let params = param.matches("acme.eu.example.com").unwrap();
+        assert_eq!(params.get("tenant"), Some(&"acme".to_string()));
+        assert_eq!(params.get("region"), Some(&"eu".to_string()));
+
+        assert!(HostPattern::parse("api..example.com").is_err());
+        assert!(HostPattern::parse(":.example.com").is_err());
+        assert!(HostPattern::parse(":a.:a.example.com").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_host_router_dispatches_by_host() {
+        let mut tenants = Router::new("/");
+        tenants.get("/users/:id", |req| {
+            Box::pin(async move {
+                Ok(format!(
+                    "{}:{}",
+                    req.param("tenant").unwrap_or_default(),
+                    req.param("id").unwrap_or_default()
+                ))
+            })
+        });
+
+        let mut hosts = HostRouter::new();
+        hosts
+            .host(":tenant.example.com", tenants)
+            .host("*.example.org", router("org"))
+            .host("api.example.com", router("api"));
+
+        let (res, _) = dispatch(&hosts, Some("api.example.com:8080"), "/").await;
+        assert_eq!(res.get_body().cloned(), text("api"));
+
+        let (res, _) = dispatch(&hosts, Some("acme.example.com"), "/users/7").await;
+        assert_eq!(res.get_body().cloned(), text("acme:7"));
+
+        let (res, _) = dispatch(&hosts, Some("www.example.org"), "/").await;
+        assert_eq!(res.get_body().cloned(), text("org"));
+
+        let (_, next_called) = dispatch(&hosts, Some("example.net"), "/").await;
+        assert!(next_called);
+
+        let (_, next_called) = dispatch(&hosts, None, "/").await;
+        assert!(next_called);
+    }
+
+    #[tokio::test]
+    async fn test_host_router_default_and_fallback() {
+        let mut shared = Router::new("/");
+        shared.get("/health", |_req| Box::pin(async move { Ok("ok") }));
+
+        let mut hosts = HostRouter::new();
+        hosts
+            .host("api.example.com", router("api"))
+            .default_router(router("default"))
+            .fallback(shared);
+
+        let (res, _) = dispatch(&hosts, Some("unknown.example.com"), "/").await;
+        assert_eq!(res.get_body().cloned(), text("default"));
+
+        let (res, _) = dispatch(&hosts, None, "/").await;
+        assert_eq!(res.get_body().cloned(), text("default"));
+
+        let (res, _) = dispatch(&hosts, Some("api.example.com"), "/health").await;
+        assert_eq!(res.get_body().cloned(), text("ok"));
+
+        let (res, next_called) = dispatch(&hosts, Some("api.example.com"), "/missing").await;
+        assert!(next_called);
+        assert_eq!(res.get_body(), None);
+    }
+}
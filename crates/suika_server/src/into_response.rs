@@ -0,0 +1,253 @@
+use crate::error::HttpError;
+use crate::response::{Response, StatusCode};
+use std::collections::HashMap;
+use suika_json::JsonValue;
+use suika_templates::template_value::TemplateValue;
+
+/// Converts a value returned by a handler into the response sent to the client.
+///
+/// The value is written into the response being built for the request, so
+/// conversions have access to its template engine. Values that only set a
+/// body leave an already chosen status code in place and default it to 200
+/// otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::into_response::IntoResponse;
+/// use suika_server::response::{Body, Response};
+///
+/// let mut res = Response::default();
+/// (201, "Created").into_response(&mut res).unwrap();
+///
+/// assert_eq!(res.status(), Some(201));
+/// assert_eq!(res.get_body(), Some(&Body::Text("Created".to_string())));
+/// ```
+pub trait IntoResponse {
+    /// Writes the value into the given response.
+    ///
+    /// # Arguments
+    ///
+    /// * `res` - A mutable reference to the response to be sent.
+    ///
+    /// # Returns
+    ///
+    /// A `Result<(), HttpError>` which is an error if the value could not be
+    /// converted, e.g. when a template fails to render.
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError>;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.replace_with(self);
+        Ok(())
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.text(self);
+        Ok(())
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.text(self.to_string());
+        Ok(())
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.set_status(res.status().unwrap_or(200))
+            .header("Content-Type", "application/octet-stream")
+            .body_bytes(self);
+        Ok(())
+    }
+}
+
+impl IntoResponse for JsonValue {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.set_status(res.status().unwrap_or(200)).body_json(self);
+        Ok(())
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.error(self);
+        Ok(())
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (StatusCode, T) {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        let (status_code, value) = self;
+        value.into_response(res)?;
+        res.set_status(status_code);
+        Ok(())
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        match self {
+            Ok(value) => value.into_response(res),
+            Err(error) => error.into_response(res),
+        }
+    }
+}
+
+/// A template rendered with the response's template engine when it is
+/// converted into a response.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::into_response::Template;
+/// use suika_templates::template_value::TemplateValue;
+///
+/// let template = Template::new("hello.html")
+///     .with("name", TemplateValue::String("World".to_string()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Template {
+    name: String,
+    context: HashMap<String, TemplateValue>,
+}
+
+impl Template {
+    /// Creates a new `Template` with an empty context.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template registered with the template engine.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            context: HashMap::new(),
+        }
+    }
+
+    /// Creates a new `Template` with the given context.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the template registered with the template engine.
+    /// * `context` - The values available to the template.
+    pub fn with_context(name: &str, context: HashMap<String, TemplateValue>) -> Self {
+        Self {
+            name: name.to_string(),
+            context,
+        }
+    }
+
+    /// Adds a value to the template context.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The name of the value in the template.
+    /// * `value` - The value itself.
+    pub fn with(mut self, key: &str, value: TemplateValue) -> Self {
+        self.context.insert(key.to_string(), value);
+        self
+    }
+}
+
+impl IntoResponse for Template {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        res.render_template(&self.name, &self.context)?;
+        res.set_status(res.status().unwrap_or(200))
+            .header("Content-Type", "text/html; charset=utf-8");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Body;
+    use std::sync::Arc;
+    use suika_templates::template_engine::TemplateEngine;
+
+    #[test]
+    fn test_strings_into_response() {
+        let mut res = Response::default();
+        "Hello".into_response(&mut res).unwrap();
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(
+            res.get_header("Content-Type"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(res.get_body(), Some(&Body::Text("Hello".to_string())));
+
+        let mut res = Response::default();
+        String::from("World").into_response(&mut res).unwrap();
+        assert_eq!(res.get_body(), Some(&Body::Text("World".to_string())));
+    }
+
+    #[test]
+    fn test_bytes_and_json_into_response() {
+        let mut res = Response::default();
+        vec![1u8, 2, 3].into_response(&mut res).unwrap();
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(
+            res.get_header("Content-Type"),
+            Some("application/octet-stream")
+        );
+        assert_eq!(res.get_body(), Some(&Body::Binary(vec![1, 2, 3])));
+
+        let json = JsonValue::Object(vec![("ok".to_string(), JsonValue::Boolean(true))]);
+        let mut res = Response::default();
+        json.clone().into_response(&mut res).unwrap();
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(res.get_header("Content-Type"), Some("application/json"));
+        assert_eq!(res.get_body(), Some(&Body::Text(json.to_string())));
+    }
+
+    #[test]
+    fn test_status_tuple_and_result_into_response() {
+        let mut res = Response::default();
+        (202, "Accepted").into_response(&mut res).unwrap();
+        assert_eq!(res.status(), Some(202));
+
+        let mut res = Response::default();
+        let result: Result<&'static str, HttpError> = Err(HttpError::Forbidden("Nope".to_string()));
+        result.into_response(&mut res).unwrap();
+        assert_eq!(res.status(), Some(403));
+        assert_eq!(res.get_body(), Some(&Body::Text("Nope".to_string())));
+
+        let mut res = Response::default();
+        let result: Result<(StatusCode, String), (StatusCode, &'static str)> =
+            Err((422, "Invalid"));
+        result.into_response(&mut res).unwrap();
+        assert_eq!(res.status(), Some(422));
+        assert_eq!(res.get_body(), Some(&Body::Text("Invalid".to_string())));
+    }
+
+    #[test]
+    fn test_template_into_response() {
+        let mut template_engine = TemplateEngine::new();
+        template_engine.add_template("hello.html", "<p>Hello, <%= name %>!</p>");
+
+        let mut res = Response::new(Some(Arc::new(template_engine)));
+        Template::new("hello.html")
+            .with("name", TemplateValue::String("World".to_string()))
+            .into_response(&mut res)
+            .unwrap();
+
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(
+            res.get_header("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text("<p>Hello, World!</p>".to_string()))
+        );
+
+        let mut res = Response::default();
+        assert!(Template::new("hello.html").into_response(&mut res).is_err());
+    }
+}
@@ -1,7 +1,26 @@
+pub mod cookie;
+mod crypto;
+mod diagnostics;
 pub mod error;
+pub mod error_handler;
+pub mod host_router;
+pub mod into_response;
+pub mod jwt;
 pub mod middleware;
+pub mod normalize;
+pub mod path_pattern;
+mod random;
 pub mod request;
+pub mod resource;
 pub mod response;
+pub mod route_table;
+mod route_tree;
 pub mod router;
 pub mod server;
-pub use middleware::{CorsMiddleware, FaviconMiddleware, LoggerMiddleware, StaticFileMiddleware};
+pub mod trace_context;
+pub use middleware::{
+    AccessLogMiddleware, ApiKeyMiddleware, BasicAuthMiddleware, BearerAuthMiddleware,
+    CatchPanicMiddleware, CompressionMiddleware, CorsMiddleware, CsrfMiddleware, FaviconMiddleware,
+    JwtMiddleware, LoggerMiddleware, RateLimitMiddleware, RequestIdMiddleware,
+    SecurityHeadersMiddleware, StaticFileMiddleware, TimeoutMiddleware,
+};
@@ -0,0 +1,882 @@
+use crate::diagnostics::error;
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::request::Request;
+use crate::response::{Body, Response};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use suika_json::JsonValue;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// The format access log lines are written in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessLogFormat {
+    /// The Common Log Format:
+    /// `127.0.0.1 - - [10/Oct/2024:13:55:36 +0000] "GET /a HTTP/1.1" 200 2326`.
+    Common,
+    /// The Common Log Format followed by the quoted `Referer` and
+    /// `User-Agent` headers.
+    Combined,
+    /// One JSON object per line.
+    Json,
+    /// A format string with `{name}` placeholders for the entry's fields:
+    /// `remote_addr`, `time`, `method`, `path`, `query`, `version`, `status`,
+    /// `bytes`, `latency_ms`, `latency_us`, `referer`, `user_agent` and
+    /// `request_id`.
+    /// Unknown placeholders are kept as written.
+    Custom(String),
+}
+
+/// A destination for access log lines.
+///
+/// Closures taking a line implement `LogSink`, so logs can be collected
+/// anywhere.
+pub trait LogSink: Send + Sync {
+    /// Writes one log line, without a trailing newline.
+    fn write_line(&self, line: &str);
+}
+
+impl<F> LogSink for F
+where
+    F: Fn(&str) + Send + Sync,
+{
+    fn write_line(&self, line: &str) {
+        self(line)
+    }
+}
+
+/// Writes log lines to standard output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSink;
+
+impl LogSink for StdoutSink {
+    fn write_line(&self, line: &str) {
+        println!("{}", line);
+    }
+}
+
+/// Sends log lines over a channel, e.g. to a task shipping them elsewhere.
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    sender: UnboundedSender<String>,
+}
+
+impl ChannelSink {
+    /// Creates a new `ChannelSink` sending lines to `sender`. Lines are
+    /// dropped once the receiver is closed.
+    pub fn new(sender: UnboundedSender<String>) -> Self {
+        Self { sender }
+    }
+}
+
+impl LogSink for ChannelSink {
+    fn write_line(&self, line: &str) {
+        let _ = self.sender.send(line.to_string());
+    }
+}
+
+/// Appends log lines to a file, optionally rotating it by size.
+///
+/// Lines are written by a background thread, so logging never blocks the
+/// server on disk I/O; `flush` waits for the lines sent so far. Lines still
+/// queued when the sink is dropped are written before the thread exits.
+///
+/// Rotated files are renamed with a numeric suffix, `access.log.1` being the
+/// most recent, and the oldest file is deleted once `max_files` are kept.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::access_log::FileSink;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let sink = FileSink::new(dir.path().join("access.log"))
+///     .unwrap()
+///     .rotate(10 * 1024 * 1024, 5);
+/// ```
+#[derive(Debug)]
+pub struct FileSink {
+    path: PathBuf,
+    sender: mpsc::Sender<FileCommand>,
+}
+
+/// A message to the thread writing a `FileSink`'s file.
+#[derive(Debug)]
+enum FileCommand {
+    Line(String),
+    Rotate { max_bytes: u64, max_files: usize },
+    Flush(mpsc::Sender<()>),
+}
+
+impl FileSink {
+    /// Opens a file for appending log lines, creating it if needed, and
+    /// starts the thread writing to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or the thread cannot be
+    /// started.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        let writer = FileWriter {
+            path: path.clone(),
+            max_bytes: None,
+            max_files: 0,
+            file,
+            size,
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("suika-access-log".to_string())
+            .spawn(move || writer.run(receiver))?;
+        Ok(Self { path, sender })
+    }
+
+    /// Rotates the file before it grows past `max_bytes`, keeping up to
+    /// `max_files` rotated files.
+    pub fn rotate(self, max_bytes: u64, max_files: usize) -> Self {
+        self.send(FileCommand::Rotate {
+            max_bytes,
+            max_files,
+        });
+        self
+    }
+
+    /// Blocks until the lines written so far are in the file.
+    pub fn flush(&self) {
+        let (sender, receiver) = mpsc::channel();
+        self.send(FileCommand::Flush(sender));
+        let _ = receiver.recv();
+    }
+
+    fn send(&self, command: FileCommand) {
+        if self.sender.send(command).is_err() {
+            error!("The writer of {} has stopped", self.path.display());
+        }
+    }
+}
+
+impl LogSink for FileSink {
+    fn write_line(&self, line: &str) {
+        self.send(FileCommand::Line(line.to_string()));
+    }
+}
+
+/// The state of a `FileSink`'s file, owned by its writer thread.
+struct FileWriter {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl FileWriter {
+    fn run(mut self, receiver: mpsc::Receiver<FileCommand>) {
+        for command in receiver {
+            match command {
+                FileCommand::Line(line) => self.write_line(&line),
+                FileCommand::Rotate {
+                    max_bytes,
+                    max_files,
+                } => {
+                    self.max_bytes = Some(max_bytes);
+                    self.max_files = max_files;
+                }
+                FileCommand::Flush(done) => {
+                    if let Err(e) = self.file.flush() {
+                        error!("Failed to flush {}: {}", self.path.display(), e);
+                    }
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: &str) {
+        let len = line.len() as u64 + 1;
+
+        if let Some(max_bytes) = self.max_bytes {
+            if self.size > 0 && self.size + len > max_bytes {
+                match self.rotate_files() {
+                    Ok(rotated) => {
+                        self.file = rotated;
+                        self.size = 0;
+                    }
+                    Err(e) => error!("Failed to rotate {}: {}", self.path.display(), e),
+                }
+            }
+        }
+
+        match writeln!(self.file, "{}", line) {
+            Ok(()) => self.size += len,
+            Err(e) => error!("Failed to write to {}: {}", self.path.display(), e),
+        }
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate_files(&self) -> io::Result<File> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        open_append(&self.path)
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// A record of one handled request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLogEntry {
+    pub(crate) time: SystemTime,
+    pub(crate) remote_addr: Option<SocketAddr>,
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: Option<String>,
+    pub(crate) version: String,
+    pub(crate) status: u16,
+    pub(crate) bytes: Option<usize>,
+    pub(crate) latency: Duration,
+    pub(crate) referer: Option<String>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) request_id: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// Returns the time the request was received.
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Returns the address of the client, if known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Returns the request method.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the request path as sent by the client.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the raw query string, if any.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Returns the HTTP version of the request.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the response status code.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the size of the response body, or `None` for streamed bodies.
+    pub fn bytes(&self) -> Option<usize> {
+        self.bytes
+    }
+
+    /// Returns the time taken to handle the request.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Returns the `Referer` header of the request, if any.
+    pub fn referer(&self) -> Option<&str> {
+        self.referer.as_deref()
+    }
+
+    /// Returns the `User-Agent` header of the request, if any.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Returns the id of the request, set by `RequestIdMiddleware`.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Formats the entry as a log line.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format of the line.
+    pub fn format(&self, format: &AccessLogFormat) -> String {
+        match format {
+            AccessLogFormat::Common => self.common(),
+            AccessLogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                self.common(),
+                escape(self.referer().unwrap_or("-")),
+                escape(self.user_agent().unwrap_or("-"))
+            ),
+            AccessLogFormat::Json => self.to_json().to_string(),
+            AccessLogFormat::Custom(template) => self.custom(template),
+        }
+    }
+
+    fn target(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+
+    fn host(&self) -> String {
+        self.remote_addr
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn bytes_field(&self) -> String {
+        self.bytes
+            .map(|bytes| bytes.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    fn common(&self) -> String {
+        let request_line = format!("{} {} {}", self.method, self.target(), self.version);
+        format!(
+            "{} - - [{}] \"{}\" {} {}",
+            self.host(),
+            UtcTime::from(self.time).clf(),
+            escape(&request_line),
+            self.status,
+            self.bytes_field()
+        )
+    }
+
+    fn to_json(&self) -> JsonValue {
+        fn optional(value: Option<&str>) -> JsonValue {
+            value
+                .map(|value| JsonValue::String(value.to_string()))
+                .unwrap_or(JsonValue::Null)
+        }
+
+        JsonValue::Object(vec![
+            (
+                "time".to_string(),
+                JsonValue::String(UtcTime::from(self.time).rfc3339()),
+            ),
+            (
+                "remote_addr".to_string(),
+                optional(
+                    self.remote_addr
+                        .map(|addr| addr.ip().to_string())
+                        .as_deref(),
+                ),
+            ),
+            ("method".to_string(), JsonValue::String(self.method.clone())),
+            ("path".to_string(), JsonValue::String(self.path.clone())),
+            ("query".to_string(), optional(self.query())),
+            (
+                "version".to_string(),
+                JsonValue::String(self.version.clone()),
+            ),
+            ("status".to_string(), JsonValue::Number(self.status as f64)),
+            (
+                "bytes".to_string(),
+                self.bytes
+                    .map(|bytes| JsonValue::Number(bytes as f64))
+                    .unwrap_or(JsonValue::Null),
+            ),
+            (
+                "latency_ms".to_string(),
+                JsonValue::Number(self.latency_ms()),
+            ),
+            ("referer".to_string(), optional(self.referer())),
+            ("user_agent".to_string(), optional(self.user_agent())),
+            ("request_id".to_string(), optional(self.request_id())),
+        ])
+    }
+
+    fn latency_ms(&self) -> f64 {
+        (self.latency.as_secs_f64() * 1_000_000.0).round() / 1000.0
+    }
+
+    fn custom(&self, template: &str) -> String {
+        let mut line = String::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            line.push_str(&rest[..start]);
+            let placeholder = &rest[start..];
+            let Some(end) = placeholder.find('}') else {
+                line.push_str(placeholder);
+                return line;
+            };
+            let value = match &placeholder[1..end] {
+                "remote_addr" => self.host(),
+                "time" => UtcTime::from(self.time).rfc3339(),
+                "method" => self.method.clone(),
+                "path" => self.path.clone(),
+                "query" => self.query().unwrap_or("-").to_string(),
+                "version" => self.version.clone(),
+                "status" => self.status.to_string(),
+                "bytes" => self.bytes_field(),
+                "latency_ms" => self.latency_ms().to_string(),
+                "latency_us" => self.latency.as_micros().to_string(),
+                "referer" => self.referer().unwrap_or("-").to_string(),
+                "user_agent" => self.user_agent().unwrap_or("-").to_string(),
+                "request_id" => self.request_id().unwrap_or("-").to_string(),
+                _ => placeholder[..=end].to_string(),
+            };
+            line.push_str(&value);
+            rest = &placeholder[end + 1..];
+        }
+
+        line.push_str(rest);
+        line
+    }
+}
+
+/// Escapes a value for a quoted field of the Common or Combined Log Format
+/// as Apache does: `"` and `\` are backslash-escaped and bytes outside
+/// printable ASCII are written as `\xHH`, so clients cannot forge fields or
+/// split lines.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+/// A UTC calendar time, for formatting log timestamps.
+struct UtcTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+impl From<SystemTime> for UtcTime {
+    fn from(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let days = (secs / 86_400) as i64;
+        let secs_of_day = secs % 86_400;
+
+        // Converts days since the epoch to a civil date, see
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3600,
+            minute: secs_of_day % 3600 / 60,
+            second: secs_of_day % 60,
+        }
+    }
+}
+
+impl UtcTime {
+    const MONTHS: [&'static str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    /// Formats the time as in the Common Log Format, e.g.
+    /// `10/Oct/2024:13:55:36 +0000`.
+    fn clf(&self) -> String {
+        format!(
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            self.day,
+            Self::MONTHS[self.month as usize - 1],
+            self.year,
+            self.hour,
+            self.minute,
+            self.second
+        )
+    }
+
+    /// Formats the time as RFC 3339, e.g. `2024-10-10T13:55:36Z`.
+    fn rfc3339(&self) -> String {
+        format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// A middleware component writing an access log line for every request.
+///
+/// The line is written once the rest of the stack has handled the request,
+/// so it records the response's status and size and the time taken.
+/// Requests failing with an error are logged with the error's status, and
+/// requests left without a status with `404`, as the server answers them.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::access_log::{AccessLogFormat, AccessLogMiddleware};
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(
+///     AccessLogMiddleware::new()
+///         .format(AccessLogFormat::Custom("{method} {path} {status} {latency_ms}ms".to_string())),
+/// ));
+/// ```
+#[derive(Clone)]
+pub struct AccessLogMiddleware {
+    format: AccessLogFormat,
+    sink: Arc<dyn LogSink>,
+}
+
+impl Default for AccessLogMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessLogMiddleware {
+    /// Creates a new `AccessLogMiddleware` writing the Combined Log Format to
+    /// standard output.
+    pub fn new() -> Self {
+        Self {
+            format: AccessLogFormat::Combined,
+            sink: Arc::new(StdoutSink),
+        }
+    }
+
+    /// Sets the format of the log lines.
+    pub fn format(mut self, format: AccessLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets where the log lines are written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::access_log::{AccessLogMiddleware, ChannelSink};
+    ///
+    /// let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    /// let access_log = AccessLogMiddleware::new().sink(ChannelSink::new(sender));
+    /// ```
+    pub fn sink<S>(mut self, sink: S) -> Self
+    where
+        S: LogSink + 'static,
+    {
+        self.sink = Arc::new(sink);
+        self
+    }
+
+    /// Creates the entry of a request, to be completed with the response
+    /// that is sent.
+    fn entry(req: &Request, time: SystemTime) -> AccessLogEntry {
+        AccessLogEntry {
+            time,
+            remote_addr: req.remote_addr(),
+            method: req.method().to_string(),
+            path: req.original_path().to_string(),
+            query: req.query_string().map(str::to_string),
+            version: req.version().to_string(),
+            status: 0,
+            bytes: None,
+            latency: Duration::ZERO,
+            referer: req.get_header("Referer").map(str::to_string),
+            user_agent: req.get_header("User-Agent").map(str::to_string),
+            request_id: req.request_id().map(str::to_string),
+        }
+    }
+}
+
+fn body_size(body: Option<&Body>) -> Option<usize> {
+    match body {
+        None => Some(0),
+        Some(Body::Text(text)) => Some(text.len()),
+        Some(Body::Binary(bytes)) => Some(bytes.len()),
+        Some(Body::Stream(_)) => None,
+    }
+}
+
+impl Middleware for AccessLogMiddleware {
+    /// Handles an incoming HTTP request by running the rest of the stack and
+    /// logging the response once the server has finished it, so that the
+    /// status and size of not found and error responses are those sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let time = SystemTime::now();
+            let start = Instant::now();
+            let result = next.run(req, res).await;
+
+            let entry = Self::entry(req, time);
+            let format = self.format.clone();
+            let sink = Arc::clone(&self.sink);
+            res.on_finish(move |res| {
+                let mut entry = entry.clone();
+                entry.status = res.status().unwrap_or(200);
+                entry.bytes = body_size(res.get_body());
+                entry.latency = start.elapsed();
+                sink.write_line(&entry.format(&format));
+            });
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use crate::error::HttpError;
+    use crate::error_handler::ErrorHandler;
+    use crate::server::Server;
+
+    struct MockHandler {
+        result: Result<(), HttpError>,
+    }
+
+    impl Middleware for MockHandler {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                if self.result.is_ok() {
+                    res.set_status(201);
+                    res.body("created".to_string());
+                }
+                self.result.clone()
+            })
+        }
+    }
+
+    fn entry() -> AccessLogEntry {
+        AccessLogEntry {
+            time: UNIX_EPOCH + Duration::from_secs(1_728_568_536),
+            remote_addr: Some("10.0.0.1:5000".parse().unwrap()),
+            method: "GET".to_string(),
+            path: "/items".to_string(),
+            query: Some("page=2".to_string()),
+            version: "HTTP/1.1".to_string(),
+            status: 200,
+            bytes: Some(2326),
+            latency: Duration::from_micros(1500),
+            referer: None,
+            user_agent: Some("curl/8.0".to_string()),
+            request_id: None,
+        }
+    }
+
+    async fn run(
+        result: Option<Result<(), HttpError>>,
+        error_handler: Option<ErrorHandler>,
+    ) -> Vec<String> {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink_lines = Arc::clone(&lines);
+        let access_log = AccessLogMiddleware::new()
+            .format(AccessLogFormat::Custom(
+                "{method} {path} {status} {bytes}".to_string(),
+            ))
+            .sink(move |line: &str| sink_lines.lock().unwrap().push(line.to_string()));
+
+        let mut req = Request::new(
+            "POST /items HTTP/1.1\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+        let mut middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(access_log)];
+        if let Some(result) = result {
+            middleware_stack.push(Arc::new(MockHandler { result }));
+        }
+
+        Server::handle_request(
+            &middleware_stack,
+            error_handler.as_ref(),
+            &mut req,
+            &mut res,
+        )
+        .await;
+
+        let lines = lines.lock().unwrap().clone();
+        lines
+    }
+
+    #[test]
+    fn test_access_log_formats() {
+        let entry = entry();
+
+        assert_eq!(
+            entry.format(&AccessLogFormat::Common),
+            "10.0.0.1 - - [10/Oct/2024:13:55:36 +0000] \"GET /items?page=2 HTTP/1.1\" 200 2326"
+        );
+        assert_eq!(
+            entry.format(&AccessLogFormat::Combined),
+            "10.0.0.1 - - [10/Oct/2024:13:55:36 +0000] \"GET /items?page=2 HTTP/1.1\" 200 2326 \"-\" \"curl/8.0\""
+        );
+        assert_eq!(
+            entry.format(&AccessLogFormat::Json),
+            "{\"time\":\"2024-10-10T13:55:36Z\",\"remote_addr\":\"10.0.0.1\",\"method\":\"GET\",\"path\":\"/items\",\"query\":\"page=2\",\"version\":\"HTTP/1.1\",\"status\":200,\"bytes\":2326,\"latency_ms\":1.5,\"referer\":null,\"user_agent\":\"curl/8.0\",\"request_id\":null}"
+        );
+        assert_eq!(
+            entry.format(&AccessLogFormat::Custom(
+                "{remote_addr} {method} {path} {status} {latency_us}us {unknown} {".to_string()
+            )),
+            "10.0.0.1 GET /items 200 1500us {unknown} {"
+        );
+    }
+
+    #[test]
+    fn test_access_log_escapes_quoted_fields() {
+        let mut entry = entry();
+        entry.path = "/a\"b".to_string();
+        entry.query = None;
+        entry.referer = Some("https://example.com/\\".to_string());
+        entry.user_agent = Some("evil\" 200 1 \"-\"\nfake line \u{e9}\x7f".to_string());
+
+        assert_eq!(
+            entry.format(&AccessLogFormat::Combined),
+            "10.0.0.1 - - [10/Oct/2024:13:55:36 +0000] \"GET /a\\\"b HTTP/1.1\" 200 2326 \
+             \"https://example.com/\\\\\" \
+             \"evil\\\" 200 1 \\\"-\\\"\\x0afake line \\xc3\\xa9\\x7f\""
+        );
+    }
+
+    #[test]
+    fn test_utc_time() {
+        let time = UtcTime::from(UNIX_EPOCH + Duration::from_secs(951_782_400));
+        assert_eq!(time.rfc3339(), "2000-02-29T00:00:00Z");
+        assert_eq!(
+            UtcTime::from(UNIX_EPOCH).clf(),
+            "01/Jan/1970:00:00:00 +0000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_access_log_middleware_records_response() {
+        assert_eq!(run(Some(Ok(())), None).await, vec!["POST /items 201 7"]);
+        assert_eq!(
+            run(Some(Err(HttpError::Forbidden("Nope".to_string()))), None).await,
+            vec!["POST /items 403 4"]
+        );
+        assert_eq!(run(None, None).await, vec!["POST /items 404 9"]);
+    }
+
+    #[tokio::test]
+    async fn test_access_log_middleware_records_error_handler_body() {
+        let error_handler: ErrorHandler = Arc::new(|_error, _req, res| {
+            res.body("Custom error page".to_string());
+        });
+
+        assert_eq!(
+            run(None, Some(Arc::clone(&error_handler))).await,
+            vec!["POST /items 404 17"]
+        );
+        assert_eq!(
+            run(
+                Some(Err(HttpError::Forbidden("Nope".to_string()))),
+                Some(error_handler)
+            )
+            .await,
+            vec!["POST /items 403 17"]
+        );
+    }
+
+    #[test]
+    fn test_file_sink_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        let sink = FileSink::new(&path).unwrap().rotate(10, 2);
+
+        for line in ["first", "second", "third", "fourth"] {
+            sink.write_line(line);
+        }
+        sink.flush();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("access.log.1")).unwrap(),
+            "third\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("access.log.2")).unwrap(),
+            "second\n"
+        );
+        assert!(!dir.path().join("access.log.3").exists());
+    }
+
+    #[test]
+    fn test_channel_sink() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        ChannelSink::new(sender).write_line("GET /");
+        assert_eq!(receiver.try_recv().unwrap(), "GET /");
+    }
+}
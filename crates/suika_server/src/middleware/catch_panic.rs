@@ -0,0 +1,167 @@
+use crate::diagnostics::error;
+use crate::error::HttpError;
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::request::Request;
+use crate::response::Response;
+use futures::FutureExt;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+
+/// A middleware component turning panics in the rest of the middleware
+/// stack into `500 Internal Server Error` responses.
+///
+/// Without it, a panicking handler ends the connection's task and the
+/// client gets no response. The panic message is logged, whatever the
+/// handler wrote to the response, including cookies, is discarded and the
+/// request fails with `HttpError::InternalServerError`, which does not
+/// include the message.
+///
+/// Only unwinding panics can be caught; builds with `panic = "abort"` still
+/// abort. Add it first so it covers the other middleware too.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::CatchPanicMiddleware;
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(CatchPanicMiddleware::new()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CatchPanicMiddleware;
+
+impl CatchPanicMiddleware {
+    /// Creates a new `CatchPanicMiddleware`.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>")
+}
+
+impl Middleware for CatchPanicMiddleware {
+    /// Handles an incoming HTTP request by running the rest of the stack
+    /// and catching its panics.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            match AssertUnwindSafe(next.run(req, res)).catch_unwind().await {
+                Ok(result) => result,
+                Err(payload) => {
+                    error!(
+                        "Panic while handling {} {}: {}",
+                        req.method(),
+                        req.path(),
+                        panic_message(payload.as_ref())
+                    );
+                    res.reset();
+                    Err(HttpError::InternalServerError(
+                        "Internal Server Error".to_string(),
+                    ))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookie::Cookie;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use suika_templates::template_value::TemplateValue;
+
+    struct PanicHandler;
+
+    impl Middleware for PanicHandler {
+        fn handle<'a>(
+            &'a self,
+            req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.header("X-Started", "true")
+                    .set_cookie(Cookie::new("session", "half-built"))
+                    .template_var("user", TemplateValue::String("alice".to_string()));
+                tokio::task::yield_now().await;
+                match req.path() {
+                    "/str" => panic!("handler failed"),
+                    "/string" => panic!("handler failed for {}", req.path()),
+                    _ => {
+                        res.set_status(200);
+                        Ok(())
+                    }
+                }
+            })
+        }
+    }
+
+    async fn run(path: &str) -> (Result<(), HttpError>, Response) {
+        let mut req = Request::new(
+            &format!("GET {} HTTP/1.1\r\n\r\n", path),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(PanicHandler)];
+
+        let result = CatchPanicMiddleware::new()
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await;
+        (result, res)
+    }
+
+    #[tokio::test]
+    async fn test_catch_panic() {
+        let (result, res) = run("/").await;
+        assert!(result.is_ok());
+        assert_eq!(res.status(), Some(200));
+
+        for path in ["/str", "/string"] {
+            let (result, res) = run(path).await;
+            assert_eq!(
+                result,
+                Err(HttpError::InternalServerError(
+                    "Internal Server Error".to_string()
+                ))
+            );
+            assert_eq!(res.get_header("X-Started"), None);
+            assert!(res.cookies().is_empty());
+            assert!(res.get_template_var("user").is_none());
+        }
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload: Box<dyn Any + Send> = Box::new("static");
+        assert_eq!(panic_message(payload.as_ref()), "static");
+        let payload: Box<dyn Any + Send> = Box::new("owned".to_string());
+        assert_eq!(panic_message(payload.as_ref()), "owned");
+        let payload: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(panic_message(payload.as_ref()), "Box<dyn Any>");
+    }
+}
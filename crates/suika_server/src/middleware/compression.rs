@@ -0,0 +1,656 @@
+use crate::error::HttpError;
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::request::Request;
+use crate::response::{Body, BoxedReader, Response};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{Result as IoResult, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use suika_mime::is_compressed_mime_type;
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// The content encodings supported by `CompressionMiddleware`, in order of
+/// server preference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    const ALL: [Encoding; 3] = [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate];
+
+    /// Returns the token used for this encoding in `Accept-Encoding` and
+    /// `Content-Encoding` headers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Parses an `Accept-Encoding` header into `(coding, q-value)` pairs.
+///
+/// Codings without an explicit q-value default to `1.0`. Malformed q-values
+/// are treated as `0.0`, making the coding unacceptable.
+fn parse_accept_encoding(header: &str) -> Vec<(String, f32)> {
+    header
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.split(';');
+            let coding = pieces.next()?.trim().to_ascii_lowercase();
+            if coding.is_empty() {
+                return None;
+            }
+
+            let mut quality = 1.0;
+            for param in pieces {
+                if let Some((name, value)) = param.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("q") {
+                        quality = value.trim().parse::<f32>().unwrap_or(0.0);
+                    }
+                }
+            }
+
+            Some((coding, quality.clamp(0.0, 1.0)))
+        })
+        .collect()
+}
+
+/// Selects the best supported encoding for an `Accept-Encoding` header.
+///
+/// The encoding with the highest q-value wins. Ties are broken using the
+/// server preference order `br`, `gzip`, `deflate`. A `*` entry applies to any
+/// coding not listed explicitly.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::compression::{negotiate_encoding, Encoding};
+///
+/// let encodings = [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate];
+///
+/// assert_eq!(negotiate_encoding("gzip, deflate, br", &encodings), Some(Encoding::Brotli));
+/// assert_eq!(negotiate_encoding("gzip;q=1.0, br;q=0.5", &encodings), Some(Encoding::Gzip));
+/// assert_eq!(negotiate_encoding("identity", &encodings), None);
+/// ```
+pub fn negotiate_encoding(accept_encoding: &str, supported: &[Encoding]) -> Option<Encoding> {
+    let accepted = parse_accept_encoding(accept_encoding);
+    let wildcard = accepted
+        .iter()
+        .find(|(coding, _)| coding == "*")
+        .map(|(_, q)| *q);
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for encoding in Encoding::ALL.iter().filter(|e| supported.contains(e)) {
+        let quality = accepted
+            .iter()
+            .find(|(coding, _)| {
+                coding == encoding.as_str() || (*encoding == Encoding::Gzip && coding == "x-gzip")
+            })
+            .map(|(_, q)| *q)
+            .or(wildcard)
+            .unwrap_or(0.0);
+
+        if quality > 0.0 && best.is_none_or(|(_, best_q)| quality > best_q) {
+            best = Some((*encoding, quality));
+        }
+    }
+
+    best.map(|(encoding, _)| encoding)
+}
+
+/// An incremental encoder for one of the supported content encodings.
+enum Encoder {
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(encoding: Encoding, level: u32) -> Self {
+        match encoding {
+            Encoding::Brotli => Encoder::Brotli(Box::new(brotli::CompressorWriter::new(
+                Vec::new(),
+                4096,
+                level.min(11),
+                22,
+            ))),
+            Encoding::Gzip => {
+                Encoder::Gzip(GzEncoder::new(Vec::new(), Compression::new(level.min(9))))
+            }
+            Encoding::Deflate => {
+                Encoder::Deflate(ZlibEncoder::new(Vec::new(), Compression::new(level.min(9))))
+            }
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Encoder::Brotli(w) => w.as_mut(),
+            Encoder::Gzip(w) => w,
+            Encoder::Deflate(w) => w,
+        }
+    }
+
+    /// Compresses a chunk and flushes it, returning all output produced so far.
+    fn write_chunk(&mut self, data: &[u8]) -> IoResult<Vec<u8>> {
+        let writer = self.writer();
+        writer.write_all(data)?;
+        writer.flush()?;
+        Ok(self.take_output())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        match self {
+            Encoder::Brotli(w) => std::mem::take(w.get_mut()),
+            Encoder::Gzip(w) => std::mem::take(w.get_mut()),
+            Encoder::Deflate(w) => std::mem::take(w.get_mut()),
+        }
+    }
+
+    /// Finishes the compressed stream, returning the remaining output.
+    fn finish(self) -> IoResult<Vec<u8>> {
+        match self {
+            Encoder::Brotli(w) => Ok(w.into_inner()),
+            Encoder::Gzip(w) => w.finish(),
+            Encoder::Deflate(w) => w.finish(),
+        }
+    }
+}
+
+/// Compresses a complete buffer with the given encoding.
+fn compress_bytes(encoding: Encoding, level: u32, data: &[u8]) -> IoResult<Vec<u8>> {
+    let mut encoder = Encoder::new(encoding, level);
+    encoder.writer().write_all(data)?;
+    encoder.finish()
+}
+
+/// An `AsyncRead` adapter that compresses the data read from another reader.
+struct CompressedReader {
+    inner: BoxedReader,
+    encoder: Option<Encoder>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl CompressedReader {
+    fn new(inner: BoxedReader, encoding: Encoding, level: u32) -> Self {
+        Self {
+            inner,
+            encoder: Some(Encoder::new(encoding, level)),
+            output: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl AsyncRead for CompressedReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.position < this.output.len() {
+                let available = &this.output[this.position..];
+                let size = available.len().min(buf.remaining());
+                buf.put_slice(&available[..size]);
+                this.position += size;
+                return Poll::Ready(Ok(()));
+            }
+
+            let encoder = match this.encoder.as_mut() {
+                Some(encoder) => encoder,
+                None => return Poll::Ready(Ok(())),
+            };
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Ready(Ok(())) => {
+                    let filled = chunk_buf.filled();
+                    this.output = if filled.is_empty() {
+                        this.encoder.take().unwrap().finish()?
+                    } else {
+                        encoder.write_chunk(filled)?
+                    };
+                    this.position = 0;
+                }
+            }
+        }
+    }
+}
+
+/// A middleware component for compressing response bodies.
+///
+/// The encoding is negotiated from the request's `Accept-Encoding` header,
+/// honouring q-values. Buffered bodies smaller than the threshold, responses
+/// that already carry a `Content-Encoding`, and MIME types that are already
+/// compressed are left untouched. Streaming bodies are compressed on the fly.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::CompressionMiddleware;
+///
+/// let compression = CompressionMiddleware::new(1024);
+/// ```
+pub struct CompressionMiddleware {
+    threshold: usize,
+    level: u32,
+    encodings: Vec<Encoding>,
+}
+
+impl CompressionMiddleware {
+    /// Creates a new `CompressionMiddleware` that supports brotli, gzip and deflate.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - The minimum size in bytes of a buffered body before it is compressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::CompressionMiddleware;
+    ///
+    /// let compression = CompressionMiddleware::new(1024);
+    /// ```
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            level: 6,
+            encodings: Encoding::ALL.to_vec(),
+        }
+    }
+
+    /// Sets the compression level. Levels are clamped to the maximum of each
+    /// encoding (9 for gzip and deflate, 11 for brotli).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::CompressionMiddleware;
+    ///
+    /// let compression = CompressionMiddleware::new(1024).level(9);
+    /// ```
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Restricts the encodings the middleware may use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::CompressionMiddleware;
+    /// use suika_server::middleware::compression::Encoding;
+    ///
+    /// let compression = CompressionMiddleware::new(1024).encodings(&[Encoding::Gzip]);
+    /// ```
+    pub fn encodings(mut self, encodings: &[Encoding]) -> Self {
+        self.encodings = encodings.to_vec();
+        self
+    }
+
+    fn compress_response(
+        &self,
+        accept_encoding: Option<&str>,
+        res: &mut Response,
+    ) -> Result<(), HttpError> {
+        if res.get_header("Content-Encoding").is_some() {
+            return Ok(());
+        }
+
+        if matches!(res.status(), Some(204) | Some(304)) {
+            return Ok(());
+        }
+
+        if let Some(content_type) = res.get_header("Content-Type") {
+            if is_compressed_mime_type(content_type) {
+                return Ok(());
+            }
+        }
+
+        let body = match res.take_body() {
+            Some(body) => body,
+            None => return Ok(()),
+        };
+
+        let eligible = match &body {
+            Body::Text(text) => text.len() >= self.threshold,
+            Body::Binary(bytes) => bytes.len() >= self.threshold,
+            Body::Stream(_) => true,
+        };
+
+        if !eligible {
+            res.set_body(body);
+            return Ok(());
+        }
+
+        add_vary_accept_encoding(res);
+
+        let encoding = match accept_encoding.and_then(|h| negotiate_encoding(h, &self.encodings)) {
+            Some(encoding) => encoding,
+            None => {
+                res.set_body(body);
+                return Ok(());
+            }
+        };
+
+        match body {
+            Body::Text(text) => self.compress_buffer(encoding, text.as_bytes(), res)?,
+            Body::Binary(bytes) => self.compress_buffer(encoding, &bytes, res)?,
+            Body::Stream(stream) => {
+                if let Some(reader) = stream.take() {
+                    res.remove_header("Content-Length")
+                        .header("Content-Encoding", encoding.as_str())
+                        .body_stream(CompressedReader::new(reader, encoding, self.level));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compress_buffer(
+        &self,
+        encoding: Encoding,
+        data: &[u8],
+        res: &mut Response,
+    ) -> Result<(), HttpError> {
+        let compressed = compress_bytes(encoding, self.level, data).map_err(|e| {
+            HttpError::InternalServerError(format!("Failed to compress response: {}", e))
+        })?;
+
+        res.header("Content-Encoding", encoding.as_str());
+        if res.get_header("Content-Length").is_some() {
+            res.remove_header("Content-Length")
+                .header("Content-Length", &compressed.len().to_string());
+        }
+        res.body_bytes(compressed);
+        Ok(())
+    }
+}
+
+/// Adds `Accept-Encoding` to the response's `Vary` header, preserving any
+/// existing values.
+pub(crate) fn add_vary_accept_encoding(res: &mut Response) {
+    match res.get_header("Vary").map(str::to_string) {
+        Some(vary) => {
+            let already_listed = vary
+                .split(',')
+                .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case("Accept-Encoding"));
+            if !already_listed {
+                res.remove_header("Vary")
+                    .header("Vary", &format!("{}, Accept-Encoding", vary));
+            }
+        }
+        None => {
+            res.header("Vary", "Accept-Encoding");
+        }
+    }
+}
+
+impl Middleware for CompressionMiddleware {
+    /// Handles an incoming HTTP request by running the rest of the stack and
+    /// compressing the resulting response body.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let accept_encoding = req.get_header("Accept-Encoding").map(|h| h.to_string());
+            let is_head = req.method().eq_ignore_ascii_case("HEAD");
+
+            next.run(req, res).await?;
+
+            if is_head {
+                return Ok(());
+            }
+
+            self.compress_response(accept_encoding.as_deref(), res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{Middleware, Next};
+    use crate::request::Request;
+    use crate::response::{Body, Response};
+    use flate2::read::{GzDecoder, ZlibDecoder};
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::AsyncReadExt;
+
+    struct MockHandler {
+        content_type: &'static str,
+        body: Body,
+    }
+
+    impl Middleware for MockHandler {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.set_status(200)
+                    .header("Content-Type", self.content_type)
+                    .set_body(self.body.clone());
+                Ok(())
+            })
+        }
+    }
+
+    async fn run_compression(
+        accept_encoding: &str,
+        content_type: &'static str,
+        body: Body,
+    ) -> Response {
+        let request = format!(
+            "GET / HTTP/1.1\r\nAccept-Encoding: {}\r\n\r\n",
+            accept_encoding
+        );
+        run_compression_request(&request, content_type, body).await
+    }
+
+    async fn run_compression_request(
+        request: &str,
+        content_type: &'static str,
+        body: Body,
+    ) -> Response {
+        let mut req = Request::new(request, Arc::new(Mutex::new(HashMap::new()))).unwrap();
+        let mut res = Response::new(None);
+
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(MockHandler { content_type, body })];
+        let next = Next::new(middleware_stack.as_slice());
+
+        CompressionMiddleware::new(16)
+            .handle(&mut req, &mut res, next)
+            .await
+            .unwrap();
+        res
+    }
+
+    fn large_text() -> String {
+        "Hello, compression! ".repeat(50)
+    }
+
+    #[test]
+    fn test_negotiate_encoding() {
+        let all = Encoding::ALL;
+        assert_eq!(negotiate_encoding("gzip", &all), Some(Encoding::Gzip));
+        assert_eq!(negotiate_encoding("gzip, br", &all), Some(Encoding::Brotli));
+        assert_eq!(
+            negotiate_encoding("br;q=0.2, gzip;q=0.8, deflate;q=0.5", &all),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(
+            negotiate_encoding("br;q=0, deflate", &all),
+            Some(Encoding::Deflate)
+        );
+        assert_eq!(negotiate_encoding("*", &all), Some(Encoding::Brotli));
+        assert_eq!(
+            negotiate_encoding("*;q=0.5, br;q=0", &all),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(negotiate_encoding("identity", &all), None);
+        assert_eq!(negotiate_encoding("", &all), None);
+        assert_eq!(negotiate_encoding("br", &[Encoding::Gzip]), None);
+    }
+
+    #[tokio::test]
+    async fn test_compresses_text_with_gzip() {
+        let text = large_text();
+        let res = run_compression("gzip", "text/plain", Body::Text(text.clone())).await;
+
+        assert_eq!(
+            res.headers().get("Content-Encoding"),
+            Some(&"gzip".to_string())
+        );
+        assert_eq!(
+            res.headers().get("Vary"),
+            Some(&"Accept-Encoding".to_string())
+        );
+
+        if let Some(Body::Binary(bytes)) = res.get_body() {
+            let mut decoded = String::new();
+            GzDecoder::new(&bytes[..])
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        } else {
+            panic!("Expected body to be Some(Body::Binary)");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_matches_accept_encoding_case_insensitively() {
+        let res = run_compression_request(
+            "GET / HTTP/1.1\r\naccept-encoding: gzip\r\n\r\n",
+            "text/plain",
+            Body::Text(large_text()),
+        )
+        .await;
+
+        assert_eq!(res.get_header("Content-Encoding"), Some("gzip"));
+    }
+
+    #[tokio::test]
+    async fn test_compresses_with_deflate_and_brotli() {
+        let text = large_text();
+
+        let res = run_compression("deflate", "application/json", Body::Text(text.clone())).await;
+        assert_eq!(
+            res.headers().get("Content-Encoding"),
+            Some(&"deflate".to_string())
+        );
+        if let Some(Body::Binary(bytes)) = res.get_body() {
+            let mut decoded = String::new();
+            ZlibDecoder::new(&bytes[..])
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        } else {
+            panic!("Expected body to be Some(Body::Binary)");
+        }
+
+        let res = run_compression("br", "text/html", Body::Text(text.clone())).await;
+        assert_eq!(
+            res.headers().get("Content-Encoding"),
+            Some(&"br".to_string())
+        );
+        if let Some(Body::Binary(bytes)) = res.get_body() {
+            let mut decoded = String::new();
+            brotli::Decompressor::new(&bytes[..], 4096)
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        } else {
+            panic!("Expected body to be Some(Body::Binary)");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skips_small_bodies() {
+        let res = run_compression("gzip", "text/plain", Body::Text("tiny".to_string())).await;
+        assert_eq!(res.headers().get("Content-Encoding"), None);
+        assert_eq!(res.get_body(), Some(&Body::Text("tiny".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_skips_compressed_mime_types() {
+        let bytes = vec![7u8; 1024];
+        let res = run_compression("gzip", "image/png", Body::Binary(bytes.clone())).await;
+        assert_eq!(res.headers().get("Content-Encoding"), None);
+        assert_eq!(res.headers().get("Vary"), None);
+        assert_eq!(res.get_body(), Some(&Body::Binary(bytes)));
+    }
+
+    #[tokio::test]
+    async fn test_sets_vary_without_acceptable_encoding() {
+        let text = large_text();
+        let res = run_compression("identity", "text/plain", Body::Text(text.clone())).await;
+        assert_eq!(res.headers().get("Content-Encoding"), None);
+        assert_eq!(
+            res.headers().get("Vary"),
+            Some(&"Accept-Encoding".to_string())
+        );
+        assert_eq!(res.get_body(), Some(&Body::Text(text)));
+    }
+
+    #[tokio::test]
+    async fn test_compresses_streaming_body() {
+        let text = large_text();
+        let stream =
+            crate::response::BodyStream::new(std::io::Cursor::new(text.clone().into_bytes()));
+        let mut res = run_compression("gzip", "text/plain", Body::Stream(stream)).await;
+
+        assert_eq!(res.get_header("Content-Encoding"), Some("gzip"));
+
+        if let Some(Body::Stream(stream)) = res.take_body() {
+            let mut compressed = Vec::new();
+            stream
+                .take()
+                .unwrap()
+                .read_to_end(&mut compressed)
+                .await
+                .unwrap();
+
+            let mut decoded = String::new();
+            GzDecoder::new(&compressed[..])
+                .read_to_string(&mut decoded)
+                .unwrap();
+            assert_eq!(decoded, text);
+        } else {
+            panic!("Expected body to be Some(Body::Stream)");
+        }
+    }
+}
@@ -1,12 +1,305 @@
+use crate::error::HttpError;
 use crate::middleware::{Middleware, MiddlewareFuture, Next};
 use crate::request::Request;
 use crate::response::Response;
+use regex::Regex;
+use std::fmt;
+use std::sync::Arc;
+
+/// A rule matching the origins allowed to make cross-origin requests.
+#[derive(Clone)]
+enum OriginRule {
+    /// Matches one origin exactly, e.g. `https://example.com`.
+    Exact(String),
+    /// Matches origins whose host is a domain or one of its subdomains,
+    /// e.g. `.example.com`.
+    Suffix(String),
+    /// Matches origins with a regex.
+    Regex(Regex),
+    /// Matches origins accepted by a predicate.
+    Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl OriginRule {
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginRule::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            OriginRule::Suffix(suffix) => {
+                let origin = origin.to_ascii_lowercase();
+                let host = origin.split_once("://").map_or("", |(_, host)| host);
+                let host = host
+                    .rsplit_once(':')
+                    .filter(|(_, port)| port.bytes().all(|b| b.is_ascii_digit()))
+                    .map_or(host, |(host, _)| host);
+                let suffix = suffix.to_ascii_lowercase();
+                let domain = suffix.trim_start_matches('.');
+                let is_subdomain = host
+                    .strip_suffix(domain)
+                    .is_some_and(|label| label.len() > 1 && label.ends_with('.'));
+                !domain.is_empty() && (is_subdomain || (!suffix.starts_with('.') && host == domain))
+            }
+            OriginRule::Regex(regex) => regex.is_match(origin),
+            OriginRule::Predicate(predicate) => predicate(origin),
+        }
+    }
+}
+
+impl fmt::Debug for OriginRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OriginRule::Exact(origin) => f.debug_tuple("Exact").field(origin).finish(),
+            OriginRule::Suffix(suffix) => f.debug_tuple("Suffix").field(suffix).finish(),
+            OriginRule::Regex(regex) => f.debug_tuple("Regex").field(&regex.as_str()).finish(),
+            OriginRule::Predicate(_) => f.write_str("Predicate"),
+        }
+    }
+}
 
 /// A middleware component for handling CORS (Cross-Origin Resource Sharing).
-pub struct CorsMiddleware;
+///
+/// By default any origin may make requests with the `GET`, `POST`, `PUT`,
+/// `DELETE` and `OPTIONS` methods and the `Content-Type` and `Authorization`
+/// headers. Adding an allowed origin restricts requests to the allowed
+/// origins, whose value is echoed in `Access-Control-Allow-Origin` along with
+/// `Vary: Origin`. Credentials require an allow-list.
+///
+/// Preflight requests, `OPTIONS` requests with an `Origin` and an
+/// `Access-Control-Request-Method` header, are answered with `204 No
+/// Content` when the origin, method and headers are allowed and fail with
+/// `HttpError::Forbidden` otherwise. Other requests are passed on, and get
+/// the CORS headers if their origin is allowed.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::CorsMiddleware;
+///
+/// let cors = CorsMiddleware::new()
+///     .allow_origin("https://example.com")
+///     .allow_origin_suffix(".example.com")
+///     .allow_methods(&["GET", "POST", "PATCH"])
+///     .expose_headers(&["X-Request-Id"])
+///     .allow_credentials(true)
+///     .max_age(600);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CorsMiddleware {
+    origins: Vec<OriginRule>,
+    methods: Vec<String>,
+    headers: Vec<String>,
+    exposed_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<u64>,
+}
+
+impl Default for CorsMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CorsMiddleware {
+    /// Creates a new `CorsMiddleware` allowing any origin.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::CorsMiddleware;
+    ///
+    /// let cors = CorsMiddleware::new();
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: to_strings(&["GET", "POST", "PUT", "DELETE", "OPTIONS"]),
+            headers: to_strings(&["Content-Type", "Authorization"]),
+            exposed_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allows an origin, compared case-insensitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - The origin, e.g. `https://example.com`.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.origins.push(OriginRule::Exact(origin.to_string()));
+        self
+    }
+
+    /// Allows the origins of a domain's subdomains, on any port, compared
+    /// case-insensitively and only at a `.` label boundary, so `example.com`
+    /// never matches `evilexample.com`.
+    ///
+    /// # Arguments
+    ///
+    /// * `suffix` - The domain. With a leading dot, e.g. `.example.com`, only
+    ///   subdomains match; without, e.g. `example.com`, the domain itself
+    ///   matches too.
+    pub fn allow_origin_suffix(mut self, suffix: &str) -> Self {
+        self.origins.push(OriginRule::Suffix(suffix.to_string()));
+        self
+    }
+
+    /// Allows every origin matching a regex.
+    ///
+    /// # Arguments
+    ///
+    /// * `regex` - The regex, which should usually be anchored with `^` and `$`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use regex::Regex;
+    /// use suika_server::middleware::CorsMiddleware;
+    ///
+    /// let cors = CorsMiddleware::new()
+    ///     .allow_origin_regex(Regex::new(r"^https://[a-z]+\.example\.com$").unwrap());
+    /// ```
+    pub fn allow_origin_regex(mut self, regex: Regex) -> Self {
+        self.origins.push(OriginRule::Regex(regex));
+        self
+    }
+
+    /// Allows every origin accepted by a predicate.
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - A function returning `true` for allowed origins.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::CorsMiddleware;
+    ///
+    /// let cors = CorsMiddleware::new()
+    ///     .allow_origin_fn(|origin| origin.starts_with("http://localhost:"));
+    /// ```
+    pub fn allow_origin_fn<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.origins
+            .push(OriginRule::Predicate(Arc::new(predicate)));
+        self
+    }
+
+    /// Sets the methods cross-origin requests may use.
+    pub fn allow_methods(mut self, methods: &[&str]) -> Self {
+        self.methods = methods.iter().map(|m| m.to_ascii_uppercase()).collect();
+        self
+    }
+
+    /// Sets the headers cross-origin requests may send.
+    pub fn allow_headers(mut self, headers: &[&str]) -> Self {
+        self.headers = to_strings(headers);
+        self
+    }
+
+    /// Sets the response headers exposed to cross-origin scripts.
+    pub fn expose_headers(mut self, headers: &[&str]) -> Self {
+        self.exposed_headers = to_strings(headers);
+        self
+    }
+
+    /// Allows cross-origin requests to include credentials such as cookies.
+    ///
+    /// Credentials are only allowed for origins on the allow-list: without
+    /// one, no origin is allowed, since echoing every origin with
+    /// `Access-Control-Allow-Credentials` would let any site read
+    /// authenticated responses.
+    pub fn allow_credentials(mut self, enabled: bool) -> Self {
+        self.credentials = enabled;
+        self
+    }
+
+    /// Sets how long, in seconds, browsers may cache preflight responses.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    /// Returns `true` if cross-origin requests from the origin are allowed.
+    pub fn is_allowed_origin(&self, origin: &str) -> bool {
+        if self.origins.is_empty() {
+            return !self.credentials;
+        }
+        self.origins.iter().any(|rule| rule.matches(origin))
+    }
+
+    /// Returns `true` if `Access-Control-Allow-Origin` depends on the
+    /// request's origin, which responses announce with `Vary: Origin`.
+    fn varies_by_origin(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// Adds the headers shared by preflight and actual responses.
+    fn add_origin_headers(&self, origin: &str, res: &mut Response) {
+        if self.varies_by_origin() {
+            res.header("Access-Control-Allow-Origin", origin);
+        } else {
+            res.header("Access-Control-Allow-Origin", "*");
+        }
+        if self.credentials {
+            res.header("Access-Control-Allow-Credentials", "true");
+        }
+    }
+
+    /// Answers a preflight request for an allowed origin.
+    fn preflight(
+        &self,
+        origin: &str,
+        method: &str,
+        headers: Option<&str>,
+        res: &mut Response,
+    ) -> Result<(), HttpError> {
+        if !self.methods.iter().any(|m| m.eq_ignore_ascii_case(method)) {
+            return Err(HttpError::Forbidden(format!(
+                "CORS method {} is not allowed",
+                method
+            )));
+        }
+
+        let requested = headers
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|header| !header.is_empty());
+        for header in requested {
+            if !self.headers.iter().any(|h| h.eq_ignore_ascii_case(header)) {
+                return Err(HttpError::Forbidden(format!(
+                    "CORS header {} is not allowed",
+                    header
+                )));
+            }
+        }
+
+        self.add_origin_headers(origin, res);
+        res.header("Access-Control-Allow-Methods", &self.methods.join(", "));
+        if !self.headers.is_empty() {
+            res.header("Access-Control-Allow-Headers", &self.headers.join(", "));
+        }
+        if let Some(max_age) = self.max_age {
+            res.header("Access-Control-Max-Age", &max_age.to_string());
+        }
+        add_vary(
+            res,
+            &[
+                "Origin",
+                "Access-Control-Request-Method",
+                "Access-Control-Request-Headers",
+            ],
+        );
+        res.set_status(204);
+        Ok(())
+    }
+}
 
 impl Middleware for CorsMiddleware {
-    /// Handles an incoming HTTP request by adding CORS headers.
+    /// Handles an incoming HTTP request by answering preflights and adding
+    /// CORS headers to the responses of allowed origins.
     ///
     /// # Arguments
     ///
@@ -24,28 +317,68 @@ impl Middleware for CorsMiddleware {
         mut next: Next<'a>,
     ) -> MiddlewareFuture<'a> {
         Box::pin(async move {
-            res.header("Access-Control-Allow-Origin", "*").await;
-            res.header(
-                "Access-Control-Allow-Methods",
-                "GET, POST, PUT, DELETE, OPTIONS",
-            )
-            .await;
-            res.header(
-                "Access-Control-Allow-Headers",
-                "Content-Type, Authorization",
-            )
-            .await;
+            let origin = req.get_header("Origin").map(str::to_string);
+            let allowed_origin = origin.filter(|origin| self.is_allowed_origin(origin));
 
-            if req.method() == "OPTIONS" {
-                res.set_status(204).await;
-                return Ok(());
+            let requested_method = req.get_header("Access-Control-Request-Method");
+            if req.method() == "OPTIONS" && req.get_header("Origin").is_some() {
+                if let Some(method) = requested_method {
+                    return match &allowed_origin {
+                        Some(origin) => self.preflight(
+                            origin,
+                            method,
+                            req.get_header("Access-Control-Request-Headers"),
+                            res,
+                        ),
+                        None => Err(HttpError::Forbidden(
+                            "CORS origin is not allowed".to_string(),
+                        )),
+                    };
+                }
             }
 
-            next.run(req, res).await
+            let result = next.run(req, res).await;
+
+            if let Some(origin) = &allowed_origin {
+                self.add_origin_headers(origin, res);
+                if !self.exposed_headers.is_empty() {
+                    res.header(
+                        "Access-Control-Expose-Headers",
+                        &self.exposed_headers.join(", "),
+                    );
+                }
+            }
+            if self.varies_by_origin() {
+                add_vary(res, &["Origin"]);
+            }
+
+            result
         })
     }
 }
 
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Adds header names to the response's `Vary` header, preserving any
+/// existing values.
+fn add_vary(res: &mut Response, names: &[&str]) {
+    let mut vary: Vec<String> = res
+        .get_header("Vary")
+        .map(|vary| vary.split(',').map(|v| v.trim().to_string()).collect())
+        .unwrap_or_default();
+    if vary.iter().any(|v| v == "*") {
+        return;
+    }
+    for name in names {
+        if !vary.iter().any(|v| v.eq_ignore_ascii_case(name)) {
+            vary.push(name.to_string());
+        }
+    }
+    res.remove_header("Vary").header("Vary", &vary.join(", "));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,82 +419,239 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_cors_middleware_headers() {
-        let mut req = Request::new(
-            "GET / HTTP/1.1\r\n\r\n",
-            Arc::new(Mutex::new(HashMap::new())),
-        )
-        .unwrap();
-    
+    async fn run(cors: &CorsMiddleware, request: &str) -> (Result<(), HttpError>, Response, bool) {
+        let mut req = Request::new(request, Arc::new(Mutex::new(HashMap::new()))).unwrap();
         let mut res = Response::new(None);
 
-        let cors_middleware = CorsMiddleware;
         let next_middleware = MockNextMiddleware::new();
         let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
             vec![Arc::new(next_middleware.clone())];
         let next = Next::new(middleware_stack.as_slice());
 
-        cors_middleware
-            .handle(&mut req, &mut res, next.clone())
-            .await
-            .unwrap();
+        let result = cors.handle(&mut req, &mut res, next).await;
+        let next_called = *next_middleware.called.lock().await;
+        (result, res, next_called)
+    }
+
+    fn restricted() -> CorsMiddleware {
+        CorsMiddleware::new()
+            .allow_origin("https://example.com")
+            .allow_origin_suffix(".example.org")
+            .allow_origin_suffix("example.net")
+            .allow_origin_regex(Regex::new(r"^https://app-\d+\.test$").unwrap())
+            .allow_origin_fn(|origin| origin.starts_with("http://localhost:"))
+    }
 
-        let inner = res.get_inner().await;
-        assert_eq!(
-            inner.headers().get("Access-Control-Allow-Origin"),
-            Some(&"*".to_string())
-        );
+    #[tokio::test]
+    async fn test_cors_middleware_headers() {
+        let (result, res, next_called) = run(
+            &CorsMiddleware::new(),
+            "GET / HTTP/1.1\r\nOrigin: https://example.com\r\n\r\n",
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(res.get_header("Access-Control-Allow-Origin"), Some("*"));
+        assert_eq!(res.get_header("Access-Control-Allow-Methods"), None);
+        assert_eq!(res.get_header("Vary"), None);
+        assert!(next_called);
+    }
+
+    #[tokio::test]
+    async fn test_cors_middleware_options_request() {
+        let (result, res, next_called) = run(
+            &CorsMiddleware::new().max_age(600),
+            "OPTIONS / HTTP/1.1\r\nOrigin: https://example.com\r\nAccess-Control-Request-Method: PUT\r\nAccess-Control-Request-Headers: content-type\r\n\r\n",
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(res.get_header("Access-Control-Allow-Origin"), Some("*"));
         assert_eq!(
-            inner.headers().get("Access-Control-Allow-Methods"),
-            Some(&"GET, POST, PUT, DELETE, OPTIONS".to_string())
+            res.get_header("Access-Control-Allow-Methods"),
+            Some("GET, POST, PUT, DELETE, OPTIONS")
         );
         assert_eq!(
-            inner.headers().get("Access-Control-Allow-Headers"),
-            Some(&"Content-Type, Authorization".to_string())
+            res.get_header("Access-Control-Allow-Headers"),
+            Some("Content-Type, Authorization")
         );
+        assert_eq!(res.get_header("Access-Control-Max-Age"), Some("600"));
+        assert_eq!(res.status(), Some(204));
+        assert!(!next_called);
+    }
 
-        let next_called = *next_middleware.called.lock().await;
+    #[tokio::test]
+    async fn test_cors_middleware_passes_plain_options_requests() {
+        let (result, res, next_called) =
+            run(&CorsMiddleware::new(), "OPTIONS / HTTP/1.1\r\n\r\n").await;
+
+        assert!(result.is_ok());
+        assert_eq!(res.status(), None);
         assert!(next_called);
     }
 
     #[tokio::test]
-    async fn test_cors_middleware_options_request() {
-        let mut req = Request::new(
-            "OPTIONS / HTTP/1.1\r\n\r\n",
-            Arc::new(Mutex::new(HashMap::new())),
+    async fn test_cors_middleware_origin_allow_list() {
+        let cors = restricted();
+        for origin in [
+            "https://example.com",
+            "https://api.example.org",
+            "https://API.Example.org",
+            "https://example.net",
+            "https://a.b.example.net",
+            "https://api.example.org:8443",
+            "https://example.net:8080",
+            "https://app-42.test",
+            "http://localhost:3000",
+        ] {
+            let (_, res, next_called) = run(
+                &cors,
+                &format!("GET / HTTP/1.1\r\nOrigin: {}\r\n\r\n", origin),
+            )
+            .await;
+            assert_eq!(res.get_header("Access-Control-Allow-Origin"), Some(origin));
+            assert_eq!(res.get_header("Vary"), Some("Origin"));
+            assert!(next_called);
+        }
+
+        for origin in [
+            "https://evil.com",
+            "https://example.org",
+            "https://evilexample.org",
+            "https://evilexample.net",
+            "https://example.net.evil.com",
+            "https://evilexample.org:8443",
+            "https://example.net.evil.com:8080",
+        ] {
+            let (_, res, next_called) = run(
+                &cors,
+                &format!("GET / HTTP/1.1\r\nOrigin: {}\r\n\r\n", origin),
+            )
+            .await;
+            assert_eq!(
+                res.get_header("Access-Control-Allow-Origin"),
+                None,
+                "{}",
+                origin
+            );
+            assert_eq!(res.get_header("Vary"), Some("Origin"));
+            assert!(next_called);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cors_middleware_validates_preflights() {
+        let cors = restricted().allow_methods(&["get", "patch"]);
+
+        let (result, _, next_called) = run(
+            &cors,
+            "OPTIONS / HTTP/1.1\r\nOrigin: https://evil.com\r\nAccess-Control-Request-Method: GET\r\n\r\n",
         )
-        .unwrap();
+        .await;
+        assert_eq!(
+            result,
+            Err(HttpError::Forbidden(
+                "CORS origin is not allowed".to_string()
+            ))
+        );
+        assert!(!next_called);
 
-        let mut res = Response::new(None);
+        let (result, _, _) = run(
+            &cors,
+            "OPTIONS / HTTP/1.1\r\nOrigin: https://example.com\r\nAccess-Control-Request-Method: DELETE\r\n\r\n",
+        )
+        .await;
+        assert_eq!(
+            result,
+            Err(HttpError::Forbidden(
+                "CORS method DELETE is not allowed".to_string()
+            ))
+        );
 
-        let cors_middleware = CorsMiddleware;
-        let next_middleware = MockNextMiddleware::new();
-        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
-            vec![Arc::new(next_middleware.clone())];
-        let next = Next::new(middleware_stack.as_slice());
+        let (result, _, _) = run(
+            &cors,
+            "OPTIONS / HTTP/1.1\r\nOrigin: https://example.com\r\nAccess-Control-Request-Method: PATCH\r\nAccess-Control-Request-Headers: X-Custom\r\n\r\n",
+        )
+        .await;
+        assert_eq!(
+            result,
+            Err(HttpError::Forbidden(
+                "CORS header X-Custom is not allowed".to_string()
+            ))
+        );
+
+        let (result, res, _) = run(
+            &cors,
+            "OPTIONS / HTTP/1.1\r\nOrigin: https://example.com\r\nAccess-Control-Request-Method: PATCH\r\n\r\n",
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(
+            res.get_header("Access-Control-Allow-Methods"),
+            Some("GET, PATCH")
+        );
+        assert_eq!(
+            res.get_header("Vary"),
+            Some("Origin, Access-Control-Request-Method, Access-Control-Request-Headers")
+        );
+    }
 
-        cors_middleware
-            .handle(&mut req, &mut res, next.clone())
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn test_cors_middleware_credentials() {
+        let cors = CorsMiddleware::new()
+            .allow_origin("https://example.com")
+            .allow_credentials(true)
+            .expose_headers(&["X-Request-Id", "X-Total"]);
 
-        let inner = res.get_inner().await;
+        let (_, res, _) = run(
+            &cors,
+            "GET / HTTP/1.1\r\nOrigin: https://example.com\r\n\r\n",
+        )
+        .await;
         assert_eq!(
-            inner.headers().get("Access-Control-Allow-Origin"),
-            Some(&"*".to_string())
+            res.get_header("Access-Control-Allow-Origin"),
+            Some("https://example.com")
         );
         assert_eq!(
-            inner.headers().get("Access-Control-Allow-Methods"),
-            Some(&"GET, POST, PUT, DELETE, OPTIONS".to_string())
+            res.get_header("Access-Control-Allow-Credentials"),
+            Some("true")
         );
         assert_eq!(
-            inner.headers().get("Access-Control-Allow-Headers"),
-            Some(&"Content-Type, Authorization".to_string())
+            res.get_header("Access-Control-Expose-Headers"),
+            Some("X-Request-Id, X-Total")
         );
-        assert_eq!(inner.status_code(), Some(204));
+        assert_eq!(res.get_header("Vary"), Some("Origin"));
+    }
+    #[tokio::test]
+    async fn test_cors_middleware_credentials_require_allow_list() {
+        let listed = CorsMiddleware::new()
+            .allow_origin("https://example.com")
+            .allow_credentials(true);
+        let unlisted = CorsMiddleware::new().allow_credentials(true);
 
-        let next_called = *next_middleware.called.lock().await;
-        assert!(!next_called);
+        for (cors, origin) in [
+            (&listed, "https://evil.com"),
+            (&unlisted, "https://example.com"),
+        ] {
+            let (_, res, next_called) = run(
+                cors,
+                &format!("GET / HTTP/1.1\r\nOrigin: {}\r\n\r\n", origin),
+            )
+            .await;
+            assert_eq!(res.get_header("Access-Control-Allow-Origin"), None);
+            assert_eq!(res.get_header("Access-Control-Allow-Credentials"), None);
+            assert!(next_called);
+
+            let (result, res, _) = run(
+                cors,
+                &format!(
+                    "OPTIONS / HTTP/1.1\r\nOrigin: {}\r\nAccess-Control-Request-Method: GET\r\n\r\n",
+                    origin
+                ),
+            )
+            .await;
+            assert!(matches!(result, Err(HttpError::Forbidden(_))));
+            assert_eq!(res.get_header("Access-Control-Allow-Credentials"), None);
+        }
     }
 }
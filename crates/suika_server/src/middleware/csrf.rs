@@ -0,0 +1,444 @@
+use crate::cookie::{Cookie, SameSite};
+use crate::crypto::constant_time_eq;
+use crate::diagnostics::debug;
+use crate::error::HttpError;
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::random::random_bytes;
+use crate::request::Request;
+use crate::response::Response;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use suika_templates::template_value::TemplateValue;
+
+/// A middleware component protecting against cross-site request forgery
+/// with the double-submit cookie pattern.
+///
+/// Every response carries a random token in a cookie (`csrf_token` by
+/// default), which is also exposed to templates as the `csrf_token`
+/// variable. Requests with an unsafe method (anything but `GET`, `HEAD`,
+/// `OPTIONS` and `TRACE`) must send the same token back in the
+/// `X-CSRF-Token` header or the `csrf_token` form field, and their `Origin`
+/// (or, failing that, `Referer`) header must match the `Host` header or a
+/// trusted origin. Requests failing either check are rejected with
+/// `403 Forbidden`.
+///
+/// With a `secret`, tokens are signed so tokens the server did not issue
+/// are rejected. Signed tokens are not bound to a client, so they do not stop
+/// a sibling subdomain from planting a token fetched from the server. To stop
+/// that, use a cookie name starting with `__Host-` together with
+/// `secure(true)`: browsers only accept such cookies from the host itself,
+/// over HTTPS, with `Path=/` and no `Domain`.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::csrf::CsrfMiddleware;
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(
+///     CsrfMiddleware::new()
+///         .cookie_name("__Host-csrf_token")
+///         .secret(b"change me")
+///         .secure(true)
+///         .trust_origin("https://admin.example.com"),
+/// ));
+/// ```
+///
+/// A form rendered with `Response::render_template` then submits the token:
+///
+/// ```html
+/// <form method="post">
+///   <input type="hidden" name="csrf_token" value="<%= csrf_token %>">
+/// </form>
+/// ```
+#[derive(Debug, Clone)]
+pub struct CsrfMiddleware {
+    cookie_name: String,
+    header: String,
+    field: String,
+    secret: Option<Vec<u8>>,
+    secure: bool,
+    trusted_origins: Vec<String>,
+}
+
+impl Default for CsrfMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CsrfMiddleware {
+    /// Creates a new `CsrfMiddleware` using the `csrf_token` cookie and form
+    /// field and the `X-CSRF-Token` header, with unsigned tokens.
+    pub fn new() -> Self {
+        Self {
+            cookie_name: "csrf_token".to_string(),
+            header: "X-CSRF-Token".to_string(),
+            field: "csrf_token".to_string(),
+            secret: None,
+            secure: false,
+            trusted_origins: Vec::new(),
+        }
+    }
+
+    /// Sets the name of the cookie holding the token. A name starting with
+    /// `__Host-` keeps subdomains from setting the cookie; it requires
+    /// `secure(true)`.
+    pub fn cookie_name(mut self, name: &str) -> Self {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    /// Sets the header scripts send the token in.
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = name.to_string();
+        self
+    }
+
+    /// Sets the form field forms send the token in.
+    pub fn field(mut self, name: &str) -> Self {
+        self.field = name.to_string();
+        self
+    }
+
+    /// Signs tokens with an HMAC-SHA256 of `secret`.
+    pub fn secret(mut self, secret: &[u8]) -> Self {
+        self.secret = Some(secret.to_vec());
+        self
+    }
+
+    /// Sets whether the cookie is only sent over HTTPS.
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Accepts unsafe requests from another origin, e.g.
+    /// `https://admin.example.com`.
+    pub fn trust_origin(mut self, origin: &str) -> Self {
+        self.trusted_origins
+            .push(origin.trim_end_matches('/').to_ascii_lowercase());
+        self
+    }
+
+    fn sign(&self, nonce: &str) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(nonce.as_bytes());
+        Some(URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    fn generate_token(&self) -> String {
+        let nonce = URL_SAFE_NO_PAD.encode(random_bytes::<32>());
+        match self.sign(&nonce) {
+            Some(signature) => format!("{}.{}", nonce, signature),
+            None => nonce,
+        }
+    }
+
+    /// Returns `true` if `token` could have been generated by this
+    /// middleware.
+    fn is_valid_token(&self, token: &str) -> bool {
+        let well_formed = |part: &str| {
+            part.len() == 43
+                && part
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        };
+
+        if self.secret.is_none() {
+            return well_formed(token);
+        }
+        match token.split_once('.') {
+            Some((nonce, signature)) if well_formed(nonce) => {
+                self.sign(nonce).is_some_and(|expected| {
+                    constant_time_eq(expected.as_bytes(), signature.as_bytes())
+                })
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks that an unsafe request comes from the server's own origin or
+    /// a trusted one. Requests sending neither `Origin` nor `Referer`, such
+    /// as those from non-browser clients, are allowed.
+    fn is_same_origin(&self, req: &Request) -> bool {
+        let origin = match req.get_header("Origin") {
+            Some(origin) => origin.trim().to_ascii_lowercase(),
+            None => match req.get_header("Referer") {
+                Some(referer) => {
+                    let referer = referer.trim().to_ascii_lowercase();
+                    match referer.split_once("://") {
+                        Some((scheme, rest)) => {
+                            let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+                            format!("{}://{}", scheme, authority)
+                        }
+                        None => return false,
+                    }
+                }
+                None => return true,
+            },
+        };
+
+        if self.trusted_origins.contains(&origin) {
+            return true;
+        }
+        match (origin.split_once("://"), req.get_header("Host")) {
+            (Some((_, authority)), Some(host)) => authority == host.trim().to_ascii_lowercase(),
+            _ => false,
+        }
+    }
+
+    fn submitted_token<'r>(&self, req: &'r Request) -> Option<&'r str> {
+        req.get_header(&self.header).or_else(|| {
+            req.form_data()
+                .and_then(|form| form.get(&self.field))
+                .map(String::as_str)
+        })
+    }
+
+    fn check(&self, req: &Request, cookie_token: Option<&str>) -> Result<(), HttpError> {
+        if !self.is_same_origin(req) {
+            debug!("Rejected cross-origin {} {}", req.method(), req.path());
+            return Err(HttpError::Forbidden(
+                "Cross-origin request blocked".to_string(),
+            ));
+        }
+
+        match (cookie_token, self.submitted_token(req)) {
+            (Some(expected), Some(submitted))
+                if constant_time_eq(expected.as_bytes(), submitted.as_bytes()) =>
+            {
+                Ok(())
+            }
+            _ => {
+                debug!(
+                    "Rejected {} {} without a valid CSRF token",
+                    req.method(),
+                    req.path()
+                );
+                Err(HttpError::Forbidden(
+                    "Missing or invalid CSRF token".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+impl Middleware for CsrfMiddleware {
+    /// Handles an incoming HTTP request by issuing a CSRF token and, for
+    /// unsafe methods, checking the submitted token and origin.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let cookie_token = req
+                .cookie(&self.cookie_name)
+                .filter(|token| self.is_valid_token(token))
+                .map(str::to_string);
+
+            let token = match &cookie_token {
+                Some(token) => token.clone(),
+                None => {
+                    let token = self.generate_token();
+                    res.set_cookie(
+                        Cookie::new(&self.cookie_name, &token)
+                            .path("/")
+                            .secure(self.secure)
+                            .same_site(SameSite::Lax),
+                    );
+                    token
+                }
+            };
+            res.template_var("csrf_token", TemplateValue::String(token));
+
+            if !matches!(req.method(), "GET" | "HEAD" | "OPTIONS" | "TRACE") {
+                self.check(req, cookie_token.as_deref())?;
+            }
+
+            next.run(req, res).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    struct OkHandler;
+
+    impl Middleware for OkHandler {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.set_status(200);
+                Ok(())
+            })
+        }
+    }
+
+    async fn run(middleware: &CsrfMiddleware, request: &str) -> (Result<(), HttpError>, Response) {
+        let mut req = Request::new(request, Arc::new(Mutex::new(HashMap::new()))).unwrap();
+        let mut res = Response::new(None);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(OkHandler)];
+
+        let result = middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await;
+        (result, res)
+    }
+
+    fn template_token(res: &Response) -> String {
+        match res.get_template_var("csrf_token") {
+            Some(TemplateValue::String(token)) => token.clone(),
+            other => panic!("Expected a csrf_token template var, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_csrf_issues_tokens() {
+        let middleware = CsrfMiddleware::new().secure(true);
+        let (result, res) = run(&middleware, "GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").await;
+        assert!(result.is_ok());
+
+        let token = template_token(&res);
+        let cookie = res.get_cookie("csrf_token").unwrap();
+        assert_eq!(cookie.value(), token);
+        assert_eq!(
+            cookie.to_string(),
+            format!("csrf_token={}; Path=/; Secure; SameSite=Lax", token)
+        );
+
+        let request = format!("GET / HTTP/1.1\r\nCookie: csrf_token={}\r\n\r\n", token);
+        let (_, res) = run(&middleware, &request).await;
+        assert!(res.get_cookie("csrf_token").is_none());
+        assert_eq!(template_token(&res), token);
+
+        let (_, res) = run(
+            &middleware,
+            "GET / HTTP/1.1\r\nCookie: csrf_token=forged\r\n\r\n",
+        )
+        .await;
+        assert_ne!(template_token(&res), "forged");
+        assert!(res.get_cookie("csrf_token").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_csrf_checks_submitted_tokens() {
+        let middleware = CsrfMiddleware::new();
+        let token = middleware.generate_token();
+        let other = middleware.generate_token();
+
+        let header = format!(
+            "POST / HTTP/1.1\r\nCookie: csrf_token={}\r\nX-CSRF-Token: {}\r\n\r\n",
+            token, token
+        );
+        assert!(run(&middleware, &header).await.0.is_ok());
+
+        let form = format!(
+            "POST / HTTP/1.1\r\nCookie: csrf_token={}\r\nContent-Type: application/x-www-form-urlencoded\r\n\r\ncsrf_token={}",
+            token, token
+        );
+        assert!(run(&middleware, &form).await.0.is_ok());
+
+        for request in [
+            format!("POST / HTTP/1.1\r\nCookie: csrf_token={}\r\n\r\n", token),
+            format!("DELETE / HTTP/1.1\r\nX-CSRF-Token: {}\r\n\r\n", token),
+            format!(
+                "PUT / HTTP/1.1\r\nCookie: csrf_token={}\r\nX-CSRF-Token: {}\r\n\r\n",
+                token, other
+            ),
+        ] {
+            let (result, _) = run(&middleware, &request).await;
+            assert!(
+                matches!(result, Err(HttpError::Forbidden(_))),
+                "{}",
+                request
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_csrf_checks_origin() {
+        let middleware = CsrfMiddleware::new().trust_origin("https://admin.example.com/");
+        let token = middleware.generate_token();
+        let request = |origin: &str| {
+            format!(
+                "POST / HTTP/1.1\r\nHost: example.com:8080\r\n{}Cookie: csrf_token={}\r\nX-CSRF-Token: {}\r\n\r\n",
+                origin, token, token
+            )
+        };
+
+        for allowed in [
+            "",
+            "Origin: https://example.com:8080\r\n",
+            "Origin: https://Admin.Example.com\r\n",
+            "Referer: https://example.com:8080/form?x=1\r\n",
+        ] {
+            assert!(
+                run(&middleware, &request(allowed)).await.0.is_ok(),
+                "{}",
+                allowed
+            );
+        }
+
+        for blocked in [
+            "Origin: https://evil.com\r\n",
+            "Origin: null\r\n",
+            "Origin: https://example.com\r\n",
+            "Referer: https://evil.com/example.com:8080\r\n",
+        ] {
+            let (result, _) = run(&middleware, &request(blocked)).await;
+            assert!(
+                matches!(result, Err(HttpError::Forbidden(_))),
+                "{}",
+                blocked
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_csrf_signed_tokens() {
+        let middleware = CsrfMiddleware::new().secret(b"secret");
+        let token = middleware.generate_token();
+        assert!(middleware.is_valid_token(&token));
+        assert!(!CsrfMiddleware::new()
+            .secret(b"other")
+            .is_valid_token(&token));
+
+        let unsigned = CsrfMiddleware::new().generate_token();
+        assert!(!middleware.is_valid_token(&unsigned));
+
+        let request = format!(
+            "POST / HTTP/1.1\r\nCookie: csrf_token={}\r\nX-CSRF-Token: {}\r\n\r\n",
+            unsigned, unsigned
+        );
+        let (result, res) = run(&middleware, &request).await;
+        assert!(matches!(result, Err(HttpError::Forbidden(_))));
+        assert!(res.get_cookie("csrf_token").is_some());
+    }
+}
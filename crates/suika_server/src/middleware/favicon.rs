@@ -1,3 +1,4 @@
+use crate::diagnostics::warning;
 use crate::error::HttpError;
 use crate::middleware::{Middleware, MiddlewareFuture, Next};
 use crate::request::Request;
@@ -13,14 +14,14 @@ fn map_io_error(err: std::io::Error) -> HttpError {
 
 async fn handle_favicon_request(path: PathBuf, res: &mut Response) -> Result<(), HttpError> {
     let absolute_path = path.canonicalize().map_err(|e| {
-        println!("Failed to get absolute path: {:?}", e);
+        warning!("Failed to get absolute path: {:?}", e);
         HttpError::NotFound("Favicon not found".to_string())
     })?;
 
     if !absolute_path.exists() {
-        println!("File does not exist: {:?}", absolute_path);
-        res.set_status(404).await;
-        res.body("Favicon not found".to_string()).await;
+        warning!("File does not exist: {:?}", absolute_path);
+        res.set_status(404);
+        res.body("Favicon not found".to_string());
         return Err(HttpError::NotFound("Favicon not found".to_string()));
     }
 
@@ -30,7 +31,7 @@ async fn handle_favicon_request(path: PathBuf, res: &mut Response) -> Result<(),
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer).map_err(map_io_error)?;
 
-    res.body_bytes(buffer).await;
+    res.body_bytes(buffer);
 
     let mime_type = absolute_path
         .extension()
@@ -38,8 +39,8 @@ async fn handle_favicon_request(path: PathBuf, res: &mut Response) -> Result<(),
         .map(get_mime_type)
         .unwrap_or_else(|| "application/octet-stream".to_string());
 
-    res.set_status(200).await;
-    res.header("Content-Type", mime_type.as_str()).await;
+    res.set_status(200);
+    res.header("Content-Type", mime_type.as_str());
 
     Ok(())
 }
@@ -164,15 +165,14 @@ mod tests {
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
+        assert_eq!(res.status(), Some(200));
         assert_eq!(
-            inner.headers().get("Content-Type"),
+            res.headers().get("Content-Type"),
             Some(&"image/x-icon".to_string())
         );
         assert_eq!(
-            inner.body(),
-            &Some(Body::Binary(b"fake favicon data".to_vec()))
+            res.get_body(),
+            Some(&Body::Binary(b"fake favicon data".to_vec()))
         );
 
         let next_called = *next_middleware.called.lock().await;
@@ -1,3 +1,4 @@
+use crate::diagnostics::info;
 use crate::middleware::{Middleware, MiddlewareFuture, Next};
 use crate::request::Request;
 use crate::response::Response;
@@ -149,7 +150,7 @@ impl Middleware for LoggerMiddleware {
         mut next: Next<'a>,
     ) -> MiddlewareFuture<'a> {
         Box::pin(async move {
-            println!("Logger => Method: {}, Path: {}", req.method(), req.path());
+            info!("Logger => Method: {}, Path: {}", req.method(), req.path());
             next.run(req, res).await
         })
     }
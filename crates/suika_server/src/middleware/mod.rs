@@ -1,11 +1,29 @@
+pub mod access_log;
+pub mod auth;
+pub mod catch_panic;
+pub mod compression;
 pub mod cors;
+pub mod csrf;
 pub mod favicon;
 pub mod logger;
+pub mod rate_limit;
+pub mod request_id;
+pub mod security_headers;
 pub mod static_file;
+pub mod timeout;
 pub mod traits;
 
+pub use access_log::AccessLogMiddleware;
+pub use auth::{ApiKeyMiddleware, BasicAuthMiddleware, BearerAuthMiddleware, JwtMiddleware};
+pub use catch_panic::CatchPanicMiddleware;
+pub use compression::CompressionMiddleware;
 pub use cors::CorsMiddleware;
+pub use csrf::CsrfMiddleware;
 pub use favicon::FaviconMiddleware;
 pub use logger::LoggerMiddleware;
+pub use rate_limit::RateLimitMiddleware;
+pub use request_id::RequestIdMiddleware;
+pub use security_headers::SecurityHeadersMiddleware;
 pub use static_file::StaticFileMiddleware;
+pub use timeout::TimeoutMiddleware;
 pub use traits::{Middleware, MiddlewareFuture, Next};
@@ -0,0 +1,733 @@
+use crate::error::HttpError;
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::path_pattern::PathPattern;
+use crate::request::Request;
+use crate::response::Response;
+use futures::future::BoxFuture;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A rate limit and the algorithm enforcing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimit {
+    /// A bucket holding up to `capacity` tokens, refilled at a steady rate of
+    /// `capacity` tokens per `period`. Each request takes a token, so bursts
+    /// of up to `capacity` requests are allowed.
+    TokenBucket { capacity: u32, period: Duration },
+    /// At most `limit` requests in any `window`, estimated from the counts of
+    /// the current and previous fixed windows.
+    SlidingWindow { limit: u32, window: Duration },
+}
+
+impl RateLimit {
+    /// Creates a token bucket limit of `capacity` requests per `period`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::rate_limit::RateLimit;
+    /// use std::time::Duration;
+    ///
+    /// let limit = RateLimit::token_bucket(10, Duration::from_secs(60));
+    /// assert_eq!(limit.limit(), 10);
+    /// ```
+    pub fn token_bucket(capacity: u32, period: Duration) -> Self {
+        RateLimit::TokenBucket { capacity, period }
+    }
+
+    /// Creates a sliding window limit of `limit` requests per `window`.
+    pub fn sliding_window(limit: u32, window: Duration) -> Self {
+        RateLimit::SlidingWindow { limit, window }
+    }
+
+    /// Returns the number of requests allowed in a burst or window.
+    pub fn limit(&self) -> u32 {
+        match self {
+            RateLimit::TokenBucket { capacity, .. } => *capacity,
+            RateLimit::SlidingWindow { limit, .. } => *limit,
+        }
+    }
+
+    /// Returns the period the limit applies to.
+    pub fn period(&self) -> Duration {
+        match self {
+            RateLimit::TokenBucket { period, .. } => *period,
+            RateLimit::SlidingWindow { window, .. } => *window,
+        }
+    }
+}
+
+/// The outcome of counting a request against a rate limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitDecision {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+    /// The number of requests allowed in a burst or window.
+    pub limit: u32,
+    /// The number of requests still allowed right now.
+    pub remaining: u32,
+    /// The time until the limit is fully available again.
+    pub reset: Duration,
+    /// The time to wait before retrying a rejected request.
+    pub retry_after: Option<Duration>,
+}
+
+/// Storage for rate limit counters.
+///
+/// `MemoryStore` keeps counters in the process. Implement this trait to
+/// share counters between servers through an external backend.
+pub trait RateLimitStore: Send + Sync {
+    /// Counts a request for `key` against `limit`.
+    ///
+    /// Rejected requests must not be counted. An error is returned to the
+    /// client; a store preferring to let requests through when its backend
+    /// is down should return an allowed decision instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key the request is counted under.
+    /// * `limit` - The limit to enforce.
+    fn hit<'a>(
+        &'a self,
+        key: &'a str,
+        limit: &'a RateLimit,
+    ) -> BoxFuture<'a, Result<RateLimitDecision, HttpError>>;
+}
+
+#[derive(Debug)]
+enum Counter {
+    Bucket {
+        tokens: f64,
+        updated: Instant,
+    },
+    Window {
+        start: Instant,
+        current: u32,
+        previous: u32,
+    },
+}
+
+#[derive(Debug)]
+struct Entry {
+    counter: Counter,
+    expires: Instant,
+}
+
+impl Entry {
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        let counter = match limit {
+            RateLimit::TokenBucket { capacity, .. } => Counter::Bucket {
+                tokens: *capacity as f64,
+                updated: now,
+            },
+            RateLimit::SlidingWindow { .. } => Counter::Window {
+                start: now,
+                current: 0,
+                previous: 0,
+            },
+        };
+        Self {
+            counter,
+            expires: now,
+        }
+    }
+
+    /// Returns `true` if the counter is kept by the algorithm of `limit`.
+    fn counts(&self, limit: &RateLimit) -> bool {
+        matches!(
+            (&self.counter, limit),
+            (Counter::Bucket { .. }, RateLimit::TokenBucket { .. })
+                | (Counter::Window { .. }, RateLimit::SlidingWindow { .. })
+        )
+    }
+}
+
+/// An in-memory `RateLimitStore`.
+///
+/// Counters that have returned to their initial state are removed every
+/// `purge_interval`, so memory use follows the number of active keys.
+#[derive(Debug)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, Entry>>,
+    purge_interval: Duration,
+    last_purge: Mutex<Instant>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    /// Creates an empty store purging expired counters every minute.
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            purge_interval: Duration::from_secs(60),
+            last_purge: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Sets how often expired counters are removed.
+    pub fn purge_interval(mut self, interval: Duration) -> Self {
+        self.purge_interval = interval;
+        self
+    }
+
+    /// Returns the number of keys with a counter.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns `true` if no key has a counter.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Counts a request for `key` against `limit` at the instant `now`.
+    pub(crate) fn hit_at(&self, key: &str, limit: &RateLimit, now: Instant) -> RateLimitDecision {
+        let mut entries = self.entries.lock().unwrap();
+        self.purge(&mut entries, now);
+
+        let entry = entries
+            .entry(key.to_string())
+            .or_insert_with(|| Entry::new(limit, now));
+        if !entry.counts(limit) {
+            *entry = Entry::new(limit, now);
+        }
+
+        let (decision, expires) = match (&mut entry.counter, limit) {
+            (Counter::Bucket { tokens, updated }, RateLimit::TokenBucket { capacity, period }) => {
+                take_token(tokens, updated, *capacity, *period, now)
+            }
+            (
+                Counter::Window {
+                    start,
+                    current,
+                    previous,
+                },
+                RateLimit::SlidingWindow { limit, window },
+            ) => count_in_window(start, current, previous, *limit, *window, now),
+            _ => unreachable!("the counter was reset to match the limit"),
+        };
+        entry.expires = expires;
+        decision
+    }
+
+    fn purge(&self, entries: &mut HashMap<String, Entry>, now: Instant) {
+        let mut last_purge = self.last_purge.lock().unwrap();
+        if now.saturating_duration_since(*last_purge) >= self.purge_interval {
+            entries.retain(|_, entry| entry.expires > now);
+            *last_purge = now;
+        }
+    }
+}
+
+impl RateLimitStore for MemoryStore {
+    fn hit<'a>(
+        &'a self,
+        key: &'a str,
+        limit: &'a RateLimit,
+    ) -> BoxFuture<'a, Result<RateLimitDecision, HttpError>> {
+        Box::pin(async move { Ok(self.hit_at(key, limit, Instant::now())) })
+    }
+}
+
+fn take_token(
+    tokens: &mut f64,
+    updated: &mut Instant,
+    capacity: u32,
+    period: Duration,
+    now: Instant,
+) -> (RateLimitDecision, Instant) {
+    let capacity_f = capacity as f64;
+    let period_secs = period.as_secs_f64();
+    // The time it takes to refill `count` tokens.
+    let refill = |count: f64| Duration::from_secs_f64(count * period_secs / capacity_f.max(1.0));
+
+    let elapsed = now.saturating_duration_since(*updated).as_secs_f64();
+    *tokens = (*tokens + elapsed * capacity_f / period_secs.max(f64::EPSILON)).min(capacity_f);
+    *updated = now;
+
+    let allowed = *tokens >= 1.0;
+    if allowed {
+        *tokens -= 1.0;
+    }
+
+    let reset = refill(capacity_f - *tokens);
+    let decision = RateLimitDecision {
+        allowed,
+        limit: capacity,
+        remaining: *tokens as u32,
+        reset,
+        retry_after: (!allowed).then(|| refill(1.0 - *tokens)),
+    };
+    (decision, now + reset)
+}
+
+fn count_in_window(
+    start: &mut Instant,
+    current: &mut u32,
+    previous: &mut u32,
+    limit: u32,
+    window: Duration,
+    now: Instant,
+) -> (RateLimitDecision, Instant) {
+    let window_secs = window.as_secs_f64().max(f64::EPSILON);
+
+    let passed = (now.saturating_duration_since(*start).as_secs_f64() / window_secs) as u32;
+    if passed > 0 {
+        *previous = if passed == 1 { *current } else { 0 };
+        *current = 0;
+        *start += window.mul_f64(passed as f64);
+    }
+
+    let elapsed = now.saturating_duration_since(*start).as_secs_f64() / window_secs;
+    let estimate = |previous: u32, current: u32| previous as f64 * (1.0 - elapsed) + current as f64;
+
+    let allowed = estimate(*previous, *current) + 1.0 <= limit as f64;
+    if allowed {
+        *current += 1;
+    }
+
+    let used = estimate(*previous, *current);
+    let until_next_window = (1.0 - elapsed) * window_secs;
+    let retry_after = (!allowed).then(|| {
+        let room = limit as f64 - 1.0 - *current as f64;
+        let fraction = if *previous > 0 && room >= 0.0 {
+            (1.0 - room / *previous as f64).max(elapsed)
+        } else if *current > 0 && limit > 0 {
+            1.0 + (1.0 - (limit as f64 - 1.0) / *current as f64).max(0.0)
+        } else {
+            2.0
+        };
+        Duration::from_secs_f64((fraction - elapsed) * window_secs)
+    });
+
+    let decision = RateLimitDecision {
+        allowed,
+        limit,
+        remaining: (limit as f64 - used).max(0.0) as u32,
+        reset: Duration::from_secs_f64(until_next_window),
+        retry_after,
+    };
+    (decision, *start + window * 2)
+}
+
+/// A function computing the rate limit key of a request.
+pub type KeyFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// What requests are counted together.
+#[derive(Clone)]
+pub enum RateLimitKey {
+    /// The client's IP address.
+    Ip,
+    /// The value of a request header, such as an API key.
+    Header(String),
+    /// The method and route, so each route has a single shared limit.
+    /// Requests not matching a route added with `RateLimitMiddleware::route`
+    /// share one limit per method.
+    Route,
+    /// A key computed from the request.
+    Custom(KeyFn),
+}
+
+impl RateLimitKey {
+    /// Keys requests by the value of the header `name`.
+    pub fn header(name: &str) -> Self {
+        RateLimitKey::Header(name.to_string())
+    }
+
+    /// Keys requests by the value returned by `key`.
+    pub fn custom<F>(key: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        RateLimitKey::Custom(Arc::new(key))
+    }
+
+    fn key(&self, req: &Request, route: &str) -> Option<String> {
+        match self {
+            RateLimitKey::Ip => req.remote_addr().map(|addr| addr.ip().to_string()),
+            RateLimitKey::Header(name) => req.get_header(name).map(str::to_string),
+            RateLimitKey::Route => Some(format!("{} {}", req.method(), route)),
+            RateLimitKey::Custom(key) => key(req),
+        }
+    }
+}
+
+impl fmt::Debug for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitKey::Ip => write!(f, "Ip"),
+            RateLimitKey::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            RateLimitKey::Route => write!(f, "Route"),
+            RateLimitKey::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RouteLimit {
+    method: String,
+    pattern: String,
+    regex: Regex,
+    limit: RateLimit,
+}
+
+impl RouteLimit {
+    fn matches(&self, req: &Request) -> bool {
+        (self.method == "*" || self.method.eq_ignore_ascii_case(req.method()))
+            && self.regex.is_match(req.path())
+    }
+}
+
+/// A middleware component limiting the rate of requests.
+///
+/// Requests are counted per key, by default the client's IP address, in a
+/// `RateLimitStore`. Every limited response carries `RateLimit-Limit`,
+/// `RateLimit-Remaining` and `RateLimit-Reset` headers, and requests over the
+/// limit are rejected with `429 Too Many Requests` and a `Retry-After`
+/// header. Requests for which no key can be computed are not limited.
+///
+/// Routes added with `route` have their own limit and counters, which
+/// replace the default limit for matching requests.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::rate_limit::{RateLimit, RateLimitMiddleware};
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(
+///     RateLimitMiddleware::new(RateLimit::token_bucket(100, Duration::from_secs(60)))
+///         .route("POST", "/login", RateLimit::sliding_window(5, Duration::from_secs(300))),
+/// ));
+/// ```
+pub struct RateLimitMiddleware {
+    limit: RateLimit,
+    key: RateLimitKey,
+    store: Arc<dyn RateLimitStore>,
+    routes: Vec<RouteLimit>,
+}
+
+impl RateLimitMiddleware {
+    /// Creates a new `RateLimitMiddleware` enforcing `limit` per client IP
+    /// address, with counters kept in a `MemoryStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - The default limit.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            key: RateLimitKey::Ip,
+            store: Arc::new(MemoryStore::new()),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Sets what requests are counted together.
+    pub fn key(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// Sets the store counters are kept in.
+    pub fn store(mut self, store: Arc<dyn RateLimitStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Sets the limit of requests matching a method and a path pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The HTTP method, or `*` for any method.
+    /// * `pattern` - A route pattern, such as `/users/:id/login`.
+    /// * `limit` - The limit of matching requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid route pattern.
+    pub fn route(mut self, method: &str, pattern: &str, limit: RateLimit) -> Self {
+        let path_pattern = PathPattern::parse(pattern).unwrap_or_else(|e| panic!("{}", e));
+        self.routes.push(RouteLimit {
+            method: method.to_ascii_uppercase(),
+            pattern: path_pattern.as_str().to_string(),
+            regex: path_pattern.to_regex(),
+            limit,
+        });
+        self
+    }
+
+    /// Returns the scope counters are kept in, the route and the limit of
+    /// a request.
+    fn limit_for<'a>(&'a self, req: &'a Request) -> (String, &'a str, &'a RateLimit) {
+        match self.routes.iter().find(|route| route.matches(req)) {
+            Some(route) => (
+                format!("{} {}", route.method, route.pattern),
+                &route.pattern,
+                &route.limit,
+            ),
+            None => ("*".to_string(), "*", &self.limit),
+        }
+    }
+}
+
+impl fmt::Debug for RateLimitMiddleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimitMiddleware")
+            .field("limit", &self.limit)
+            .field("key", &self.key)
+            .field("routes", &self.routes)
+            .finish_non_exhaustive()
+    }
+}
+
+fn seconds(duration: Duration) -> String {
+    duration.as_secs_f64().ceil().to_string()
+}
+
+impl Middleware for RateLimitMiddleware {
+    /// Handles an incoming HTTP request by counting it against its limit and
+    /// rejecting it if the limit is exceeded.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let (scope, route, limit) = self.limit_for(req);
+            let key = match self.key.key(req, route) {
+                Some(key) => format!("{}|{}", scope, key),
+                None => return next.run(req, res).await,
+            };
+            let limit = *limit;
+
+            let decision = self.store.hit(&key, &limit).await?;
+
+            let result = if decision.allowed {
+                next.run(req, res).await
+            } else {
+                let retry_after = decision.retry_after.unwrap_or(decision.reset);
+                res.header(
+                    "Retry-After",
+                    &seconds(retry_after.max(Duration::from_secs(1))),
+                );
+                Err(HttpError::TooManyRequests(
+                    "Rate limit exceeded, try again later".to_string(),
+                ))
+            };
+
+            res.header("RateLimit-Limit", &decision.limit.to_string())
+                .header("RateLimit-Remaining", &decision.remaining.to_string())
+                .header("RateLimit-Reset", &seconds(decision.reset));
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct OkHandler;
+
+    impl Middleware for OkHandler {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.set_status(200);
+                Ok(())
+            })
+        }
+    }
+
+    async fn run(
+        middleware: &RateLimitMiddleware,
+        request: &str,
+    ) -> (Result<(), HttpError>, Response) {
+        let mut req = Request::new(request, Arc::new(Mutex::new(HashMap::new()))).unwrap();
+        req.set_remote_addr("10.0.0.1:5000".parse().unwrap());
+        let mut res = Response::new(None);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(OkHandler)];
+
+        let result = middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await;
+        (result, res)
+    }
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let store = MemoryStore::new();
+        let limit = RateLimit::token_bucket(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert_eq!(store.hit_at("a", &limit, now).remaining, 1);
+        assert_eq!(store.hit_at("a", &limit, now).remaining, 0);
+
+        let rejected = store.hit_at("a", &limit, now);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.retry_after, Some(Duration::from_secs(5)));
+        assert_eq!(rejected.reset, Duration::from_secs(10));
+
+        assert!(store.hit_at("b", &limit, now).allowed);
+        assert!(
+            store
+                .hit_at("a", &limit, now + Duration::from_secs(5))
+                .allowed
+        );
+        assert!(
+            !store
+                .hit_at("a", &limit, now + Duration::from_secs(5))
+                .allowed
+        );
+    }
+
+    #[test]
+    fn test_sliding_window_weighs_previous_window() {
+        let store = MemoryStore::new();
+        let limit = RateLimit::sliding_window(4, Duration::from_secs(10));
+        let now = Instant::now();
+
+        for remaining in [3, 2, 1, 0] {
+            assert_eq!(store.hit_at("a", &limit, now).remaining, remaining);
+        }
+        let rejected = store.hit_at("a", &limit, now + Duration::from_secs(2));
+        assert!(!rejected.allowed);
+        assert_eq!(seconds(rejected.retry_after.unwrap()), "11");
+
+        // Halfway through the next window, half of the previous count is left.
+        let later = now + Duration::from_secs(15);
+        assert!(store.hit_at("a", &limit, later).allowed);
+        assert!(store.hit_at("a", &limit, later).allowed);
+        assert!(!store.hit_at("a", &limit, later).allowed);
+
+        assert_eq!(
+            store
+                .hit_at("a", &limit, now + Duration::from_secs(40))
+                .remaining,
+            3
+        );
+    }
+
+    #[test]
+    fn test_memory_store_purges_expired_counters() {
+        let store = MemoryStore::new().purge_interval(Duration::from_secs(1));
+        let limit = RateLimit::token_bucket(5, Duration::from_secs(5));
+        let now = Instant::now();
+
+        store.hit_at("a", &limit, now);
+        store.hit_at("b", &limit, now + Duration::from_millis(500));
+        assert_eq!(store.len(), 2);
+
+        store.hit_at("c", &limit, now + Duration::from_millis(1200));
+        assert_eq!(store.len(), 2);
+
+        store.hit_at("c", &limit, now + Duration::from_millis(2500));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_rejects_with_429() {
+        let middleware =
+            RateLimitMiddleware::new(RateLimit::token_bucket(2, Duration::from_secs(60)));
+        let request = "GET / HTTP/1.1\r\n\r\n";
+
+        let (result, res) = run(&middleware, request).await;
+        assert!(result.is_ok());
+        assert_eq!(res.get_header("RateLimit-Limit"), Some("2"));
+        assert_eq!(res.get_header("RateLimit-Remaining"), Some("1"));
+        assert_eq!(res.get_header("RateLimit-Reset"), Some("30"));
+
+        run(&middleware, request).await.0.unwrap();
+        let (result, res) = run(&middleware, request).await;
+        assert!(matches!(result, Err(HttpError::TooManyRequests(_))));
+        assert_eq!(res.get_header("Retry-After"), Some("30"));
+        assert_eq!(res.get_header("RateLimit-Remaining"), Some("0"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_route_overrides_and_keys() {
+        let middleware =
+            RateLimitMiddleware::new(RateLimit::token_bucket(100, Duration::from_secs(60)))
+                .key(RateLimitKey::header("X-Api-Key"))
+                .route(
+                    "post",
+                    "/users/:id/login",
+                    RateLimit::sliding_window(1, Duration::from_secs(60)),
+                );
+        let login = |id: &str, key: &str| {
+            format!(
+                "POST /users/{}/login HTTP/1.1\r\nX-Api-Key: {}\r\n\r\n",
+                id, key
+            )
+        };
+
+        assert!(run(&middleware, &login("1", "a")).await.0.is_ok());
+        assert!(run(&middleware, &login("2", "a")).await.0.is_err());
+        assert!(run(&middleware, &login("1", "b")).await.0.is_ok());
+
+        let (result, res) = run(
+            &middleware,
+            "GET /users/1/login HTTP/1.1\r\nX-Api-Key: a\r\n\r\n",
+        )
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(res.get_header("RateLimit-Limit"), Some("100"));
+
+        let (result, res) = run(&middleware, "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(result.is_ok());
+        assert_eq!(res.get_header("RateLimit-Limit"), None);
+
+        let per_route =
+            RateLimitMiddleware::new(RateLimit::token_bucket(1, Duration::from_secs(60)))
+                .key(RateLimitKey::Route)
+                .route(
+                    "GET",
+                    "/i/:id",
+                    RateLimit::token_bucket(1, Duration::from_secs(60)),
+                );
+        assert!(run(&per_route, "GET /a HTTP/1.1\r\n\r\n").await.0.is_ok());
+        assert!(run(&per_route, "GET /i/1 HTTP/1.1\r\n\r\n").await.0.is_ok());
+        assert!(run(&per_route, "GET /i/2 HTTP/1.1\r\n\r\n")
+            .await
+            .0
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_unmatched_paths_share_route_key() {
+        let middleware =
+            RateLimitMiddleware::new(RateLimit::token_bucket(1, Duration::from_secs(60)))
+                .key(RateLimitKey::Route);
+
+        assert!(run(&middleware, "GET /a HTTP/1.1\r\n\r\n").await.0.is_ok());
+        assert!(run(&middleware, "GET /b HTTP/1.1\r\n\r\n").await.0.is_err());
+        assert!(run(&middleware, "POST /a HTTP/1.1\r\n\r\n").await.0.is_ok());
+    }
+}
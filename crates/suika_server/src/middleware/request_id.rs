@@ -0,0 +1,216 @@
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::random::{ulid, uuid_v4};
+use crate::request::Request;
+use crate::response::Response;
+
+/// The format of generated request ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestIdFormat {
+    /// A random UUID, e.g. `f47ac10b-58cc-4372-a567-0e02b2c3d479`.
+    Uuid,
+    /// A ULID, ordered by creation time, e.g. `01ARZ3NDEKTSV4RRFFQ69G5FAV`.
+    Ulid,
+}
+
+impl RequestIdFormat {
+    /// Generates a new id in this format.
+    pub fn generate(&self) -> String {
+        match self {
+            RequestIdFormat::Uuid => uuid_v4(),
+            RequestIdFormat::Ulid => ulid(),
+        }
+    }
+}
+
+/// A middleware component giving every request an id.
+///
+/// The id sent by the client in the `X-Request-Id` header is kept if it is
+/// valid: at most `max_length` characters, all of them ASCII letters, digits
+/// or one of `-_.:+/=@`. Otherwise a new id is generated. The id is stored
+/// in the request, where handlers and `AccessLogMiddleware` read it with
+/// `Request::request_id`, and echoed in the response's `X-Request-Id` header.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::request_id::{RequestIdFormat, RequestIdMiddleware};
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(
+///     RequestIdMiddleware::new().format(RequestIdFormat::Ulid),
+/// ));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestIdMiddleware {
+    header: String,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+    max_length: usize,
+}
+
+impl Default for RequestIdMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestIdMiddleware {
+    /// Creates a new `RequestIdMiddleware` using the `X-Request-Id` header,
+    /// generating UUIDs and accepting incoming ids of up to 128 characters.
+    pub fn new() -> Self {
+        Self {
+            header: "X-Request-Id".to_string(),
+            format: RequestIdFormat::Uuid,
+            trust_incoming: true,
+            max_length: 128,
+        }
+    }
+
+    /// Sets the header the id is read from and echoed in.
+    pub fn header(mut self, name: &str) -> Self {
+        self.header = name.to_string();
+        self
+    }
+
+    /// Sets the format of generated ids.
+    pub fn format(mut self, format: RequestIdFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets whether ids sent by clients are kept. When disabled, every
+    /// request gets a generated id.
+    pub fn trust_incoming(mut self, enabled: bool) -> Self {
+        self.trust_incoming = enabled;
+        self
+    }
+
+    /// Sets the maximum length of ids sent by clients.
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Returns `true` if an id sent by a client may be used.
+    pub fn is_valid(&self, id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= self.max_length
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.:+/=@".contains(&b))
+    }
+
+    fn request_id(&self, req: &Request) -> String {
+        req.get_header(&self.header)
+            .filter(|id| self.trust_incoming && self.is_valid(id))
+            .map(str::to_string)
+            .unwrap_or_else(|| self.format.generate())
+    }
+}
+
+impl Middleware for RequestIdMiddleware {
+    /// Handles an incoming HTTP request by assigning it an id and echoing
+    /// the id in the response.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let id = self.request_id(req);
+            req.set_request_id(&id);
+            #[cfg(feature = "tracing")]
+            tracing::Span::current().record("request_id", id.as_str());
+
+            let result = next.run(req, res).await;
+            res.remove_header(&self.header).header(&self.header, &id);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    struct EchoHandler;
+
+    impl Middleware for EchoHandler {
+        fn handle<'a>(
+            &'a self,
+            req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.set_status(200);
+                res.body(req.request_id().unwrap_or_default().to_string());
+                Ok(())
+            })
+        }
+    }
+
+    async fn run(middleware: &RequestIdMiddleware, headers: &str) -> (String, Response) {
+        let mut req = Request::new(
+            &format!("GET / HTTP/1.1\r\n{}\r\n", headers),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(EchoHandler)];
+
+        middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+
+        (req.request_id().unwrap().to_string(), res)
+    }
+
+    #[tokio::test]
+    async fn test_request_id_keeps_valid_incoming_ids() {
+        let middleware = RequestIdMiddleware::new();
+        let (id, res) = run(&middleware, "x-request-id: abc-123.def\r\n").await;
+
+        assert_eq!(id, "abc-123.def");
+        assert_eq!(res.get_header("X-Request-Id"), Some("abc-123.def"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generates_ids() {
+        let middleware = RequestIdMiddleware::new().max_length(8);
+
+        for headers in [
+            "",
+            "X-Request-Id: has spaces\r\n",
+            "X-Request-Id: 123456789\r\n",
+        ] {
+            let (id, res) = run(&middleware, headers).await;
+            assert_eq!(id.len(), 36, "{}", headers);
+            assert_eq!(res.get_header("X-Request-Id"), Some(id.as_str()));
+        }
+
+        let untrusted = RequestIdMiddleware::new()
+            .trust_incoming(false)
+            .format(RequestIdFormat::Ulid)
+            .header("X-Correlation-Id");
+        let (id, res) = run(&untrusted, "X-Correlation-Id: abc\r\n").await;
+        assert_eq!(id.len(), 26);
+        assert_eq!(res.get_header("X-Correlation-Id"), Some(id.as_str()));
+    }
+}
@@ -0,0 +1,627 @@
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::random::random_bytes;
+use crate::request::Request;
+use crate::response::Response;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fmt;
+use std::time::Duration;
+use suika_templates::template_value::TemplateValue;
+
+/// A `Content-Security-Policy`, built from directives and their sources.
+///
+/// With `nonce` enabled, every response gets a fresh nonce that is added to
+/// the `script-src` and `style-src` directives and exposed to templates as
+/// the `csp_nonce` variable.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::security_headers::ContentSecurityPolicy;
+///
+/// let csp = ContentSecurityPolicy::new()
+///     .directive("img-src", &["'self'", "data:"])
+///     .nonce(true);
+///
+/// assert_eq!(
+///     csp.header_value(Some("abc")),
+///     "default-src 'self'; base-uri 'self'; form-action 'self'; object-src 'none'; \
+///      img-src 'self' data:; script-src 'self' 'nonce-abc'; style-src 'self' 'nonce-abc'"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<String>)>,
+    nonce: bool,
+    report_only: bool,
+}
+
+impl Default for ContentSecurityPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentSecurityPolicy {
+    /// Creates a policy only allowing resources from the page's own origin:
+    /// `default-src 'self'; base-uri 'self'; form-action 'self';
+    /// object-src 'none'`.
+    pub fn new() -> Self {
+        Self::empty()
+            .directive("default-src", &["'self'"])
+            .directive("base-uri", &["'self'"])
+            .directive("form-action", &["'self'"])
+            .directive("object-src", &["'none'"])
+    }
+
+    /// Creates a policy without any directives.
+    pub fn empty() -> Self {
+        Self {
+            directives: Vec::new(),
+            nonce: false,
+            report_only: false,
+        }
+    }
+
+    /// Sets the sources of a directive, replacing any previous ones. A
+    /// directive without sources, such as `upgrade-insecure-requests`, is
+    /// set with an empty slice.
+    pub fn directive(mut self, name: &str, sources: &[&str]) -> Self {
+        let sources = sources.iter().map(|source| source.to_string()).collect();
+        match self
+            .directives
+            .iter_mut()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        {
+            Some((_, existing)) => *existing = sources,
+            None => self.directives.push((name.to_string(), sources)),
+        }
+        self
+    }
+
+    /// Removes a directive.
+    pub fn remove(mut self, name: &str) -> Self {
+        self.directives
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+        self
+    }
+
+    /// Sets whether a per-response nonce is added to `script-src` and
+    /// `style-src`.
+    pub fn nonce(mut self, enabled: bool) -> Self {
+        self.nonce = enabled;
+        self
+    }
+
+    /// Sets whether the policy is only reported, through
+    /// `Content-Security-Policy-Report-Only`, instead of enforced.
+    pub fn report_only(mut self, enabled: bool) -> Self {
+        self.report_only = enabled;
+        self
+    }
+
+    /// Returns the sources of a directive.
+    pub fn get_directive(&self, name: &str) -> Option<&[String]> {
+        self.directives
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, sources)| sources.as_slice())
+    }
+
+    /// Returns `true` if a nonce is generated for every response.
+    pub fn uses_nonce(&self) -> bool {
+        self.nonce
+    }
+
+    /// Returns the name of the header the policy is sent in.
+    pub fn header_name(&self) -> &'static str {
+        if self.report_only {
+            "Content-Security-Policy-Report-Only"
+        } else {
+            "Content-Security-Policy"
+        }
+    }
+
+    /// Formats the policy as a header value.
+    ///
+    /// # Arguments
+    ///
+    /// * `nonce` - The nonce of the response. Directives it is added to
+    ///   that are missing start from the `default-src` sources, and
+    ///   `'none'` is dropped from them.
+    pub fn header_value(&self, nonce: Option<&str>) -> String {
+        let mut directives = self.directives.clone();
+        if let Some(nonce) = nonce.filter(|_| self.nonce) {
+            let defaults = self
+                .get_directive("default-src")
+                .map(<[String]>::to_vec)
+                .unwrap_or_default();
+            for name in ["script-src", "style-src"] {
+                let source = format!("'nonce-{}'", nonce);
+                let sources = match directives
+                    .iter_mut()
+                    .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+                {
+                    Some((_, sources)) => sources,
+                    None => {
+                        directives.push((name.to_string(), defaults.clone()));
+                        &mut directives.last_mut().unwrap().1
+                    }
+                };
+                // 'none' cannot be combined with other sources.
+                sources.retain(|existing| existing != "'none'");
+                sources.push(source);
+            }
+        }
+
+        directives
+            .iter()
+            .map(|(name, sources)| {
+                if sources.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{} {}", name, sources.join(" "))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// A `Strict-Transport-Security` policy telling browsers to only use HTTPS.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::security_headers::Hsts;
+/// use std::time::Duration;
+///
+/// let hsts = Hsts::new(Duration::from_secs(63072000)).preload(true);
+/// assert_eq!(hsts.to_string(), "max-age=63072000; includeSubDomains; preload");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hsts {
+    max_age: Duration,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Hsts {
+    /// Creates a policy covering subdomains for `max_age`.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            include_subdomains: true,
+            preload: false,
+        }
+    }
+
+    /// Sets whether the policy covers subdomains.
+    pub fn include_subdomains(mut self, enabled: bool) -> Self {
+        self.include_subdomains = enabled;
+        self
+    }
+
+    /// Sets whether the domain asks to be included in browsers' preload
+    /// lists.
+    pub fn preload(mut self, enabled: bool) -> Self {
+        self.preload = enabled;
+        self
+    }
+}
+
+impl fmt::Display for Hsts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max-age={}", self.max_age.as_secs())?;
+        if self.include_subdomains {
+            write!(f, "; includeSubDomains")?;
+        }
+        if self.preload {
+            write!(f, "; preload")?;
+        }
+        Ok(())
+    }
+}
+
+/// The pages allowed to embed responses in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOptions {
+    /// No page may embed the response.
+    Deny,
+    /// Only pages from the same origin may embed the response.
+    SameOrigin,
+}
+
+impl FrameOptions {
+    /// Returns the `X-Frame-Options` value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "DENY",
+            FrameOptions::SameOrigin => "SAMEORIGIN",
+        }
+    }
+
+    /// Returns the equivalent `frame-ancestors` source.
+    pub fn frame_ancestors(&self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "'none'",
+            FrameOptions::SameOrigin => "'self'",
+        }
+    }
+}
+
+/// A middleware component adding security headers to every response.
+///
+/// The defaults are:
+///
+/// * `Content-Security-Policy: default-src 'self'; base-uri 'self';
+///   form-action 'self'; object-src 'none'; frame-ancestors 'none'`
+/// * `Strict-Transport-Security: max-age=31536000; includeSubDomains`
+/// * `X-Content-Type-Options: nosniff`
+/// * `X-Frame-Options: DENY`
+/// * `Referrer-Policy: strict-origin-when-cross-origin`
+/// * `Cross-Origin-Opener-Policy: same-origin`
+/// * `Cross-Origin-Resource-Policy: same-origin`
+///
+/// `Permissions-Policy` and `Cross-Origin-Embedder-Policy` are only sent
+/// when configured. Every header can be disabled by setting it to `None`,
+/// and headers already set by a handler are kept. The `frame-ancestors`
+/// directive follows `X-Frame-Options` unless the policy sets it.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::security_headers::{
+///     ContentSecurityPolicy, FrameOptions, SecurityHeadersMiddleware,
+/// };
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(
+///     SecurityHeadersMiddleware::new()
+///         .content_security_policy(Some(
+///             ContentSecurityPolicy::new()
+///                 .directive("img-src", &["'self'", "https://cdn.example.com"])
+///                 .nonce(true),
+///         ))
+///         .frame_options(Some(FrameOptions::SameOrigin))
+///         .permissions_policy(Some("camera=(), geolocation=()")),
+/// ));
+/// ```
+///
+/// With nonces enabled, templates mark their inline scripts with it:
+///
+/// ```html
+/// <script nonce="<%= csp_nonce %>">...</script>
+/// ```
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersMiddleware {
+    csp: Option<ContentSecurityPolicy>,
+    hsts: Option<Hsts>,
+    content_type_options: bool,
+    frame_options: Option<FrameOptions>,
+    referrer_policy: Option<String>,
+    permissions_policy: Option<String>,
+    cross_origin_opener_policy: Option<String>,
+    cross_origin_embedder_policy: Option<String>,
+    cross_origin_resource_policy: Option<String>,
+}
+
+impl Default for SecurityHeadersMiddleware {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecurityHeadersMiddleware {
+    /// Creates a new `SecurityHeadersMiddleware` with the default headers.
+    pub fn new() -> Self {
+        Self {
+            csp: Some(ContentSecurityPolicy::new()),
+            hsts: Some(Hsts::new(Duration::from_secs(365 * 24 * 60 * 60))),
+            content_type_options: true,
+            frame_options: Some(FrameOptions::Deny),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            permissions_policy: None,
+            cross_origin_opener_policy: Some("same-origin".to_string()),
+            cross_origin_embedder_policy: None,
+            cross_origin_resource_policy: Some("same-origin".to_string()),
+        }
+    }
+
+    /// Sets the `Content-Security-Policy`.
+    pub fn content_security_policy(mut self, csp: Option<ContentSecurityPolicy>) -> Self {
+        self.csp = csp;
+        self
+    }
+
+    /// Sets the `Strict-Transport-Security` policy.
+    pub fn hsts(mut self, hsts: Option<Hsts>) -> Self {
+        self.hsts = hsts;
+        self
+    }
+
+    /// Sets whether `X-Content-Type-Options: nosniff` is sent.
+    pub fn content_type_options(mut self, enabled: bool) -> Self {
+        self.content_type_options = enabled;
+        self
+    }
+
+    /// Sets `X-Frame-Options` and the default `frame-ancestors` directive.
+    pub fn frame_options(mut self, frame_options: Option<FrameOptions>) -> Self {
+        self.frame_options = frame_options;
+        self
+    }
+
+    /// Sets the `Referrer-Policy`, e.g. `no-referrer`.
+    pub fn referrer_policy(mut self, policy: Option<&str>) -> Self {
+        self.referrer_policy = policy.map(str::to_string);
+        self
+    }
+
+    /// Sets the `Permissions-Policy`, e.g. `camera=(), microphone=()`.
+    pub fn permissions_policy(mut self, policy: Option<&str>) -> Self {
+        self.permissions_policy = policy.map(str::to_string);
+        self
+    }
+
+    /// Sets the `Cross-Origin-Opener-Policy`, e.g. `same-origin-allow-popups`.
+    pub fn cross_origin_opener_policy(mut self, policy: Option<&str>) -> Self {
+        self.cross_origin_opener_policy = policy.map(str::to_string);
+        self
+    }
+
+    /// Sets the `Cross-Origin-Embedder-Policy`, e.g. `require-corp`.
+    pub fn cross_origin_embedder_policy(mut self, policy: Option<&str>) -> Self {
+        self.cross_origin_embedder_policy = policy.map(str::to_string);
+        self
+    }
+
+    /// Sets the `Cross-Origin-Resource-Policy`, e.g. `same-site`.
+    pub fn cross_origin_resource_policy(mut self, policy: Option<&str>) -> Self {
+        self.cross_origin_resource_policy = policy.map(str::to_string);
+        self
+    }
+
+    /// Returns the headers sent with a response using `nonce`.
+    fn headers(&self, nonce: Option<&str>) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+
+        if let Some(csp) = &self.csp {
+            let csp = match self.frame_options {
+                Some(frame_options) if csp.get_directive("frame-ancestors").is_none() => csp
+                    .clone()
+                    .directive("frame-ancestors", &[frame_options.frame_ancestors()]),
+                _ => csp.clone(),
+            };
+            headers.push((csp.header_name(), csp.header_value(nonce)));
+        }
+        if let Some(hsts) = self.hsts {
+            headers.push(("Strict-Transport-Security", hsts.to_string()));
+        }
+        if self.content_type_options {
+            headers.push(("X-Content-Type-Options", "nosniff".to_string()));
+        }
+        if let Some(frame_options) = self.frame_options {
+            headers.push(("X-Frame-Options", frame_options.as_str().to_string()));
+        }
+
+        for (name, value) in [
+            ("Referrer-Policy", &self.referrer_policy),
+            ("Permissions-Policy", &self.permissions_policy),
+            (
+                "Cross-Origin-Opener-Policy",
+                &self.cross_origin_opener_policy,
+            ),
+            (
+                "Cross-Origin-Embedder-Policy",
+                &self.cross_origin_embedder_policy,
+            ),
+            (
+                "Cross-Origin-Resource-Policy",
+                &self.cross_origin_resource_policy,
+            ),
+        ] {
+            if let Some(value) = value {
+                headers.push((name, value.clone()));
+            }
+        }
+
+        headers
+    }
+}
+
+impl Middleware for SecurityHeadersMiddleware {
+    /// Handles an incoming HTTP request by adding the security headers to
+    /// its response.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let nonce = self
+                .csp
+                .as_ref()
+                .filter(|csp| csp.uses_nonce())
+                .map(|_| STANDARD.encode(random_bytes::<16>()));
+            if let Some(nonce) = &nonce {
+                res.template_var("csp_nonce", TemplateValue::String(nonce.clone()));
+            }
+
+            let result = next.run(req, res).await;
+
+            for (name, value) in self.headers(nonce.as_deref()) {
+                if res.get_header(name).is_none() {
+                    res.header(name, &value);
+                }
+            }
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::Body;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    struct NonceHandler;
+
+    impl Middleware for NonceHandler {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                let nonce = match res.get_template_var("csp_nonce") {
+                    Some(TemplateValue::String(nonce)) => nonce.clone(),
+                    _ => String::new(),
+                };
+                let mut replacement = Response::default();
+                replacement
+                    .set_status(200)
+                    .header("X-Frame-Options", "SAMEORIGIN")
+                    .body(nonce);
+                res.replace_with(replacement);
+                Ok(())
+            })
+        }
+    }
+
+    async fn run(middleware: &SecurityHeadersMiddleware) -> Response {
+        let mut req = Request::new(
+            "GET / HTTP/1.1\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(NonceHandler)];
+
+        middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        res
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_defaults() {
+        let res = run(&SecurityHeadersMiddleware::new()).await;
+
+        assert_eq!(
+            res.get_header("Content-Security-Policy"),
+            Some(
+                "default-src 'self'; base-uri 'self'; form-action 'self'; object-src 'none'; \
+                 frame-ancestors 'none'"
+            )
+        );
+        assert_eq!(
+            res.get_header("Strict-Transport-Security"),
+            Some("max-age=31536000; includeSubDomains")
+        );
+        assert_eq!(res.get_header("X-Content-Type-Options"), Some("nosniff"));
+        assert_eq!(res.get_header("X-Frame-Options"), Some("SAMEORIGIN"));
+        assert_eq!(
+            res.get_header("Referrer-Policy"),
+            Some("strict-origin-when-cross-origin")
+        );
+        assert_eq!(
+            res.get_header("Cross-Origin-Opener-Policy"),
+            Some("same-origin")
+        );
+        assert_eq!(
+            res.get_header("Cross-Origin-Resource-Policy"),
+            Some("same-origin")
+        );
+        assert_eq!(res.get_header("Permissions-Policy"), None);
+        assert_eq!(res.get_header("Cross-Origin-Embedder-Policy"), None);
+        assert!(res.get_template_var("csp_nonce").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_security_headers_builder() {
+        let middleware = SecurityHeadersMiddleware::new()
+            .content_security_policy(Some(
+                ContentSecurityPolicy::empty()
+                    .directive("default-src", &["'none'"])
+                    .directive("script-src", &["'self'"])
+                    .directive("upgrade-insecure-requests", &[])
+                    .nonce(true)
+                    .report_only(true),
+            ))
+            .hsts(None)
+            .content_type_options(false)
+            .frame_options(Some(FrameOptions::SameOrigin))
+            .referrer_policy(Some("no-referrer"))
+            .permissions_policy(Some("camera=()"))
+            .cross_origin_embedder_policy(Some("require-corp"))
+            .cross_origin_opener_policy(None)
+            .cross_origin_resource_policy(None);
+
+        let res = run(&middleware).await;
+        let nonce = match res.get_template_var("csp_nonce") {
+            Some(TemplateValue::String(nonce)) => nonce.clone(),
+            other => panic!("Expected a csp_nonce template var, got {:?}", other),
+        };
+        assert_eq!(nonce.len(), 24);
+        assert_eq!(res.get_body(), Some(&Body::Text(nonce.clone())));
+
+        assert_eq!(res.get_header("Content-Security-Policy"), None);
+        assert_eq!(
+            res.get_header("Content-Security-Policy-Report-Only"),
+            Some(
+                format!(
+                    "default-src 'none'; script-src 'self' 'nonce-{0}'; upgrade-insecure-requests; \
+                     frame-ancestors 'self'; style-src 'nonce-{0}'",
+                    nonce
+                )
+                .as_str()
+            )
+        );
+        assert_eq!(res.get_header("Strict-Transport-Security"), None);
+        assert_eq!(res.get_header("X-Content-Type-Options"), None);
+        assert_eq!(res.get_header("Referrer-Policy"), Some("no-referrer"));
+        assert_eq!(res.get_header("Permissions-Policy"), Some("camera=()"));
+        assert_eq!(
+            res.get_header("Cross-Origin-Embedder-Policy"),
+            Some("require-corp")
+        );
+        assert_eq!(res.get_header("Cross-Origin-Opener-Policy"), None);
+        assert_eq!(res.get_header("Cross-Origin-Resource-Policy"), None);
+
+        let second = run(&middleware).await;
+        assert_ne!(
+            second.get_template_var("csp_nonce"),
+            res.get_template_var("csp_nonce")
+        );
+    }
+
+    #[test]
+    fn test_content_security_policy_nonce_ignores_directive_case() {
+        let policy = ContentSecurityPolicy::empty()
+            .directive("Script-Src", &["'self'"])
+            .nonce(true);
+
+        assert_eq!(
+            policy.header_value(Some("abc")),
+            "Script-Src 'self' 'nonce-abc'; style-src 'nonce-abc'"
+        );
+    }
+}
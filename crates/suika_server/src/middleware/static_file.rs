@@ -1,19 +1,60 @@
+use crate::diagnostics::debug;
 use crate::error::HttpError;
+use crate::middleware::compression::{add_vary_accept_encoding, negotiate_encoding, Encoding};
 use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::normalize::{has_trailing_slash, normalize_path, TrailingSlash};
 use crate::request::Request;
-use crate::response::Response;
+use crate::response::{RedirectKind, Response};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::RwLock;
 use suika_mime::get_mime_type;
+use suika_utils::build_url;
+
+/// The precompressed siblings found next to a static file.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Variants {
+    brotli: bool,
+    gzip: bool,
+}
+
+impl Variants {
+    fn encodings(&self) -> Vec<Encoding> {
+        let mut encodings = Vec::new();
+        if self.brotli {
+            encodings.push(Encoding::Brotli);
+        }
+        if self.gzip {
+            encodings.push(Encoding::Gzip);
+        }
+        encodings
+    }
+}
+
+/// Returns the path of the precompressed sibling for an encoding.
+fn variant_path(path: &str, encoding: Encoding) -> String {
+    match encoding {
+        Encoding::Brotli => format!("{}.br", path),
+        Encoding::Gzip => format!("{}.gz", path),
+        Encoding::Deflate => path.to_string(),
+    }
+}
 
 async fn handle_static_file_request(
     path: String,
+    encoding: Option<Encoding>,
     cache_duration: u64,
     res: &mut Response,
 ) -> Result<(), HttpError> {
-    if Path::new(&path).exists() {
-        if let Err(e) = res.send_file(&path).await {
-            res.set_status(500).await;
-            res.body(format!("Internal Server Error: {}", e)).await;
+    let file_path = match encoding {
+        Some(encoding) => variant_path(&path, encoding),
+        None => path.clone(),
+    };
+
+    if Path::new(&file_path).exists() {
+        if let Err(e) = res.send_file(&file_path).await {
+            res.set_status(500);
+            res.body(format!("Internal Server Error: {}", e));
             return Err(HttpError::InternalServerError(format!(
                 "Internal Server Error: {}",
                 e
@@ -24,28 +65,45 @@ async fn handle_static_file_request(
                 .and_then(|ext| ext.to_str())
                 .map(get_mime_type)
                 .unwrap_or_else(|| "application/octet-stream".to_string());
-            res.header("Content-Type", mime_type.as_str()).await;
+            res.header("Content-Type", mime_type.as_str());
+
+            if let Some(encoding) = encoding {
+                res.header("Content-Encoding", encoding.as_str());
+            }
 
             res.header(
                 "Cache-Control",
                 &format!("public, max-age={}", cache_duration),
-            )
-            .await;
+            );
 
-            res.set_status(200).await;
+            res.set_status(200);
         }
         Ok(())
     } else {
-        println!("File not found: {}", path);
+        debug!("File not found: {}", file_path);
         Err(HttpError::NotFound("File not found".to_string()))
     }
 }
 
 /// A middleware component for serving static files.
+///
+/// When a client accepts `br` or `gzip`, precompressed `.br` and `.gz`
+/// siblings of the requested file are served in its place, keeping the
+/// original file's MIME type. The siblings found for each file are cached; if
+/// a cached sibling has since been removed, the original file is served and
+/// the siblings are looked up again on the next request.
+///
+/// Request paths are normalized before the file is looked up, so `..`
+/// segments can never reach outside the directory. Files never have a
+/// trailing `/`: by default `/static/app.js/` serves `app.js`, which can be
+/// changed with `trailing_slash`.
 pub struct StaticFileMiddleware {
     url_prefix: String,
     directory: String,
     cache_duration: u64,
+    precompressed: bool,
+    trailing_slash: TrailingSlash,
+    variants: RwLock<HashMap<String, Variants>>,
 }
 
 impl StaticFileMiddleware {
@@ -69,8 +127,100 @@ impl StaticFileMiddleware {
             url_prefix: url_prefix.to_string(),
             directory: directory.to_string(),
             cache_duration,
+            precompressed: true,
+            trailing_slash: TrailingSlash::default(),
+            variants: RwLock::new(HashMap::new()),
         }
     }
+
+    /// Enables or disables serving precompressed `.br` and `.gz` siblings.
+    /// Enabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::StaticFileMiddleware;
+    ///
+    /// let static_file_middleware =
+    ///     StaticFileMiddleware::new("/static", "public", 3600).precompressed(false);
+    /// ```
+    pub fn precompressed(mut self, enabled: bool) -> Self {
+        self.precompressed = enabled;
+        self
+    }
+
+    /// Sets how a trailing `/` in request paths is treated.
+    ///
+    /// With `TrailingSlash::MatchBoth`, the default, the `/` is ignored. With
+    /// `Strict`, such requests are passed to the next middleware, and with
+    /// `Redirect` they are redirected to the path without the `/` when the
+    /// file exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::StaticFileMiddleware;
+    /// use suika_server::normalize::TrailingSlash;
+    ///
+    /// let static_file_middleware =
+    ///     StaticFileMiddleware::new("/static", "public", 3600).trailing_slash(TrailingSlash::Redirect);
+    /// ```
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = policy;
+        self
+    }
+
+    /// Maps a request path to a file path, or `None` if the path is not under
+    /// the URL prefix.
+    fn file_path(&self, request_path: &str) -> Option<String> {
+        let stripped = request_path.strip_prefix(self.url_prefix.trim_end_matches('/'))?;
+        if !(stripped.is_empty() || stripped.starts_with('/')) {
+            return None;
+        }
+        let relative = normalize_path(stripped);
+        Some(format!(
+            "{}{}",
+            self.directory.trim_end_matches('/'),
+            relative.trim_end_matches('/')
+        ))
+    }
+
+    /// Looks up the precompressed siblings of an existing file, checking the
+    /// disk only on the first request for that file.
+    fn lookup_variants(&self, path: &str) -> Option<Variants> {
+        if let Some(variants) = self.variants.read().unwrap().get(path) {
+            return Some(*variants);
+        }
+
+        if !Path::new(path).is_file() {
+            return None;
+        }
+
+        let variants = Variants {
+            brotli: Path::new(&variant_path(path, Encoding::Brotli)).is_file(),
+            gzip: Path::new(&variant_path(path, Encoding::Gzip)).is_file(),
+        };
+
+        self.variants
+            .write()
+            .unwrap()
+            .insert(path.to_string(), variants);
+        Some(variants)
+    }
+
+    /// Chooses the precompressed variant to serve for a request, if any.
+    fn select_encoding(&self, path: &str, accept_encoding: Option<&str>) -> Option<Encoding> {
+        if !self.precompressed {
+            return None;
+        }
+
+        let encodings = self.lookup_variants(path)?.encodings();
+        if encodings.is_empty() {
+            return None;
+        }
+
+        negotiate_encoding(accept_encoding.unwrap_or(""), &encodings)
+    }
 }
 
 impl Middleware for StaticFileMiddleware {
@@ -91,20 +241,60 @@ impl Middleware for StaticFileMiddleware {
         res: &'a mut Response,
         mut next: Next<'a>,
     ) -> MiddlewareFuture<'a> {
-        let url_prefix = self.url_prefix.clone();
-        let directory = self.directory.clone();
         let cache_duration = self.cache_duration;
 
         Box::pin(async move {
-            let path = if let Some(stripped_path) = req.path().strip_prefix(&url_prefix) {
-                format!("{}{}", directory, stripped_path)
-            } else {
+            let Some(path) = self.file_path(req.path()) else {
                 return next.run(req, res).await;
             };
 
-            match handle_static_file_request(path, cache_duration, res).await {
-                Ok(_) => Ok(()),
-                Err(_) => next.run(req, res).await,
+            if has_trailing_slash(req.path()) {
+                match self.trailing_slash {
+                    TrailingSlash::MatchBoth => {}
+                    TrailingSlash::Strict => return next.run(req, res).await,
+                    TrailingSlash::Redirect => {
+                        if !Path::new(&path).is_file() {
+                            return next.run(req, res).await;
+                        }
+                        let query = req
+                            .query_params()
+                            .iter()
+                            .map(|(key, value)| (key.as_str(), value.as_str()))
+                            .collect();
+                        let location = build_url(req.path().trim_end_matches('/'), &query);
+                        res.redirect(&location, RedirectKind::PermanentRedirect)?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            let encoding = self.select_encoding(&path, req.get_header("Accept-Encoding"));
+            let has_variants = self
+                .variants
+                .read()
+                .unwrap()
+                .get(&path)
+                .is_some_and(|variants| *variants != Variants::default());
+
+            let mut result =
+                handle_static_file_request(path.clone(), encoding, cache_duration, res).await;
+            if encoding.is_some() && matches!(result, Err(HttpError::NotFound(_))) {
+                // The cached sibling was removed since it was looked up.
+                self.variants.write().unwrap().remove(&path);
+                result = handle_static_file_request(path.clone(), None, cache_duration, res).await;
+            }
+
+            match result {
+                Ok(_) => {
+                    if has_variants {
+                        add_vary_accept_encoding(res);
+                    }
+                    Ok(())
+                }
+                Err(_) => {
+                    self.variants.write().unwrap().remove(&path);
+                    next.run(req, res).await
+                }
             }
         })
     }
@@ -187,15 +377,14 @@ mod tests {
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
+        assert_eq!(res.status(), Some(200));
         assert_eq!(
-            inner.headers().get("Content-Type"),
+            res.headers().get("Content-Type"),
             Some(&"text/plain".to_string())
         );
         assert_eq!(
-            inner.body(),
-            &Some(Body::Binary(b"fake file data".to_vec()))
+            res.get_body(),
+            Some(&Body::Binary(b"fake file data".to_vec()))
         );
 
         let next_called = *next_middleware.called.lock().await;
@@ -209,7 +398,7 @@ mod tests {
             Arc::new(Mutex::new(HashMap::new())),
         )
         .unwrap();
-    
+
         let mut res = Response::new(None);
 
         let static_file_middleware = StaticFileMiddleware::new("/static", "some/directory", 3600);
@@ -226,4 +415,258 @@ mod tests {
         let next_called = *next_middleware.called.lock().await;
         assert!(next_called);
     }
+
+    fn static_request(path: &str, accept_encoding: Option<&str>) -> Request {
+        let header = accept_encoding
+            .map(|value| format!("Accept-Encoding: {}\r\n", value))
+            .unwrap_or_default();
+        Request::new(
+            &format!("GET {} HTTP/1.1\r\n{}\r\n", path, header),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_serves_precompressed_variants() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.br"), b"brotli").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzip").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", dir.path().to_str().unwrap(), 3600);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![];
+
+        let cases = [
+            (Some("gzip, br"), Some("br"), b"brotli".to_vec()),
+            (Some("gzip"), Some("gzip"), b"gzip".to_vec()),
+            (Some("br;q=0.1, gzip"), Some("gzip"), b"gzip".to_vec()),
+            (Some("identity"), None, b"plain".to_vec()),
+            (None, None, b"plain".to_vec()),
+        ];
+
+        for (accept_encoding, content_encoding, body) in cases {
+            let mut req = static_request("/static/app.js", accept_encoding);
+            let mut res = Response::new(None);
+
+            static_file_middleware
+                .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                res.headers().get("Content-Type"),
+                Some(&"application/javascript".to_string())
+            );
+            assert_eq!(
+                res.headers().get("Content-Encoding").map(|s| s.as_str()),
+                content_encoding
+            );
+            assert_eq!(
+                res.headers().get("Vary"),
+                Some(&"Accept-Encoding".to_string())
+            );
+            assert_eq!(res.get_body(), Some(&Body::Binary(body)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_matches_accept_encoding_case_insensitively() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzip").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", dir.path().to_str().unwrap(), 3600);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![];
+
+        let mut req = Request::new(
+            "GET /static/app.js HTTP/1.1\r\naccept-encoding: gzip\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+        static_file_middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.get_header("Content-Encoding"), Some("gzip"));
+        assert_eq!(res.get_body(), Some(&Body::Binary(b"gzip".to_vec())));
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_caches_variant_lookups() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.css"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.css.gz"), b"gzip").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", dir.path().to_str().unwrap(), 3600);
+        let path = format!("{}/app.css", dir.path().to_str().unwrap());
+
+        assert_eq!(
+            static_file_middleware.select_encoding(&path, Some("gzip")),
+            Some(Encoding::Gzip)
+        );
+
+        // A sibling created after the first lookup is not picked up.
+        std::fs::write(dir.path().join("app.css.br"), b"brotli").unwrap();
+        assert_eq!(
+            static_file_middleware.select_encoding(&path, Some("br, gzip")),
+            Some(Encoding::Gzip)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_serves_file_when_cached_variant_is_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzip").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", dir.path().to_str().unwrap(), 3600);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(MockNextMiddleware::new())];
+
+        let mut req = static_request("/static/app.js", Some("gzip"));
+        let mut res = Response::new(None);
+        static_file_middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(res.get_body(), Some(&Body::Binary(b"gzip".to_vec())));
+
+        std::fs::remove_file(dir.path().join("app.js.gz")).unwrap();
+
+        let mut req = static_request("/static/app.js", Some("gzip"));
+        let mut res = Response::new(None);
+        static_file_middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(res.get_header("Content-Encoding"), None);
+        assert_eq!(res.get_body(), Some(&Body::Binary(b"plain".to_vec())));
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_appends_to_vary() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzip").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", dir.path().to_str().unwrap(), 3600);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![];
+
+        let mut req = static_request("/static/app.js", Some("gzip"));
+        let mut res = Response::new(None);
+        // Set by an outer middleware, such as CORS.
+        res.header("Vary", "Origin");
+        static_file_middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.get_header("Vary"), Some("Origin, Accept-Encoding"));
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_precompressed_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"gzip").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", dir.path().to_str().unwrap(), 3600)
+                .precompressed(false);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![];
+
+        let mut req = static_request("/static/app.js", Some("gzip"));
+        let mut res = Response::new(None);
+        static_file_middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+
+        assert_eq!(res.headers().get("Content-Encoding"), None);
+        assert_eq!(res.get_body(), Some(&Body::Binary(b"plain".to_vec())));
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_normalizes_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        let directory = dir.path().join("public");
+        std::fs::create_dir(&directory).unwrap();
+        std::fs::write(directory.join("site.css"), b"css").unwrap();
+
+        let static_file_middleware =
+            StaticFileMiddleware::new("/static", directory.to_str().unwrap(), 3600);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![];
+
+        let cases = [
+            ("/static//site.css", Some(Body::Binary(b"css".to_vec()))),
+            ("/static/site.css/", Some(Body::Binary(b"css".to_vec()))),
+            ("/static/../app.js", None),
+            ("/staticsite.css", None),
+        ];
+        for (path, body) in cases {
+            let mut req = static_request(path, None);
+            let mut res = Response::new(None);
+            static_file_middleware
+                .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+                .await
+                .unwrap();
+            assert_eq!(res.get_body(), body.as_ref(), "{}", path);
+        }
+
+        let mut req = static_request("/", None);
+        req.set_path("/static/../app.js".to_string());
+        let mut res = Response::new(None);
+        static_file_middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(res.get_body(), None);
+    }
+
+    #[tokio::test]
+    async fn test_static_file_middleware_trailing_slash_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), b"plain").unwrap();
+        let directory = dir.path().to_str().unwrap();
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![];
+
+        let strict = StaticFileMiddleware::new("/static", directory, 3600)
+            .trailing_slash(TrailingSlash::Strict);
+        let mut req = static_request("/static/app.js/", None);
+        let mut res = Response::new(None);
+        strict
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(res.get_body(), None);
+
+        let redirect = StaticFileMiddleware::new("/static", directory, 3600)
+            .trailing_slash(TrailingSlash::Redirect);
+        let mut req = static_request("/static/app.js/?v=2", None);
+        let mut res = Response::new(None);
+        redirect
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), Some(308));
+        assert_eq!(res.get_header("Location"), Some("/static/app.js?v=2"));
+
+        let mut req = static_request("/static/missing.js/", None);
+        let mut res = Response::new(None);
+        redirect
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), None);
+    }
 }
@@ -0,0 +1,243 @@
+use crate::diagnostics::warning;
+use crate::error::HttpError;
+use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::path_pattern::PathPattern;
+use crate::request::Request;
+use crate::response::Response;
+use regex::Regex;
+use std::time::Duration;
+
+/// The error returned for requests that time out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutStatus {
+    /// `503 Service Unavailable`.
+    ServiceUnavailable,
+    /// `504 Gateway Timeout`.
+    GatewayTimeout,
+}
+
+impl TimeoutStatus {
+    /// Returns the error for a request that took longer than `timeout`.
+    pub fn to_error(&self, timeout: Duration) -> HttpError {
+        let message = format!("Request timed out after {:?}", timeout);
+        match self {
+            TimeoutStatus::ServiceUnavailable => HttpError::ServiceUnavailable(message),
+            TimeoutStatus::GatewayTimeout => HttpError::GatewayTimeout(message),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RouteTimeout {
+    method: String,
+    regex: Regex,
+    timeout: Duration,
+}
+
+impl RouteTimeout {
+    fn matches(&self, req: &Request) -> bool {
+        (self.method == "*" || self.method.eq_ignore_ascii_case(req.method()))
+            && self.regex.is_match(req.path())
+    }
+}
+
+/// A middleware component bounding the time the rest of the middleware
+/// stack may take to handle a request.
+///
+/// Requests not handled in time are abandoned: the handler's future is
+/// dropped, whatever it wrote to the response, including cookies, is
+/// discarded and the request fails with `503 Service Unavailable`, or `504
+/// Gateway Timeout` when configured with `status`. Routes added with `route`
+/// have their own timeout.
+///
+/// Handlers are only interrupted when they yield, so blocking work should
+/// run in `tokio::task::spawn_blocking`.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::timeout::{TimeoutMiddleware, TimeoutStatus};
+/// use suika_server::server::Server;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let mut server = Server::new("127.0.0.1:8080");
+/// server.use_middleware(Arc::new(
+///     TimeoutMiddleware::new(Duration::from_secs(10))
+///         .route("POST", "/reports/:id", Duration::from_secs(60))
+///         .status(TimeoutStatus::GatewayTimeout),
+/// ));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TimeoutMiddleware {
+    timeout: Duration,
+    status: TimeoutStatus,
+    routes: Vec<RouteTimeout>,
+}
+
+impl TimeoutMiddleware {
+    /// Creates a new `TimeoutMiddleware` answering requests that take
+    /// longer than `timeout` with `503 Service Unavailable`.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            status: TimeoutStatus::ServiceUnavailable,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Sets the error returned for requests that time out.
+    pub fn status(mut self, status: TimeoutStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Sets the timeout of requests matching a method and a path pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The HTTP method, or `*` for any method.
+    /// * `pattern` - A route pattern, such as `/reports/:id`.
+    /// * `timeout` - The timeout of matching requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid route pattern.
+    pub fn route(mut self, method: &str, pattern: &str, timeout: Duration) -> Self {
+        let path_pattern = PathPattern::parse(pattern).unwrap_or_else(|e| panic!("{}", e));
+        self.routes.push(RouteTimeout {
+            method: method.to_ascii_uppercase(),
+            regex: path_pattern.to_regex(),
+            timeout,
+        });
+        self
+    }
+
+    /// Returns the timeout of a request.
+    pub fn timeout_for(&self, req: &Request) -> Duration {
+        self.routes
+            .iter()
+            .find(|route| route.matches(req))
+            .map_or(self.timeout, |route| route.timeout)
+    }
+}
+
+impl Middleware for TimeoutMiddleware {
+    /// Handles an incoming HTTP request by running the rest of the stack
+    /// with a timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response to be sent.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        mut next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        Box::pin(async move {
+            let timeout = self.timeout_for(req);
+            match tokio::time::timeout(timeout, next.run(req, res)).await {
+                Ok(result) => result,
+                Err(_) => {
+                    warning!(
+                        "{} {} timed out after {:?}",
+                        req.method(),
+                        req.path(),
+                        timeout
+                    );
+                    res.reset();
+                    Err(self.status.to_error(timeout))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cookie::Cookie;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use suika_templates::template_value::TemplateValue;
+
+    struct SleepHandler;
+
+    impl Middleware for SleepHandler {
+        fn handle<'a>(
+            &'a self,
+            req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.header("X-Started", "true")
+                    .set_cookie(Cookie::new("session", "half-built"))
+                    .template_var("user", TemplateValue::String("alice".to_string()));
+                let millis = req.query_param("ms").unwrap_or("0").parse().unwrap();
+                tokio::time::sleep(Duration::from_millis(millis)).await;
+                res.set_status(200);
+                Ok(())
+            })
+        }
+    }
+
+    async fn run(
+        middleware: &TimeoutMiddleware,
+        request_line: &str,
+    ) -> (Result<(), HttpError>, Response) {
+        let mut req = Request::new(
+            &format!("{} HTTP/1.1\r\n\r\n", request_line),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(SleepHandler)];
+
+        let result = middleware
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await;
+        (result, res)
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let middleware = TimeoutMiddleware::new(Duration::from_millis(50));
+
+        let (result, res) = run(&middleware, "GET /fast?ms=0").await;
+        assert!(result.is_ok());
+        assert_eq!(res.status(), Some(200));
+
+        let (result, res) = run(&middleware, "GET /slow?ms=5000").await;
+        assert!(matches!(result, Err(HttpError::ServiceUnavailable(_))));
+        assert_eq!(res.get_header("X-Started"), None);
+        assert!(res.cookies().is_empty());
+        assert!(res.get_template_var("user").is_none());
+
+        let gateway = middleware.status(TimeoutStatus::GatewayTimeout);
+        let (result, _) = run(&gateway, "GET /slow?ms=5000").await;
+        assert!(matches!(result, Err(HttpError::GatewayTimeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_routes() {
+        let middleware = TimeoutMiddleware::new(Duration::from_millis(20)).route(
+            "POST",
+            "/reports/:id",
+            Duration::from_secs(5),
+        );
+
+        let (result, _) = run(&middleware, "POST /reports/1?ms=100").await;
+        assert!(result.is_ok());
+
+        let (result, _) = run(&middleware, "GET /reports/1?ms=100").await;
+        assert!(result.is_err());
+    }
+}
@@ -28,6 +28,18 @@ pub trait Middleware: Send + Sync {
         res: &'a mut Response,
         next: Next<'a>,
     ) -> MiddlewareFuture<'a>;
+
+    /// Returns a short name for the middleware, used when listing routes.
+    ///
+    /// Defaults to the name of the implementing type without its module path.
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let base = name.split('<').next().unwrap_or(name);
+        match base.rfind("::") {
+            Some(index) => &name[index + 2..],
+            None => name,
+        }
+    }
 }
 
 /// Represents the next middleware in the stack.
@@ -167,4 +179,11 @@ mod tests {
         assert_eq!(*counter1.lock().await, 1);
         assert_eq!(*counter2.lock().await, 1);
     }
+
+    #[test]
+    fn test_middleware_name() {
+        let middleware: Arc<dyn Middleware + Send + Sync> =
+            Arc::new(MockMiddleware::new(Arc::new(TokioMutex::new(0))));
+        assert_eq!(middleware.name(), "MockMiddleware");
+    }
 }
@@ -0,0 +1,117 @@
+/// How routers and static file middleware treat a trailing `/` in request paths.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::normalize::TrailingSlash;
+/// use suika_server::router::Router;
+///
+/// let mut router = Router::new("/");
+/// router.trailing_slash(TrailingSlash::Redirect);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+    /// Paths must match the pattern exactly: `/todo` only matches `/todo`
+    /// and `/todo/` only matches `/todo/`.
+    Strict,
+    /// Paths that only differ from the canonical form by a trailing `/` are
+    /// answered with a `308 Permanent Redirect` to it.
+    Redirect,
+    /// Paths match with or without a trailing `/`.
+    #[default]
+    MatchBoth,
+}
+
+/// Normalizes a request path.
+///
+/// Repeated slashes are collapsed and `.` and `..` segments are resolved,
+/// never going above the root. A trailing `/` is kept, so that the
+/// trailing-slash policy can be applied afterwards.
+///
+/// # Arguments
+///
+/// * `path` - The path to normalize.
+///
+/// # Returns
+///
+/// The normalized path, which always starts with `/`.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::normalize::normalize_path;
+///
+/// assert_eq!(normalize_path("/public//test.js"), "/public/test.js");
+/// assert_eq!(normalize_path("/a/./b/../c/"), "/a/c/");
+/// assert_eq!(normalize_path("/../../etc/passwd"), "/etc/passwd");
+/// assert_eq!(normalize_path(""), "/");
+/// ```
+pub fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    let ends_in_directory = path.ends_with('/')
+        || path.ends_with("/.")
+        || path.ends_with("/..")
+        || path == "."
+        || path == "..";
+    if ends_in_directory && normalized.len() > 1 {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// Returns `true` if the path ends with a `/` and is not the root.
+pub(crate) fn has_trailing_slash(path: &str) -> bool {
+    path.len() > 1 && path.ends_with('/')
+}
+
+/// Returns the path with its trailing `/` added or removed.
+pub(crate) fn toggle_trailing_slash(path: &str) -> String {
+    if has_trailing_slash(path) {
+        path.trim_end_matches('/').to_string()
+    } else {
+        format!("{}/", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/"), "/");
+        assert_eq!(normalize_path("//"), "/");
+        assert_eq!(normalize_path("/todo"), "/todo");
+        assert_eq!(normalize_path("/todo/"), "/todo/");
+        assert_eq!(
+            normalize_path("//public///css//site.css"),
+            "/public/css/site.css"
+        );
+        assert_eq!(normalize_path("/a/b/.."), "/a/");
+        assert_eq!(normalize_path("/a/b/."), "/a/b/");
+        assert_eq!(normalize_path("/a/../../.."), "/");
+        assert_eq!(normalize_path("/public/../../secret.txt"), "/secret.txt");
+        assert_eq!(normalize_path("relative/path"), "/relative/path");
+        assert_eq!(normalize_path("/a/..b/c."), "/a/..b/c.");
+    }
+
+    #[test]
+    fn test_trailing_slash_helpers() {
+        assert!(has_trailing_slash("/todo/"));
+        assert!(!has_trailing_slash("/todo"));
+        assert!(!has_trailing_slash("/"));
+        assert_eq!(toggle_trailing_slash("/todo"), "/todo/");
+        assert_eq!(toggle_trailing_slash("/todo/"), "/todo");
+    }
+}
@@ -0,0 +1,497 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use suika_utils::percent_encode;
+
+/// An error returned when a route path pattern cannot be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternError {
+    pattern: String,
+    message: String,
+}
+
+impl PatternError {
+    pub(crate) fn new(pattern: &str, message: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid route pattern {:?}: {}",
+            self.pattern, self.message
+        )
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// A single `/`-separated segment of a path pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// A literal segment, e.g. `users` in `/users/:id`.
+    Static(String),
+    /// A named parameter matching one segment, e.g. `:id`, `:id?` or `:id(\d+)`.
+    Param {
+        name: String,
+        constraint: Option<String>,
+        optional: bool,
+    },
+    /// A trailing wildcard matching the rest of the path, e.g. `*rest`.
+    Wildcard(String),
+}
+
+/// A parsed Express-style route path pattern.
+///
+/// The supported syntax is:
+///
+/// * `/users` - static segments, matched literally.
+/// * `/users/:id` - a named parameter matching a single segment.
+/// * `/users/:id(\d+)` - a parameter whose value must match the given regex.
+/// * `/posts/:year/:month?` - an optional parameter segment.
+/// * `/static/*path` - a trailing wildcard capturing the rest of the path.
+///
+/// Patterns are always matched against the whole path, and a single trailing
+/// slash is accepted.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::path_pattern::PathPattern;
+///
+/// let pattern = PathPattern::parse(r"/users/:id(\d+)/:tab?").unwrap();
+/// let regex = pattern.to_regex();
+///
+/// assert!(regex.is_match("/users/42"));
+/// assert!(regex.is_match("/users/42/posts/"));
+/// assert!(!regex.is_match("/users/abc"));
+/// assert!(!regex.is_match("/users/42/posts/archive"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathPattern {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl PathPattern {
+    /// Parses a path pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern to parse. A leading `/` is added if missing.
+    ///
+    /// # Returns
+    ///
+    /// The parsed `PathPattern`, or a `PatternError` describing why the
+    /// pattern is invalid.
+    pub fn parse(pattern: &str) -> Result<Self, PatternError> {
+        let source = if pattern.starts_with('/') {
+            pattern.to_string()
+        } else {
+            format!("/{}", pattern)
+        };
+
+        let mut segments = Vec::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '/' {
+                continue;
+            }
+            if let (Some(Segment::Wildcard(_)), Some(_)) = (segments.last(), chars.peek()) {
+                return Err(PatternError::new(
+                    &source,
+                    "a wildcard must be the last segment",
+                ));
+            }
+
+            match chars.peek() {
+                None | Some('/') => {}
+                Some(':') => {
+                    chars.next();
+                    let name = parse_name(&mut chars);
+                    if name.is_empty() {
+                        return Err(PatternError::new(&source, "missing parameter name"));
+                    }
+
+                    let constraint = if chars.peek() == Some(&'(') {
+                        chars.next();
+                        let constraint = parse_constraint(&mut chars)
+                            .ok_or_else(|| PatternError::new(&source, "unclosed constraint"))?;
+                        Regex::new(&constraint).map_err(|e| {
+                            PatternError::new(&source, format!("invalid constraint: {}", e))
+                        })?;
+                        Some(constraint)
+                    } else {
+                        None
+                    };
+
+                    let optional = chars.peek() == Some(&'?');
+                    if optional {
+                        chars.next();
+                    }
+
+                    segments.push(Segment::Param {
+                        name,
+                        constraint,
+                        optional,
+                    });
+                }
+                Some('*') => {
+                    chars.next();
+                    let name = parse_name(&mut chars);
+                    let name = if name.is_empty() {
+                        "wildcard".to_string()
+                    } else {
+                        name
+                    };
+                    segments.push(Segment::Wildcard(name));
+                }
+                Some(_) => {
+                    let mut text = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c == '/' {
+                            break;
+                        }
+                        text.push(c);
+                        chars.next();
+                    }
+                    segments.push(Segment::Static(text));
+                    continue;
+                }
+            }
+
+            if !matches!(chars.peek(), None | Some('/')) {
+                return Err(PatternError::new(
+                    &source,
+                    "parameters and wildcards must span a whole segment",
+                ));
+            }
+        }
+
+        let mut names: Vec<&str> = Vec::new();
+        for segment in &segments {
+            if let Segment::Param { name, .. } | Segment::Wildcard(name) = segment {
+                if names.contains(&name.as_str()) {
+                    return Err(PatternError::new(
+                        &source,
+                        format!("duplicate parameter name {:?}", name),
+                    ));
+                }
+                names.push(name);
+            }
+        }
+
+        Ok(Self { source, segments })
+    }
+
+    /// Returns the pattern as it was parsed.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the segments of the pattern.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Compiles the pattern into an anchored regex with a named capture group
+    /// for every parameter and wildcard.
+    pub fn to_regex(&self) -> Regex {
+        let mut regex = String::from("^");
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(text) => {
+                    regex.push('/');
+                    regex.push_str(&regex::escape(text));
+                }
+                Segment::Param {
+                    name,
+                    constraint,
+                    optional,
+                } => {
+                    let group = format!(
+                        "/(?P<{}>{})",
+                        name,
+                        constraint
+                            .as_ref()
+                            .map(|c| format!("(?:{})", c))
+                            .unwrap_or_else(|| "[^/]+".to_string())
+                    );
+                    if *optional {
+                        regex.push_str(&format!("(?:{})?", group));
+                    } else {
+                        regex.push_str(&group);
+                    }
+                }
+                Segment::Wildcard(name) => {
+                    regex.push_str(&format!("(?:/(?P<{}>.*))?", name));
+                }
+            }
+        }
+        regex.push_str("/?$");
+        Regex::new(&regex).expect("Path patterns always compile to a valid regex")
+    }
+
+    /// Returns the names of the pattern's parameters and wildcard, in order.
+    pub fn param_names(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Param { name, .. } | Segment::Wildcard(name) => Some(name.as_str()),
+            Segment::Static(_) => None,
+        })
+    }
+
+    /// Builds a path matching the pattern from parameter values.
+    ///
+    /// Values are percent-encoded, except for the `/` separators of a
+    /// wildcard value. Optional parameters and wildcards without a value are
+    /// left out, and parameters not used by the pattern are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `params` - The values of the pattern's parameters, by name.
+    ///
+    /// # Returns
+    ///
+    /// The path, or a `PatternError` if a required parameter is missing or a
+    /// value does not match its constraint.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::path_pattern::PathPattern;
+    /// use std::collections::HashMap;
+    ///
+    /// let pattern = PathPattern::parse(r"/users/:id(\d+)/:tab?").unwrap();
+    ///
+    /// let params = HashMap::from([("id", "42")]);
+    /// assert_eq!(pattern.to_path(&params).unwrap(), "/users/42");
+    ///
+    /// let params = HashMap::from([("id", "42"), ("tab", "recent posts")]);
+    /// assert_eq!(pattern.to_path(&params).unwrap(), "/users/42/recent%20posts");
+    ///
+    /// let params = HashMap::from([("id", "me")]);
+    /// assert!(pattern.to_path(&params).is_err());
+    /// ```
+    pub fn to_path(&self, params: &HashMap<&str, &str>) -> Result<String, PatternError> {
+        let mut path = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(text) => {
+                    path.push('/');
+                    path.push_str(text);
+                }
+                Segment::Param {
+                    name,
+                    constraint,
+                    optional,
+                } => {
+                    let Some(value) = params.get(name.as_str()) else {
+                        if *optional {
+                            continue;
+                        }
+                        return Err(PatternError::new(
+                            &self.source,
+                            format!("missing parameter {:?}", name),
+                        ));
+                    };
+                    if let Some(constraint) = constraint {
+                        let matcher = Regex::new(&format!("^(?:{})$", constraint))
+                            .expect("Constraints are validated when parsing");
+                        if !matcher.is_match(value) {
+                            return Err(PatternError::new(
+                                &self.source,
+                                format!("{:?} does not match the constraint of {:?}", value, name),
+                            ));
+                        }
+                    }
+                    path.push('/');
+                    path.push_str(&percent_encode(value));
+                }
+                Segment::Wildcard(name) => {
+                    if let Some(value) = params.get(name.as_str()) {
+                        let encoded: Vec<String> = value.split('/').map(percent_encode).collect();
+                        path.push('/');
+                        path.push_str(&encoded.join("/"));
+                    }
+                }
+            }
+        }
+        if path.is_empty() {
+            path.push('/');
+        }
+        Ok(path)
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn parse_name(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if !(c.is_ascii_alphanumeric() || c == '_') || (name.is_empty() && c.is_ascii_digit()) {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    name
+}
+
+fn parse_constraint(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let mut constraint = String::new();
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                constraint.push(c);
+                constraint.push(chars.next()?);
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(constraint);
+                }
+            }
+            _ => {}
+        }
+        constraint.push(c);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(pattern: &str, path: &str) -> Option<Vec<(String, String)>> {
+        let regex = PathPattern::parse(pattern).unwrap().to_regex();
+        regex.captures(path).map(|caps| {
+            regex
+                .capture_names()
+                .flatten()
+                .filter_map(|name| {
+                    caps.name(name)
+                        .map(|value| (name.to_string(), value.as_str().to_string()))
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_parse_segments() {
+        let pattern = PathPattern::parse(r"/users/:id(\d+)/:tab?/*rest").unwrap();
+        assert_eq!(
+            pattern.segments(),
+            &[
+                Segment::Static("users".to_string()),
+                Segment::Param {
+                    name: "id".to_string(),
+                    constraint: Some(r"\d+".to_string()),
+                    optional: false,
+                },
+                Segment::Param {
+                    name: "tab".to_string(),
+                    constraint: None,
+                    optional: true,
+                },
+                Segment::Wildcard("rest".to_string()),
+            ]
+        );
+        assert_eq!(PathPattern::parse("todos").unwrap().as_str(), "/todos");
+    }
+
+    #[test]
+    fn test_static_patterns_are_anchored() {
+        assert!(captures("/todo", "/todo").is_some());
+        assert!(captures("/todo", "/todo/").is_some());
+        assert!(captures("/todo", "/todos").is_none());
+        assert!(captures("/todo", "/todo/archive").is_none());
+        assert!(captures("/", "/").is_some());
+        assert!(captures("/", "/index").is_none());
+        assert!(captures("/a.b", "/aXb").is_none());
+    }
+
+    #[test]
+    fn test_params_and_constraints() {
+        assert_eq!(
+            captures("/users/:id", "/users/42"),
+            Some(vec![("id".to_string(), "42".to_string())])
+        );
+        assert!(captures("/users/:id", "/users").is_none());
+        assert!(captures("/users/:id", "/users/42/posts").is_none());
+
+        assert!(captures(r"/items/:id(\d+)", "/items/7").is_some());
+        assert!(captures(r"/items/:id(\d+)", "/items/seven").is_none());
+        assert!(captures("/files/:kind(img|doc)", "/files/doc").is_some());
+        assert!(captures("/files/:kind(img|doc)", "/files/imgdoc").is_none());
+    }
+
+    #[test]
+    fn test_optional_params() {
+        assert_eq!(
+            captures("/posts/:year/:month?", "/posts/2024"),
+            Some(vec![("year".to_string(), "2024".to_string())])
+        );
+        assert_eq!(
+            captures("/posts/:year/:month?", "/posts/2024/05"),
+            Some(vec![
+                ("year".to_string(), "2024".to_string()),
+                ("month".to_string(), "05".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert_eq!(
+            captures("/static/*path", "/static/css/site.css"),
+            Some(vec![("path".to_string(), "css/site.css".to_string())])
+        );
+        assert_eq!(captures("/static/*path", "/static"), Some(vec![]));
+        assert!(captures("/static/*path", "/assets/site.css").is_none());
+    }
+
+    #[test]
+    fn test_to_path() {
+        let pattern = PathPattern::parse(r"/items/:id(\d+)/:tab?/*rest").unwrap();
+
+        let params = HashMap::from([("id", "7")]);
+        assert_eq!(pattern.to_path(&params).unwrap(), "/items/7");
+
+        let params = HashMap::from([("id", "7"), ("tab", "a b"), ("rest", "x/y z")]);
+        assert_eq!(pattern.to_path(&params).unwrap(), "/items/7/a%20b/x/y%20z");
+
+        assert!(pattern.to_path(&HashMap::new()).is_err());
+        assert!(pattern.to_path(&HashMap::from([("id", "seven")])).is_err());
+        assert_eq!(
+            PathPattern::parse("/").unwrap().to_path(&HashMap::new()),
+            Ok("/".to_string())
+        );
+        assert_eq!(
+            pattern.param_names().collect::<Vec<_>>(),
+            vec!["id", "tab", "rest"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(PathPattern::parse("/users/:").is_err());
+        assert!(PathPattern::parse("/users/:id(").is_err());
+        assert!(PathPattern::parse("/users/:id([)").is_err());
+        assert!(PathPattern::parse("/users/:id.json").is_err());
+        assert!(PathPattern::parse("/*rest/more").is_err());
+        assert!(PathPattern::parse("/:id/:id").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! Random identifiers generated from the operating system's secure random
+//! number generator.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns `N` random bytes.
+///
+/// # Panics
+///
+/// Panics if the operating system cannot provide random bytes.
+pub(crate) fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).expect("Failed to generate random bytes");
+    bytes
+}
+
+/// Generates a random (version 4) UUID, e.g.
+/// `f47ac10b-58cc-4372-a567-0e02b2c3d479`.
+pub(crate) fn uuid_v4() -> String {
+    let mut bytes = random_bytes::<16>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Generates a ULID, a timestamp-ordered id of 26 Crockford base32
+/// characters, e.g. `01ARZ3NDEKTSV4RRFFQ69G5FAV`.
+pub(crate) fn ulid() -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let random = random_bytes::<10>()
+        .iter()
+        .fold(0u128, |acc, &b| (acc << 8) | u128::from(b));
+    let value = ((millis & 0xffff_ffff_ffff) << 80) | random;
+
+    (0..26)
+        .rev()
+        .map(|i| ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uuid_v4() {
+        let uuid = uuid_v4();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert!(matches!(&uuid[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(uuid, uuid_v4());
+    }
+
+    #[test]
+    fn test_ulid() {
+        let first = ulid();
+        assert_eq!(first.len(), 26);
+        assert!(first.chars().all(|c| c.is_ascii_alphanumeric()));
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(ulid() > first);
+    }
+}
@@ -1,5 +1,8 @@
+use crate::normalize::normalize_path;
+use std::any::Any;
 use std::collections::HashMap;
 use std::io::{Error, ErrorKind, Result as IoResult};
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use suika_json::JsonValue;
 use suika_utils::parse_query_string;
@@ -10,6 +13,11 @@ pub struct Request {
     method: String,
     path: String,
     original_path: String,
+    query_string: Option<String>,
+    version: String,
+    remote_addr: Option<SocketAddr>,
+    request_id: Option<String>,
+    principal: Option<Arc<dyn Any + Send + Sync>>,
     headers: HashMap<String, String>,
     query_params: HashMap<String, String>,
     body: Option<String>,
@@ -22,6 +30,9 @@ pub struct Request {
 impl Request {
     /// Creates a new `Request` from a request string.
     ///
+    /// The path is normalized with `normalize_path`, collapsing repeated
+    /// slashes and resolving dot segments; `original_path` keeps it as sent.
+    ///
     /// # Arguments
     ///
     /// * `request_string` - A string slice that holds the HTTP request.
@@ -67,14 +78,16 @@ impl Request {
         }
         let method = request_line_parts[0].to_string();
         let path_with_query = request_line_parts[1].to_string();
+        let version = request_line_parts[2].to_string();
 
-        let (path, query_params) = if let Some(query_start) = path_with_query.find('?') {
-            let path = path_with_query[..query_start].to_string();
-            let query_string = &path_with_query[query_start + 1..];
-            (path, parse_query_string(query_string))
-        } else {
-            (path_with_query, HashMap::new())
+        let (path, query_string) = match path_with_query.split_once('?') {
+            Some((path, query_string)) => (path.to_string(), Some(query_string.to_string())),
+            None => (path_with_query, None),
         };
+        let query_params = query_string
+            .as_deref()
+            .map(parse_query_string)
+            .unwrap_or_default();
 
         let mut headers = HashMap::new();
         let mut is_body = false;
@@ -112,10 +125,7 @@ impl Request {
             .map(|v| v == "application/json")
             .unwrap_or(false)
         {
-            match suika_json::parse_json(&body_content) {
-                Ok(json) => Some(json),
-                Err(_) => None,
-            }
+            suika_json::parse_json(&body_content).ok()
         } else {
             None
         };
@@ -134,10 +144,21 @@ impl Request {
             None
         };
 
+        let normalized_path = if path.starts_with('/') {
+            normalize_path(&path)
+        } else {
+            path.clone()
+        };
+
         Ok(Request {
             method,
-            path: path.clone(),
+            path: normalized_path,
             original_path: path,
+            query_string,
+            version,
+            remote_addr: None,
+            request_id: None,
+            principal: None,
             headers,
             query_params,
             body: if !body_content.is_empty() {
@@ -254,6 +275,154 @@ impl Request {
         &self.original_path
     }
 
+    /// Returns the raw query string of the request, without the `?`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let request = Request::new(
+    ///     "GET /path?b=2&a=1 HTTP/1.1\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// assert_eq!(request.query_string(), Some("b=2&a=1"));
+    /// ```
+    pub fn query_string(&self) -> Option<&str> {
+        self.query_string.as_deref()
+    }
+
+    /// Returns the HTTP version of the request, e.g. `HTTP/1.1`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let request = Request::new(
+    ///     "GET /path HTTP/1.1\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// assert_eq!(request.version(), "HTTP/1.1");
+    /// ```
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the address of the client that sent the request, if known.
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    /// Sets the address of the client that sent the request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut request = Request::new(
+    ///     "GET /path HTTP/1.1\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// request.set_remote_addr("127.0.0.1:5000".parse().unwrap());
+    /// assert_eq!(request.remote_addr(), Some("127.0.0.1:5000".parse().unwrap()));
+    /// ```
+    pub fn set_remote_addr(&mut self, addr: SocketAddr) {
+        self.remote_addr = Some(addr);
+    }
+
+    /// Returns the id of the request, set by `RequestIdMiddleware`.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Sets the id of the request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut request = Request::new(
+    ///     "GET /path HTTP/1.1\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// request.set_request_id("abc-123");
+    /// assert_eq!(request.request_id(), Some("abc-123"));
+    /// ```
+    pub fn set_request_id(&mut self, id: &str) {
+        self.request_id = Some(id.to_string());
+    }
+
+    /// Returns the authenticated principal of the request, set by an
+    /// authentication middleware, if it has the type `T`.
+    pub fn principal<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.principal.as_deref()?.downcast_ref::<T>()
+    }
+
+    /// Sets the authenticated principal of the request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut request = Request::new(
+    ///     "GET /path HTTP/1.1\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// request.set_principal("alice".to_string());
+    /// assert_eq!(request.principal::<String>().map(String::as_str), Some("alice"));
+    /// assert_eq!(request.principal::<u64>(), None);
+    /// ```
+    pub fn set_principal<T: Any + Send + Sync>(&mut self, principal: T) {
+        self.principal = Some(Arc::new(principal));
+    }
+
+    /// Returns the value of a cookie sent with the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the cookie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let request = Request::new(
+    ///     "GET /path HTTP/1.1\r\nCookie: theme=dark; lang=en\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// assert_eq!(request.cookie("lang"), Some("en"));
+    /// assert_eq!(request.cookie("missing"), None);
+    /// ```
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.get_header("Cookie")?.split(';').find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then(|| value.trim_matches('"'))
+        })
+    }
+
     /// Returns the value of the specified header.
     ///
     /// # Arguments
@@ -278,6 +447,34 @@ impl Request {
         self.headers.get(key).map(|s| s.as_str())
     }
 
+    /// Returns the value of the specified header, matching the name
+    /// case-insensitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - A string slice that holds the header name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::sync::{Arc,Mutex};
+    /// use std::collections::HashMap;
+    ///
+    /// let request = Request::new(
+    ///     "GET /path HTTP/1.1\r\nhost: example.com\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// assert_eq!(request.get_header("Host"), Some("example.com"));
+    /// ```
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
     /// Returns the value of the specified query parameter.
     ///
     /// # Arguments
@@ -440,6 +637,52 @@ impl Request {
         self.params.get(key).map(|s| s.as_str())
     }
 
+    /// Returns all parameters of the request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::collections::HashMap;
+    /// use std::sync::{Arc,Mutex};
+    ///
+    /// let mut request = Request::new(
+    ///     "GET /path HTTP/1.1\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// request.set_params(HashMap::from([("key".to_string(), "value".to_string())]));
+    /// assert_eq!(request.params().len(), 1);
+    /// ```
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    /// Returns the host the request was sent to, taken from the `Host`
+    /// header without its port.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use std::collections::HashMap;
+    /// use std::sync::{Arc,Mutex};
+    ///
+    /// let request = Request::new(
+    ///     "GET /path HTTP/1.1\r\nhost: api.example.com:8080\r\n\r\n",
+    ///     Arc::new(Mutex::new(HashMap::new())),
+    /// ).unwrap();
+    ///
+    /// assert_eq!(request.host(), Some("api.example.com"));
+    /// ```
+    pub fn host(&self) -> Option<&str> {
+        let host = self.get_header("Host")?;
+        if host.starts_with('[') {
+            return host.find(']').map(|end| &host[..=end]);
+        }
+        Some(host.split(':').next().unwrap_or(host))
+    }
+
     /// Returns all headers of the request.
     ///
     /// # Examples
@@ -672,4 +915,18 @@ mod tests {
         let module: Option<Arc<MyModule2>> = request.module("my_module");
         assert!(module.is_none());
     }
+
+    #[test]
+    fn test_path_is_normalized() {
+        let request = Request::new(
+            "GET /public//css/../test.js?v=1 HTTP/1.1\r\nhost: example.com:8080\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+
+        assert_eq!(request.path(), "/public/test.js");
+        assert_eq!(request.original_path(), "/public//css/../test.js");
+        assert_eq!(request.query_param("v"), Some("1"));
+        assert_eq!(request.host(), Some("example.com"));
+    }
 }
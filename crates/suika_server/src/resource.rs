@@ -0,0 +1,215 @@
+use crate::error::HttpError;
+use crate::middleware::MiddlewareFuture;
+use crate::request::Request;
+use crate::response::Response;
+
+/// One of the conventional actions of a RESTful resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceAction {
+    /// `GET /items`, lists the resource's entries.
+    Index,
+    /// `GET /items/:id`, shows one entry.
+    Show,
+    /// `POST /items`, creates an entry.
+    Create,
+    /// `PUT /items/:id` and `PATCH /items/:id`, updates an entry.
+    Update,
+    /// `DELETE /items/:id`, deletes an entry.
+    Destroy,
+}
+
+impl ResourceAction {
+    /// All actions, in the order their routes are registered.
+    pub const ALL: [ResourceAction; 5] = [
+        ResourceAction::Index,
+        ResourceAction::Show,
+        ResourceAction::Create,
+        ResourceAction::Update,
+        ResourceAction::Destroy,
+    ];
+
+    /// Returns the HTTP methods routed to the action.
+    pub fn methods(&self) -> &'static [&'static str] {
+        match self {
+            ResourceAction::Index | ResourceAction::Show => &["GET"],
+            ResourceAction::Create => &["POST"],
+            ResourceAction::Update => &["PUT", "PATCH"],
+            ResourceAction::Destroy => &["DELETE"],
+        }
+    }
+
+    /// Returns `true` if the action acts on a single entry, addressed by the
+    /// resource's id parameter.
+    pub fn is_member(&self) -> bool {
+        matches!(
+            self,
+            ResourceAction::Show | ResourceAction::Update | ResourceAction::Destroy
+        )
+    }
+
+    /// Returns the action's name, used to name its route.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResourceAction::Index => "index",
+            ResourceAction::Show => "show",
+            ResourceAction::Create => "create",
+            ResourceAction::Update => "update",
+            ResourceAction::Destroy => "destroy",
+        }
+    }
+}
+
+/// A RESTful resource whose actions are routed with `Router::resource`.
+///
+/// Only the actions returned by `actions` get routes, so requests for the
+/// other actions are answered with `405 Method Not Allowed` and an `Allow`
+/// header listing the supported methods. `actions` must list every action the
+/// resource overrides: it defaults to none, and listed actions that are not
+/// overridden fail with `HttpError::MethodNotAllowed`.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::middleware::MiddlewareFuture;
+/// use suika_server::request::Request;
+/// use suika_server::resource::{Resource, ResourceAction};
+/// use suika_server::response::Response;
+/// use suika_server::router::Router;
+///
+/// struct Todos;
+///
+/// impl Resource for Todos {
+///     fn actions(&self) -> Vec<ResourceAction> {
+///         vec![ResourceAction::Index, ResourceAction::Show]
+///     }
+///
+///     fn index<'a>(&'a self, _req: &'a mut Request, res: &'a mut Response) -> MiddlewareFuture<'a> {
+///         Box::pin(async move {
+///             res.text("All todos".to_string());
+///             Ok(())
+///         })
+///     }
+///
+///     fn show<'a>(&'a self, req: &'a mut Request, res: &'a mut Response) -> MiddlewareFuture<'a> {
+///         Box::pin(async move {
+///             res.text(format!("Todo {}", req.param("id").unwrap_or_default()));
+///             Ok(())
+///         })
+///     }
+/// }
+///
+/// let mut router = Router::new("/");
+/// router.resource("/todos", Todos);
+/// ```
+pub trait Resource: Send + Sync + 'static {
+    /// Returns the actions the resource supports. Defaults to none.
+    fn actions(&self) -> Vec<ResourceAction> {
+        Vec::new()
+    }
+
+    /// Returns the name of the path parameter holding an entry's id.
+    /// Defaults to `id`.
+    ///
+    /// Resources with nested resources should use a distinct name, such as
+    /// `todo_id`, so that it is not shadowed by the nested resource's id.
+    fn id_param(&self) -> &'static str {
+        "id"
+    }
+
+    /// Lists the resource's entries.
+    fn index<'a>(&'a self, _req: &'a mut Request, _res: &'a mut Response) -> MiddlewareFuture<'a> {
+        unsupported(ResourceAction::Index)
+    }
+
+    /// Shows one entry.
+    fn show<'a>(&'a self, _req: &'a mut Request, _res: &'a mut Response) -> MiddlewareFuture<'a> {
+        unsupported(ResourceAction::Show)
+    }
+
+    /// Creates an entry.
+    fn create<'a>(&'a self, _req: &'a mut Request, _res: &'a mut Response) -> MiddlewareFuture<'a> {
+        unsupported(ResourceAction::Create)
+    }
+
+    /// Updates an entry.
+    fn update<'a>(&'a self, _req: &'a mut Request, _res: &'a mut Response) -> MiddlewareFuture<'a> {
+        unsupported(ResourceAction::Update)
+    }
+
+    /// Deletes an entry.
+    fn destroy<'a>(
+        &'a self,
+        _req: &'a mut Request,
+        _res: &'a mut Response,
+    ) -> MiddlewareFuture<'a> {
+        unsupported(ResourceAction::Destroy)
+    }
+}
+
+/// Runs one of a resource's actions.
+pub(crate) fn run_action<'a, R>(
+    resource: &'a R,
+    action: ResourceAction,
+    req: &'a mut Request,
+    res: &'a mut Response,
+) -> MiddlewareFuture<'a>
+where
+    R: Resource + ?Sized,
+{
+    match action {
+        ResourceAction::Index => resource.index(req, res),
+        ResourceAction::Show => resource.show(req, res),
+        ResourceAction::Create => resource.create(req, res),
+        ResourceAction::Update => resource.update(req, res),
+        ResourceAction::Destroy => resource.destroy(req, res),
+    }
+}
+
+fn unsupported<'a>(action: ResourceAction) -> MiddlewareFuture<'a> {
+    Box::pin(async move {
+        Err(HttpError::MethodNotAllowed(format!(
+            "The {} action is not supported",
+            action.as_str()
+        )))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    struct Empty;
+
+    impl Resource for Empty {}
+
+    #[test]
+    fn test_resource_action_routes() {
+        assert_eq!(ResourceAction::Update.methods(), &["PUT", "PATCH"]);
+        assert!(ResourceAction::Show.is_member());
+        assert!(!ResourceAction::Create.is_member());
+        assert_eq!(ResourceAction::Destroy.as_str(), "destroy");
+    }
+
+    #[tokio::test]
+    async fn test_resource_defaults_are_unsupported() {
+        let mut req = Request::new(
+            "GET /items HTTP/1.1\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+
+        assert_eq!(Empty.actions(), Vec::new());
+        assert_eq!(Empty.id_param(), "id");
+
+        let result = run_action(&Empty, ResourceAction::Index, &mut req, &mut res).await;
+        assert_eq!(
+            result,
+            Err(HttpError::MethodNotAllowed(
+                "The index action is not supported".to_string()
+            ))
+        );
+    }
+}
@@ -1,44 +1,58 @@
+use crate::cookie::Cookie;
 use crate::error::HttpError;
 use std::collections::HashMap;
+use std::fmt;
 use std::io::Result as IoResult;
 use std::path::Path;
 use std::sync::Arc;
+use std::sync::Mutex;
 use suika_json::JsonValue;
 use suika_mime::get_mime_type_from_path;
+use suika_templates::template_value::TemplateValue;
 use suika_templates::TemplateEngine;
+use suika_utils::parse_url;
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::Mutex;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 
 /// Represents an HTTP response.
-#[derive(Debug)]
+///
+/// A `Response` is a plain owned builder: setters are synchronous, take
+/// `&mut self` and return `&mut Self` so they can be chained. Handlers can
+/// either modify the response passed to them or build and return their own.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::response::Response;
+///
+/// let mut response = Response::default();
+/// response
+///     .set_status(200)
+///     .header("Cache-Control", "no-store")
+///     .text("Hello World".to_string());
+///
+/// assert_eq!(response.status(), Some(200));
+/// assert_eq!(response.get_header("cache-control"), Some("no-store"));
+/// ```
+#[derive(Debug, Clone, Default)]
 pub struct Response {
-    inner: Arc<Mutex<ResponseInner>>,
-}
-
-/// Represents the inner state of the HTTP response.
-#[derive(Debug, Clone)]
-pub struct ResponseInner {
     status_code: Option<u16>,
     headers: HashMap<String, String>,
     body: Option<Body>,
-    template_engine: Option<Arc<TemplateEngine>>, // Add template engine field
+    template_engine: Option<Arc<TemplateEngine>>,
+    template_vars: HashMap<String, TemplateValue>,
+    cookies: Vec<Cookie>,
+    error: Option<HttpError>,
+    finish_hooks: Vec<FinishHook>,
 }
 
-impl ResponseInner {
-    /// Returns the status code of the response.
-    pub fn status_code(&self) -> Option<u16> {
-        self.status_code
-    }
+/// A callback run with the final response before it is sent.
+#[derive(Clone)]
+struct FinishHook(Arc<dyn Fn(&Response) + Send + Sync>);
 
-    /// Returns the headers of the response.
-    pub fn headers(&self) -> &HashMap<String, String> {
-        &self.headers
-    }
-
-    /// Returns the body of the response.
-    pub fn body(&self) -> &Option<Body> {
-        &self.body
+impl fmt::Debug for FinishHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FinishHook")
     }
 }
 
@@ -47,90 +61,576 @@ impl ResponseInner {
 pub enum Body {
     Text(String),
     Binary(Vec<u8>),
+    Stream(BodyStream),
+}
+
+/// A reader boxed for use as a streaming response body.
+pub type BoxedReader = Box<dyn AsyncRead + Send + Sync + Unpin>;
+
+/// A response body that is read incrementally while the response is sent.
+///
+/// The underlying reader can only be consumed once; clones share the same
+/// reader.
+#[derive(Clone)]
+pub struct BodyStream {
+    reader: Arc<Mutex<Option<BoxedReader>>>,
+}
+
+impl BodyStream {
+    /// Creates a new `BodyStream` from an async reader.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::response::BodyStream;
+    ///
+    /// let stream = BodyStream::new(&b"streamed"[..]);
+    /// ```
+    pub fn new(reader: impl AsyncRead + Send + Sync + Unpin + 'static) -> Self {
+        Self {
+            reader: Arc::new(Mutex::new(Some(Box::new(reader)))),
+        }
+    }
+
+    /// Takes the underlying reader, leaving the stream empty.
+    pub fn take(&self) -> Option<BoxedReader> {
+        self.reader.lock().unwrap().take()
+    }
+}
+
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BodyStream")
+    }
+}
+
+impl PartialEq for BodyStream {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.reader, &other.reader)
+    }
+}
+
+/// The kinds of HTTP redirect supported by `Response::redirect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// 301 Moved Permanently.
+    MovedPermanently,
+    /// 302 Found.
+    Found,
+    /// 303 See Other.
+    SeeOther,
+    /// 307 Temporary Redirect.
+    TemporaryRedirect,
+    /// 308 Permanent Redirect.
+    PermanentRedirect,
+}
+
+impl RedirectKind {
+    /// Returns the status code for this kind of redirect.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::response::RedirectKind;
+    ///
+    /// assert_eq!(RedirectKind::SeeOther.status_code(), 303);
+    /// assert_eq!(RedirectKind::PermanentRedirect.status_code(), 308);
+    /// ```
+    pub fn status_code(&self) -> u16 {
+        match self {
+            RedirectKind::MovedPermanently => 301,
+            RedirectKind::Found => 302,
+            RedirectKind::SeeOther => 303,
+            RedirectKind::TemporaryRedirect => 307,
+            RedirectKind::PermanentRedirect => 308,
+        }
+    }
+}
+
+/// An HTTP status code, such as `200` or `404`.
+pub type StatusCode = u16;
+
+/// Returns the reason phrase for a status code.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::response::status_text;
+///
+/// assert_eq!(status_text(200), "OK");
+/// assert_eq!(status_text(308), "Permanent Redirect");
+/// assert_eq!(status_text(599), "Unknown Status");
+/// ```
+pub fn status_text(status_code: u16) -> &'static str {
+    match status_code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Unknown Status",
+    }
+}
+
+/// Validates a value for use in a `Location` header.
+///
+/// Accepts relative references such as `/items/42`, `items/42`, `../list`
+/// or `?page=2`, and absolute `http` or `https` URLs. Control characters,
+/// whitespace and backslashes are rejected to prevent header injection, as
+/// are protocol-relative references such as `//example.com` and other
+/// schemes such as `javascript:`.
+///
+/// An invalid location is a bug in the handler, so it is reported as an
+/// `HttpError::InternalServerError`.
+fn validate_location(location: &str) -> Result<(), HttpError> {
+    let invalid =
+        || HttpError::InternalServerError(format!("Invalid redirect location: {:?}", location));
+
+    if location.is_empty()
+        || location
+            .chars()
+            .any(|c| c.is_control() || c.is_whitespace() || c == '\\')
+        || location.starts_with("//")
+    {
+        return Err(invalid());
+    }
+
+    // A colon before any `/`, `?` or `#` starts a scheme.
+    let first_segment = location.split(['/', '?', '#']).next().unwrap_or("");
+    if !first_segment.contains(':') {
+        return Ok(());
+    }
+
+    match parse_url(location) {
+        Some((scheme, host, _, _))
+            if (scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https"))
+                && !host.is_empty() =>
+        {
+            Ok(())
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Builds an RFC 6266 `Content-Disposition` value for a downloaded file.
+///
+/// Non-ASCII filenames get an ASCII fallback in `filename` and the UTF-8
+/// name percent-encoded in `filename*`.
+fn content_disposition(disposition: &str, filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if fallback == filename {
+        return format!("{}; filename=\"{}\"", disposition, filename);
+    }
+
+    let mut encoded = String::new();
+    for byte in filename.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'!'
+            | b'#'
+            | b'$'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'.'
+            | b'^'
+            | b'_'
+            | b'`'
+            | b'|'
+            | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        disposition, fallback, encoded
+    )
 }
 
 impl Response {
     /// Creates a new `Response` with default values.
     pub fn new(template_engine: Option<Arc<TemplateEngine>>) -> Self {
         Response {
-            inner: Arc::new(Mutex::new(ResponseInner {
-                status_code: None,
-                headers: HashMap::new(),
-                body: None,
-                template_engine,
-            })),
+            status_code: None,
+            headers: HashMap::new(),
+            body: None,
+            template_engine,
+            template_vars: HashMap::new(),
+            cookies: Vec::new(),
+            error: None,
+            finish_hooks: Vec::new(),
         }
     }
 
     /// Sets the status code of the response.
-    pub async fn set_status(&self, code: u16) {
-        let mut inner = self.inner.lock().await;
-        inner.status_code = Some(code);
+    pub fn set_status(&mut self, code: u16) -> &mut Self {
+        self.status_code = Some(code);
+        self
     }
 
     /// Gets the status code of the response.
-    pub async fn status(&self) -> Option<u16> {
-        let inner = self.inner.lock().await;
-        inner.status_code
+    pub fn status(&self) -> Option<u16> {
+        self.status_code
     }
 
     /// Adds a header to the response.
-    pub async fn header(&self, key: &str, value: &str) {
-        let mut inner = self.inner.lock().await;
-        inner.headers.insert(key.to_string(), value.to_string());
+    pub fn header(&mut self, key: &str, value: &str) -> &mut Self {
+        self.headers.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Gets the value of a response header, matching the name case-insensitively.
+    pub fn get_header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Removes a response header, matching the name case-insensitively.
+    pub fn remove_header(&mut self, key: &str) -> &mut Self {
+        self.headers
+            .retain(|name, _| !name.eq_ignore_ascii_case(key));
+        self
+    }
+
+    /// Returns the headers of the response.
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    /// Adds a cookie to the response, sent in its own `Set-Cookie` header.
+    /// A cookie previously added with the same name is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::cookie::Cookie;
+    /// use suika_server::response::Response;
+    ///
+    /// let mut response = Response::default();
+    /// response
+    ///     .set_cookie(Cookie::new("theme", "light"))
+    ///     .set_cookie(Cookie::new("theme", "dark"));
+    ///
+    /// assert_eq!(response.get_cookie("theme").map(|c| c.value()), Some("dark"));
+    /// ```
+    pub fn set_cookie(&mut self, cookie: Cookie) -> &mut Self {
+        self.cookies
+            .retain(|existing| existing.name() != cookie.name());
+        self.cookies.push(cookie);
+        self
+    }
+
+    /// Gets a cookie added to the response by name.
+    pub fn get_cookie(&self, name: &str) -> Option<&Cookie> {
+        self.cookies.iter().find(|cookie| cookie.name() == name)
+    }
+
+    /// Returns the cookies added to the response.
+    pub fn cookies(&self) -> &[Cookie] {
+        &self.cookies
+    }
+
+    /// Sets a value available to every template rendered for this response,
+    /// such as a token added by a middleware. Values passed to
+    /// `render_template` take precedence.
+    pub fn template_var(&mut self, name: &str, value: TemplateValue) -> &mut Self {
+        self.template_vars.insert(name.to_string(), value);
+        self
+    }
+
+    /// Gets a value set with `template_var`.
+    pub fn get_template_var(&self, name: &str) -> Option<&TemplateValue> {
+        self.template_vars.get(name)
+    }
+
+    /// Registers a callback run with the final response once the server has
+    /// answered unrouted requests and applied the error handler, just before
+    /// the response is sent. Middleware uses it to observe what the client
+    /// actually receives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::response::Response;
+    ///
+    /// let mut response = Response::new(None);
+    /// response.on_finish(|res| println!("sending {:?}", res.status()));
+    /// ```
+    pub fn on_finish<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: Fn(&Response) + Send + Sync + 'static,
+    {
+        self.finish_hooks.push(FinishHook(Arc::new(hook)));
+        self
+    }
+
+    /// Runs the callbacks registered with `on_finish`, once.
+    pub(crate) fn finish(&mut self) {
+        for hook in std::mem::take(&mut self.finish_hooks) {
+            (hook.0)(self);
+        }
     }
 
     /// Sets the body of the response to a text string.
-    pub async fn body(&self, body: String) {
-        let mut inner = self.inner.lock().await;
-        inner.body = Some(Body::Text(body));
+    pub fn body(&mut self, body: String) -> &mut Self {
+        self.body = Some(Body::Text(body));
+        self
     }
 
     /// Sets the body of the response to a JSON value.
-    pub async fn body_json(&self, json_value: JsonValue) {
-        let mut inner = self.inner.lock().await;
-        inner
-            .headers
+    pub fn body_json(&mut self, json_value: JsonValue) -> &mut Self {
+        self.headers
             .insert("Content-Type".to_string(), "application/json".to_string());
-        inner.body = Some(Body::Text(json_value.to_string()));
+        self.body = Some(Body::Text(json_value.to_string()));
+        self
     }
 
     /// Sets the body of the response to binary data.
-    pub async fn body_bytes(&self, body: Vec<u8>) {
-        let mut inner = self.inner.lock().await;
-        inner.body = Some(Body::Binary(body));
+    pub fn body_bytes(&mut self, body: Vec<u8>) -> &mut Self {
+        self.body = Some(Body::Binary(body));
+        self
+    }
+
+    /// Sets the body of the response to a stream that is sent using chunked
+    /// transfer encoding.
+    pub fn body_stream(
+        &mut self,
+        reader: impl AsyncRead + Send + Sync + Unpin + 'static,
+    ) -> &mut Self {
+        self.body = Some(Body::Stream(BodyStream::new(reader)));
+        self
+    }
+
+    /// Sets the body of the response to an existing `Body` value.
+    pub fn set_body(&mut self, body: Body) -> &mut Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Returns the body of the response.
+    pub fn get_body(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
+    /// Takes the body out of the response, leaving it empty.
+    pub fn take_body(&mut self) -> Option<Body> {
+        self.body.take()
+    }
+
+    /// Redirects the client to another location.
+    ///
+    /// Sets the status code for the redirect kind and the `Location` header.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HttpError::InternalServerError` if the location is not a
+    /// relative reference or an absolute `http`/`https` URL, is
+    /// protocol-relative or contains control characters or whitespace.
+    pub fn redirect(&mut self, location: &str, kind: RedirectKind) -> Result<&mut Self, HttpError> {
+        validate_location(location)?;
+        self.status_code = Some(kind.status_code());
+        self.headers
+            .insert("Location".to_string(), location.to_string());
+        Ok(self)
+    }
+
+    /// Marks the response as a file download with the given filename.
+    ///
+    /// Sets an RFC 6266 `Content-Disposition` header, encoding non-ASCII
+    /// filenames, and a `Content-Type` guessed from the filename when none is
+    /// set yet.
+    pub fn attachment(&mut self, filename: &str) -> &mut Self {
+        self.headers.insert(
+            "Content-Disposition".to_string(),
+            content_disposition("attachment", filename),
+        );
+        if self.get_header("Content-Type").is_none() {
+            self.headers.insert(
+                "Content-Type".to_string(),
+                get_mime_type_from_path(filename),
+            );
+        }
+        self
+    }
+
+    /// Sets an HTML body with a matching `Content-Type`, defaulting the status to 200.
+    pub fn html(&mut self, body: String) -> &mut Self {
+        self.status_code.get_or_insert(200);
+        self.headers.insert(
+            "Content-Type".to_string(),
+            "text/html; charset=utf-8".to_string(),
+        );
+        self.body = Some(Body::Text(body));
+        self
+    }
+
+    /// Sets a plain text body with a matching `Content-Type`, defaulting the status to 200.
+    pub fn text(&mut self, body: String) -> &mut Self {
+        self.status_code.get_or_insert(200);
+        self.headers.insert(
+            "Content-Type".to_string(),
+            "text/plain; charset=utf-8".to_string(),
+        );
+        self.body = Some(Body::Text(body));
+        self
+    }
+
+    /// Sets the response to 204 No Content, removing any body and content headers.
+    pub fn no_content(&mut self) -> &mut Self {
+        self.status_code = Some(204);
+        self.body = None;
+        self.headers.retain(|name, _| {
+            !name.eq_ignore_ascii_case("Content-Type")
+                && !name.eq_ignore_ascii_case("Content-Length")
+        });
+        self
+    }
+
+    /// Sets the response to 201 Created with a `Location` header for the new resource.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HttpError::InternalServerError` if the location is invalid, as
+    /// for `redirect`.
+    pub fn created(&mut self, location: &str) -> Result<&mut Self, HttpError> {
+        validate_location(location)?;
+        self.status_code = Some(201);
+        self.headers
+            .insert("Location".to_string(), location.to_string());
+        Ok(self)
     }
 
     /// Sets the response to an HTTP error.
-    pub async fn error(&self, http_error: HttpError) {
-        let mut inner = self.inner.lock().await;
+    ///
+    /// The status code and a plain text body are set from the error, and the
+    /// error is kept so that the server's error handler can replace the body
+    /// with an error page.
+    pub fn error(&mut self, http_error: HttpError) -> &mut Self {
         let (status_code, message) = http_error.to_tuple();
-        inner.status_code = Some(status_code);
-        inner.body = Some(Body::Text(message.to_string()));
+        self.status_code = Some(status_code);
+        self.body = Some(Body::Text(message.to_string()));
+        self.error = Some(http_error);
+        self
+    }
+
+    /// Returns the error the response was set to with `error`, if any.
+    pub fn get_error(&self) -> Option<&HttpError> {
+        self.error.as_ref()
+    }
+
+    /// Replaces this response with one returned by a handler.
+    ///
+    /// The template engine of this response is kept if the returned response
+    /// does not have one, as are the cookies and template values set on this
+    /// response, such as those added by middleware, that the returned
+    /// response does not override. Callbacks registered with `on_finish` are
+    /// kept too.
+    pub fn replace_with(&mut self, mut response: Response) -> &mut Self {
+        if response.template_engine.is_none() {
+            response.template_engine = self.template_engine.take();
+        }
+        for cookie in std::mem::take(&mut self.cookies) {
+            if response.get_cookie(cookie.name()).is_none() {
+                response.cookies.push(cookie);
+            }
+        }
+        for (name, value) in std::mem::take(&mut self.template_vars) {
+            response.template_vars.entry(name).or_insert(value);
+        }
+        let mut finish_hooks = std::mem::take(&mut self.finish_hooks);
+        finish_hooks.append(&mut response.finish_hooks);
+        response.finish_hooks = finish_hooks;
+        *self = response;
+        self
+    }
+
+    /// Discards everything written to the response, including cookies and
+    /// template values, keeping only its template engine and the callbacks
+    /// registered with `on_finish`.
+    pub fn reset(&mut self) -> &mut Self {
+        *self = Response {
+            template_engine: self.template_engine.take(),
+            finish_hooks: std::mem::take(&mut self.finish_hooks),
+            ..Response::default()
+        };
+        self
     }
 
     /// Sends the response over a stream.
     pub async fn send(&self, stream: &mut (impl AsyncWriteExt + Unpin)) -> IoResult<()> {
-        let inner = self.inner.lock().await;
-        let status_code = inner.status_code.unwrap_or(200);
-        let status_text = match status_code {
-            200 => "OK",
-            404 => "Not Found",
-            500 => "Internal Server Error",
-            _ => "Unknown Status",
-        };
+        let status_code = self.status_code.unwrap_or(200);
+        let status_text = status_text(status_code);
         let status_line = format!("HTTP/1.1 {} {}\r\n", status_code, status_text);
 
         stream.write_all(status_line.as_bytes()).await?;
 
-        for (key, value) in &inner.headers {
+        let is_stream = matches!(self.body, Some(Body::Stream(_)));
+
+        for (key, value) in &self.headers {
+            if is_stream
+                && (key.eq_ignore_ascii_case("Content-Length")
+                    || key.eq_ignore_ascii_case("Transfer-Encoding"))
+            {
+                continue;
+            }
             let header_line = format!("{}: {}\r\n", key, value);
             stream.write_all(header_line.as_bytes()).await?;
         }
 
+        for cookie in &self.cookies {
+            let header_line = format!("Set-Cookie: {}\r\n", cookie);
+            stream.write_all(header_line.as_bytes()).await?;
+        }
+
+        if is_stream {
+            stream.write_all(b"Transfer-Encoding: chunked\r\n").await?;
+        }
+
         stream.write_all(b"\r\n").await?;
 
-        if let Some(ref body) = inner.body {
+        if let Some(ref body) = self.body {
             match body {
                 Body::Text(ref text) => {
                     stream.write_all(text.as_bytes()).await?;
@@ -138,6 +638,23 @@ impl Response {
                 Body::Binary(ref binary) => {
                     stream.write_all(binary).await?;
                 }
+                Body::Stream(ref body_stream) => {
+                    if let Some(mut reader) = body_stream.take() {
+                        let mut chunk = vec![0; 8192];
+                        loop {
+                            let size = reader.read(&mut chunk).await?;
+                            if size == 0 {
+                                break;
+                            }
+                            stream
+                                .write_all(format!("{:X}\r\n", size).as_bytes())
+                                .await?;
+                            stream.write_all(&chunk[..size]).await?;
+                            stream.write_all(b"\r\n").await?;
+                        }
+                    }
+                    stream.write_all(b"0\r\n\r\n").await?;
+                }
             }
         }
 
@@ -146,7 +663,7 @@ impl Response {
     }
 
     /// Sends a file as the response body.
-    pub async fn send_file(&self, file_path: &str) -> Result<(), HttpError> {
+    pub async fn send_file(&mut self, file_path: &str) -> Result<(), HttpError> {
         let path = Path::new(file_path);
 
         if !path.exists() {
@@ -163,31 +680,29 @@ impl Response {
             .map_err(|e| HttpError::InternalServerError(format!("Failed to read file: {}", e)))?;
 
         let mime_type = get_mime_type_from_path(file_path);
-        self.header("Content-Type", mime_type.as_ref()).await;
-        self.header("Content-Length", &buffer.len().to_string())
-            .await;
-
-        self.set_status(200).await;
-        self.body_bytes(buffer).await;
+        self.header("Content-Type", mime_type.as_ref())
+            .header("Content-Length", &buffer.len().to_string())
+            .set_status(200)
+            .body_bytes(buffer);
 
         Ok(())
     }
 
     /// Renders a template using the template engine and sets it as the response body.
-    pub async fn render_template(
-        &self,
+    pub fn render_template(
+        &mut self,
         template_name: &str,
-        context: &HashMap<String, suika_templates::template_value::TemplateValue>,
+        context: &HashMap<String, TemplateValue>,
     ) -> Result<(), HttpError> {
-        let inner = self.inner.lock().await;
-        if let Some(template_engine) = &inner.template_engine {
+        if let Some(template_engine) = &self.template_engine {
+            let mut context_with_vars = self.template_vars.clone();
+            context_with_vars.extend(context.iter().map(|(k, v)| (k.clone(), v.clone())));
             let rendered = template_engine
-                .render(template_name, context)
+                .render(template_name, &context_with_vars)
                 .map_err(|e| {
                     HttpError::InternalServerError(format!("Failed to render template: {}", e))
                 })?;
-            drop(inner);
-            self.body(rendered).await;
+            self.body(rendered);
             Ok(())
         } else {
             Err(HttpError::InternalServerError(
@@ -195,19 +710,6 @@ impl Response {
             ))
         }
     }
-
-    /// Returns the inner state of the response.
-    pub async fn get_inner(&self) -> ResponseInner {
-        self.inner.lock().await.clone()
-    }
-}
-
-impl Clone for Response {
-    fn clone(&self) -> Self {
-        Response {
-            inner: Arc::clone(&self.inner),
-        }
-    }
 }
 
 #[cfg(test)]
@@ -220,33 +722,28 @@ mod tests {
     use suika_templates::template_engine::TemplateEngine;
     use suika_templates::template_value::TemplateValue;
     use tokio::io::{AsyncWrite, AsyncWriteExt};
-    use tokio::sync::Mutex;
 
     struct MockStream {
-        data: Arc<Mutex<Vec<u8>>>,
+        data: Vec<u8>,
     }
 
     impl MockStream {
         fn new() -> Self {
-            MockStream {
-                data: Arc::new(Mutex::new(Vec::new())),
-            }
+            MockStream { data: Vec::new() }
         }
 
-        async fn get_data(&self) -> Vec<u8> {
-            let data = self.data.lock().await;
-            data.clone()
+        fn get_data(&self) -> Vec<u8> {
+            self.data.clone()
         }
     }
 
     impl AsyncWrite for MockStream {
         fn poll_write(
-            self: Pin<&mut Self>,
+            mut self: Pin<&mut Self>,
             _: &mut Context<'_>,
             buf: &[u8],
         ) -> Poll<std::io::Result<usize>> {
-            let mut data = futures::executor::block_on(self.data.lock());
-            data.extend_from_slice(buf);
+            self.data.extend_from_slice(buf);
             Poll::Ready(Ok(buf.len()))
         }
 
@@ -259,89 +756,196 @@ mod tests {
         }
     }
 
-    #[tokio::test]
-    async fn test_set_status() {
-        let response = Response::new(None);
-        response.set_status(404).await;
-        let inner = response.inner.lock().await;
-        assert_eq!(inner.status_code, Some(404));
+    #[test]
+    fn test_set_status() {
+        let mut response = Response::new(None);
+        response.set_status(404);
+        assert_eq!(response.status_code, Some(404));
     }
 
-    #[tokio::test]
-    async fn test_status() {
-        let response = Response::new(None);
-        assert_eq!(response.status().await, None);
-        response.set_status(200).await;
-        assert_eq!(response.status().await, Some(200));
+    #[test]
+    fn test_status() {
+        let mut response = Response::new(None);
+        assert_eq!(response.status(), None);
+        response.set_status(200);
+        assert_eq!(response.status(), Some(200));
     }
 
-    #[tokio::test]
-    async fn test_header() {
-        let response = Response::new(None);
-        response.header("Content-Type", "text/plain").await;
-        let inner = response.inner.lock().await;
+    #[test]
+    fn test_header() {
+        let mut response = Response::new(None);
+        response.header("Content-Type", "text/plain");
         assert_eq!(
-            inner.headers.get("Content-Type"),
+            response.headers().get("Content-Type"),
             Some(&"text/plain".to_string())
         );
     }
 
-    #[tokio::test]
-    async fn test_body() {
-        let response = Response::new(None);
-        response.body("Hello, world!".to_string()).await;
-        let inner = response.inner.lock().await;
-        if let Some(Body::Text(ref text)) = inner.body {
+    #[test]
+    fn test_chained_setters() {
+        let mut response = Response::default();
+        response
+            .set_status(201)
+            .header("X-Custom", "value")
+            .body("Created".to_string());
+
+        assert_eq!(response.status(), Some(201));
+        assert_eq!(response.get_header("X-Custom"), Some("value"));
+        assert_eq!(
+            response.get_body(),
+            Some(&Body::Text("Created".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_body() {
+        let mut response = Response::new(None);
+        response.body("Hello, world!".to_string());
+        if let Some(Body::Text(ref text)) = response.body {
             assert_eq!(text, "Hello, world!");
         } else {
             panic!("Expected body to be Some(Body::Text)");
         }
     }
 
-    #[tokio::test]
-    async fn test_body_bytes() {
-        let response = Response::new(None);
-        response.body_bytes(vec![1, 2, 3, 4]).await;
-        let inner = response.inner.lock().await;
-        if let Some(Body::Binary(ref bytes)) = inner.body {
+    #[test]
+    fn test_body_bytes() {
+        let mut response = Response::new(None);
+        response.body_bytes(vec![1, 2, 3, 4]);
+        if let Some(Body::Binary(ref bytes)) = response.body {
             assert_eq!(bytes, &vec![1, 2, 3, 4]);
         } else {
             panic!("Expected body to be Some(Body::Binary)");
         }
     }
 
-    #[tokio::test]
-    async fn test_error() {
-        let response = Response::new(None);
-        response
-            .error(HttpError::NotFound("Resource not found".to_string()))
-            .await;
-        let inner = response.inner.lock().await;
-        assert_eq!(inner.status_code, Some(404));
-        if let Some(Body::Text(ref text)) = inner.body {
+    #[test]
+    fn test_error() {
+        let mut response = Response::new(None);
+        response.error(HttpError::NotFound("Resource not found".to_string()));
+        assert_eq!(response.status_code, Some(404));
+        if let Some(Body::Text(ref text)) = response.body {
             assert_eq!(text, "Resource not found");
         } else {
             panic!("Expected body to be Some(Body::Text)");
         }
     }
 
+    #[test]
+    fn test_replace_with_keeps_template_engine() {
+        let template_engine = Arc::new(TemplateEngine::new());
+        let mut response = Response::new(Some(template_engine));
+
+        let mut returned = Response::default();
+        returned.set_status(202).body("Accepted".to_string());
+        response.replace_with(returned);
+
+        assert_eq!(response.status(), Some(202));
+        assert_eq!(
+            response.get_body(),
+            Some(&Body::Text("Accepted".to_string()))
+        );
+        assert!(response.template_engine.is_some());
+    }
+
+    #[test]
+    fn test_replace_with_keeps_cookies_and_template_vars() {
+        let mut response = Response::new(None);
+        response
+            .set_cookie(Cookie::new("csrf", "abc"))
+            .set_cookie(Cookie::new("theme", "light"))
+            .template_var("token", TemplateValue::String("abc".to_string()))
+            .template_var("theme", TemplateValue::String("light".to_string()));
+
+        let mut returned = Response::default();
+        returned
+            .set_cookie(Cookie::new("theme", "dark"))
+            .template_var("theme", TemplateValue::String("dark".to_string()));
+        response.replace_with(returned);
+
+        assert_eq!(response.cookies().len(), 2);
+        assert_eq!(response.get_cookie("csrf").unwrap().value(), "abc");
+        assert_eq!(response.get_cookie("theme").unwrap().value(), "dark");
+        assert_eq!(
+            response.get_template_var("token"),
+            Some(&TemplateValue::String("abc".to_string()))
+        );
+        assert_eq!(
+            response.get_template_var("theme"),
+            Some(&TemplateValue::String("dark".to_string()))
+        );
+    }
+
     #[tokio::test]
     async fn test_send() {
-        let response = Response::new(None);
-        response.set_status(200).await;
-        response.header("Content-Type", "text/plain").await;
-        response.body("Hello, world!".to_string()).await;
+        let mut response = Response::new(None);
+        response
+            .set_status(200)
+            .header("Content-Type", "text/plain")
+            .body("Hello, world!".to_string());
 
         let mut mock_stream = MockStream::new();
         response.send(&mut mock_stream).await.unwrap();
 
-        let data = mock_stream.get_data().await;
+        let data = mock_stream.get_data();
         let response_string = String::from_utf8(data).unwrap();
         assert!(response_string.contains("HTTP/1.1 200 OK"));
         assert!(response_string.contains("Content-Type: text/plain"));
         assert!(response_string.contains("Hello, world!"));
     }
 
+    #[tokio::test]
+    async fn test_send_cookies() {
+        let mut response = Response::new(None);
+        response
+            .set_status(200)
+            .set_cookie(Cookie::new("a", "1").path("/"))
+            .set_cookie(Cookie::new("b", "2").http_only(true));
+
+        let mut mock_stream = MockStream::new();
+        response.send(&mut mock_stream).await.unwrap();
+
+        let response_string = String::from_utf8(mock_stream.get_data()).unwrap();
+        assert!(response_string.contains("Set-Cookie: a=1; Path=/\r\n"));
+        assert!(response_string.contains("Set-Cookie: b=2; HttpOnly\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_send_stream() {
+        let mut response = Response::new(None);
+        response
+            .set_status(200)
+            .header("Content-Length", "5")
+            .body_stream(&b"Hello"[..]);
+
+        let mut mock_stream = MockStream::new();
+        response.send(&mut mock_stream).await.unwrap();
+
+        let data = mock_stream.get_data();
+        let response_string = String::from_utf8(data).unwrap();
+        assert!(response_string.contains("Transfer-Encoding: chunked"));
+        assert!(!response_string.contains("Content-Length"));
+        assert!(response_string.ends_with("\r\n\r\n5\r\nHello\r\n0\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_get_and_remove_header() {
+        let mut response = Response::new(None);
+        response.header("Content-Type", "text/plain");
+        assert_eq!(response.get_header("content-type"), Some("text/plain"));
+
+        response.remove_header("CONTENT-TYPE");
+        assert_eq!(response.get_header("Content-Type"), None);
+    }
+
+    #[test]
+    fn test_take_body() {
+        let mut response = Response::new(None);
+        response.body("Hello".to_string());
+        assert_eq!(response.take_body(), Some(Body::Text("Hello".to_string())));
+        assert_eq!(response.take_body(), None);
+    }
+
     #[tokio::test]
     async fn test_send_file() {
         // Create a temporary file for testing
@@ -349,16 +953,18 @@ mod tests {
         let mut file = File::create(file_path).await.unwrap();
         file.write_all(b"File content").await.unwrap();
 
-        let response = Response::new(None);
+        let mut response = Response::new(None);
         response.send_file(file_path).await.unwrap();
 
-        let inner = response.inner.lock().await;
         assert_eq!(
-            inner.headers.get("Content-Type"),
+            response.headers.get("Content-Type"),
             Some(&"text/plain".to_string())
         );
-        assert_eq!(inner.headers.get("Content-Length"), Some(&"12".to_string()));
-        if let Some(Body::Binary(ref bytes)) = inner.body {
+        assert_eq!(
+            response.headers.get("Content-Length"),
+            Some(&"12".to_string())
+        );
+        if let Some(Body::Binary(ref bytes)) = response.body {
             assert_eq!(bytes, &b"File content"[..]);
         } else {
             panic!("Expected body to be Some(Body::Binary)");
@@ -368,8 +974,8 @@ mod tests {
         tokio::fs::remove_file(file_path).await.unwrap();
     }
 
-    #[tokio::test]
-    async fn test_render_template() {
+    #[test]
+    fn test_render_template() {
         let mut template_engine = TemplateEngine::new();
         template_engine.add_template(
             "hello.html",
@@ -377,44 +983,189 @@ mod tests {
         );
 
         let template_engine = Arc::new(template_engine);
-        let response = Response::new(Some(template_engine.clone()));
+        let mut response = Response::new(Some(template_engine.clone()));
         let mut context = HashMap::new();
         context.insert(
             "name".to_string(),
             TemplateValue::String("World".to_string()),
         );
 
-        response
-            .render_template("hello.html", &context)
-            .await
-            .unwrap();
-        let inner = response.inner.lock().await;
+        response.render_template("hello.html", &context).unwrap();
 
-        if let Some(Body::Text(ref text)) = inner.body {
+        if let Some(Body::Text(ref text)) = response.body {
             assert_eq!(text, "<html><body>Hello, World!</body></html>");
         } else {
             panic!("Expected body to be Some(Body::Text)");
         }
+
+        response.template_var("name", TemplateValue::String("Default".to_string()));
+        response
+            .render_template("hello.html", &HashMap::new())
+            .unwrap();
+        assert_eq!(
+            response.body,
+            Some(Body::Text(
+                "<html><body>Hello, Default!</body></html>".to_string()
+            ))
+        );
+        response.render_template("hello.html", &context).unwrap();
+        assert_eq!(
+            response.body,
+            Some(Body::Text(
+                "<html><body>Hello, World!</body></html>".to_string()
+            ))
+        );
     }
 
-    #[tokio::test]
-    async fn test_body_json() {
-        let response = Response::new(None);
+    #[test]
+    fn test_body_json() {
+        let mut response = Response::new(None);
         let json_value = JsonValue::Object(vec![(
             "key".to_string(),
             JsonValue::String("value".to_string()),
         )]);
-        response.body_json(json_value.clone()).await;
+        response.body_json(json_value.clone());
 
-        let inner = response.inner.lock().await;
         assert_eq!(
-            inner.headers.get("Content-Type"),
+            response.headers.get("Content-Type"),
             Some(&"application/json".to_string())
         );
-        if let Some(Body::Text(ref text)) = inner.body {
+        if let Some(Body::Text(ref text)) = response.body {
             assert_eq!(text, &json_value.to_string());
         } else {
             panic!("Expected body to be Some(Body::Text)");
         }
     }
+
+    #[test]
+    fn test_redirect() {
+        let kinds = [
+            (RedirectKind::MovedPermanently, 301),
+            (RedirectKind::Found, 302),
+            (RedirectKind::SeeOther, 303),
+            (RedirectKind::TemporaryRedirect, 307),
+            (RedirectKind::PermanentRedirect, 308),
+        ];
+
+        for (kind, code) in kinds {
+            let mut response = Response::new(None);
+            response.redirect("/items/42", kind).unwrap();
+            assert_eq!(response.status_code, Some(code));
+            assert_eq!(
+                response.headers.get("Location"),
+                Some(&"/items/42".to_string())
+            );
+        }
+
+        let mut response = Response::new(None);
+        response
+            .redirect("https://example.com/login?next=%2F", RedirectKind::Found)
+            .unwrap();
+        assert_eq!(response.status(), Some(302));
+
+        for location in ["items/42", "../list", "?page=2", "#top", "./a:b"] {
+            let mut response = Response::new(None);
+            response.redirect(location, RedirectKind::SeeOther).unwrap();
+            assert_eq!(response.get_header("Location"), Some(location));
+        }
+    }
+
+    #[test]
+    fn test_redirect_rejects_invalid_locations() {
+        let mut response = Response::new(None);
+        for location in [
+            "",
+            "//evil.example.com",
+            "/\\evil.example.com",
+            "\\\\evil.example.com",
+            "/ok\r\nSet-Cookie: a=b",
+            "/a b",
+            "javascript:alert(1)",
+            "https:///path",
+        ] {
+            assert!(
+                matches!(
+                    response.redirect(location, RedirectKind::Found),
+                    Err(HttpError::InternalServerError(_))
+                ),
+                "{:?}",
+                location
+            );
+        }
+        assert_eq!(response.status(), None);
+        assert_eq!(response.get_header("Location"), None);
+    }
+
+    #[test]
+    fn test_attachment() {
+        let mut response = Response::new(None);
+        response.attachment("report.pdf");
+        assert_eq!(
+            response.get_header("Content-Disposition"),
+            Some("attachment; filename=\"report.pdf\"")
+        );
+        assert_eq!(response.get_header("Content-Type"), Some("application/pdf"));
+
+        let mut response = Response::new(None);
+        response
+            .header("Content-Type", "text/csv")
+            .attachment("résumé \"final\".csv");
+        assert_eq!(
+            response.get_header("Content-Disposition"),
+            Some(
+                "attachment; filename=\"r_sum_ _final_.csv\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.csv"
+            )
+        );
+        assert_eq!(response.get_header("Content-Type"), Some("text/csv"));
+    }
+
+    #[test]
+    fn test_html_and_text() {
+        let mut response = Response::new(None);
+        response.html("<h1>Hi</h1>".to_string());
+        assert_eq!(response.status(), Some(200));
+        assert_eq!(
+            response.get_header("Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            response.get_body(),
+            Some(&Body::Text("<h1>Hi</h1>".to_string()))
+        );
+
+        let mut response = Response::new(None);
+        response.set_status(404).text("missing".to_string());
+        assert_eq!(response.status(), Some(404));
+        assert_eq!(
+            response.get_header("Content-Type"),
+            Some("text/plain; charset=utf-8")
+        );
+    }
+
+    #[test]
+    fn test_no_content_and_created() {
+        let mut response = Response::new(None);
+        response.text("body".to_string()).no_content();
+        assert_eq!(response.status(), Some(204));
+        assert_eq!(response.get_body(), None);
+        assert_eq!(response.get_header("Content-Type"), None);
+
+        let mut response = Response::new(None);
+        response.created("/todos/3").unwrap();
+        assert_eq!(response.status(), Some(201));
+        assert_eq!(response.get_header("Location"), Some("/todos/3"));
+    }
+
+    #[tokio::test]
+    async fn test_send_uses_status_text() {
+        let mut response = Response::new(None);
+        response.redirect("/", RedirectKind::SeeOther).unwrap();
+
+        let mut mock_stream = MockStream::new();
+        response.send(&mut mock_stream).await.unwrap();
+
+        let data = mock_stream.get_data();
+        let response_string = String::from_utf8(data).unwrap();
+        assert!(response_string.starts_with("HTTP/1.1 303 See Other\r\n"));
+    }
 }
@@ -0,0 +1,357 @@
+use crate::error::HttpError;
+use crate::into_response::IntoResponse;
+use crate::path_pattern::Segment;
+use crate::response::Response;
+use crate::route_tree::expand_optional;
+use crate::router::RouteSource;
+use std::fmt;
+
+/// The effective configuration of a single route, as listed by `Router::route_table`.
+#[derive(Debug, Clone)]
+pub struct RouteInfo {
+    pub(crate) method: Option<String>,
+    pub(crate) source: RouteSource,
+    pub(crate) name: Option<String>,
+    pub(crate) router: String,
+    pub(crate) middleware: Vec<&'static str>,
+}
+
+impl RouteInfo {
+    /// Returns the HTTP method of the route, or `None` if it accepts every method.
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+
+    /// Returns the full pattern of the route, including every base path.
+    pub fn pattern(&self) -> &str {
+        self.source.as_str()
+    }
+
+    /// Returns the pattern the route is matched with.
+    pub fn source(&self) -> &RouteSource {
+        &self.source
+    }
+
+    /// Returns the name of the route, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the full base path of the router the route was registered on.
+    pub fn router(&self) -> &str {
+        &self.router
+    }
+
+    /// Returns the names of the middleware wrapping the route, in the order
+    /// they run.
+    pub fn middleware(&self) -> &[&'static str] {
+        &self.middleware
+    }
+
+    fn overlaps(&self, other: &RouteInfo) -> bool {
+        match (&self.method, &other.method) {
+            (None, _) => true,
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Returns a key for every path shape the route matches. Two routes with
+    /// a common key match exactly the same paths, whatever their parameter
+    /// names.
+    fn shapes(&self) -> Vec<String> {
+        let RouteSource::Path(path_pattern) = &self.source else {
+            return vec![format!("regex:{}", self.pattern())];
+        };
+        expand_optional(path_pattern.segments())
+            .iter()
+            .map(|segments| {
+                let mut shape = String::new();
+                for segment in segments {
+                    match segment {
+                        Segment::Static(text) => shape.push_str(&format!("/{}", text)),
+                        Segment::Param { constraint, .. } => {
+                            shape.push_str("/:");
+                            if let Some(constraint) = constraint {
+                                shape.push_str(&format!("({})", constraint));
+                            }
+                        }
+                        Segment::Wildcard(_) => shape.push_str("/*"),
+                    }
+                }
+                shape
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for RouteInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (router {})",
+            self.method.as_deref().unwrap_or("*"),
+            self.pattern(),
+            self.router
+        )
+    }
+}
+
+/// A problem with the routes of a router, found by `RouteTable::conflicts`.
+#[derive(Debug, Clone)]
+pub enum RouteConflict {
+    /// A route that is never reached for some of its paths, because an
+    /// earlier route matches the same paths and methods.
+    Shadowed { route: RouteInfo, by: RouteInfo },
+    /// Two routes registered with the same name. `url_for` uses the first.
+    DuplicateName {
+        name: String,
+        first: RouteInfo,
+        second: RouteInfo,
+    },
+}
+
+impl fmt::Display for RouteConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteConflict::Shadowed { route, by } => {
+                write!(f, "{} is shadowed by {}", route, by)
+            }
+            RouteConflict::DuplicateName {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "Route name {:?} is used by both {} and {}",
+                name, first, second
+            ),
+        }
+    }
+}
+
+/// The effective routes of a router, in registration order.
+///
+/// The table prints as an aligned plain-text table that is stable across
+/// runs, so it can be committed and diffed in CI, and converts into a
+/// `text/plain` response so it can be served from a debug endpoint.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::router::Router;
+///
+/// let mut router = Router::new("/api");
+/// router.group("/users", |users| {
+///     users
+///         .get("/:id", |_req| Box::pin(async move { Ok("user") }))
+///         .name("user");
+///     users.get("/:name", |_req| Box::pin(async move { Ok("shadowed") }));
+/// });
+///
+/// let table = router.route_table();
+/// assert_eq!(table.routes()[0].pattern(), "/api/users/:id");
+/// assert_eq!(table.routes()[0].router(), "/api/users");
+///
+/// let conflicts = table.conflicts();
+/// assert_eq!(conflicts.len(), 1);
+/// assert_eq!(
+///     conflicts[0].to_string(),
+///     "GET /api/users/:name (router /api/users) is shadowed by GET /api/users/:id (router /api/users)"
+/// );
+///
+/// println!("{}", table);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    routes: Vec<RouteInfo>,
+}
+
+impl RouteTable {
+    pub(crate) fn new(routes: Vec<RouteInfo>) -> Self {
+        Self { routes }
+    }
+
+    /// Returns the routes in registration order.
+    pub fn routes(&self) -> &[RouteInfo] {
+        &self.routes
+    }
+
+    /// Finds shadowed routes and duplicate route names.
+    ///
+    /// A route is shadowed when an earlier route matches the same paths,
+    /// ignoring parameter names, for the same method or for every method.
+    /// Routes with optional parameters are reported if any of their forms is
+    /// shadowed.
+    ///
+    /// # Returns
+    ///
+    /// The conflicts in registration order, empty if there are none.
+    pub fn conflicts(&self) -> Vec<RouteConflict> {
+        let mut conflicts = Vec::new();
+        let shapes: Vec<Vec<String>> = self.routes.iter().map(RouteInfo::shapes).collect();
+
+        for (index, route) in self.routes.iter().enumerate() {
+            let shadowing = self.routes[..index]
+                .iter()
+                .enumerate()
+                .find(|(i, earlier)| {
+                    earlier.overlaps(route) && shapes[index].iter().any(|s| shapes[*i].contains(s))
+                });
+            if let Some((_, earlier)) = shadowing {
+                conflicts.push(RouteConflict::Shadowed {
+                    route: route.clone(),
+                    by: earlier.clone(),
+                });
+            }
+
+            let Some(name) = &route.name else {
+                continue;
+            };
+            let first = self.routes[..index]
+                .iter()
+                .find(|earlier| earlier.name.as_ref() == Some(name));
+            if let Some(first) = first {
+                conflicts.push(RouteConflict::DuplicateName {
+                    name: name.clone(),
+                    first: first.clone(),
+                    second: route.clone(),
+                });
+            }
+        }
+        conflicts
+    }
+}
+
+impl fmt::Display for RouteTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers = ["METHOD", "PATTERN", "NAME", "ROUTER", "MIDDLEWARE"];
+        let rows: Vec<[String; 5]> = self
+            .routes
+            .iter()
+            .map(|route| {
+                [
+                    route.method.as_deref().unwrap_or("*").to_uppercase(),
+                    route.pattern().to_string(),
+                    route.name.clone().unwrap_or_else(|| "-".to_string()),
+                    route.router.clone(),
+                    if route.middleware.is_empty() {
+                        "-".to_string()
+                    } else {
+                        route.middleware.join(", ")
+                    },
+                ]
+            })
+            .collect();
+
+        let mut widths = headers.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let mut write_row = |cells: [&str; 5]| {
+            let line: Vec<String> = cells
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            writeln!(f, "{}", line.join("  ").trim_end())
+        };
+        write_row(headers)?;
+        for row in &rows {
+            write_row([&row[0], &row[1], &row[2], &row[3], &row[4]])?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoResponse for RouteTable {
+    fn into_response(self, res: &mut Response) -> Result<(), HttpError> {
+        self.to_string().into_response(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::middleware::LoggerMiddleware;
+    use crate::router::Router;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_route_table_lists_effective_routes() {
+        let mut users = Router::new("/users");
+        users.use_middleware(Arc::new(LoggerMiddleware));
+        users
+            .get("/:id", |_req| Box::pin(async move { Ok("user") }))
+            .name("user");
+
+        let mut api = Router::new("/api");
+        api.any("/health", |_req| Box::pin(async move { Ok("ok") }));
+        api.mount(users);
+
+        let table = api.route_table();
+        let routes = table.routes();
+        assert_eq!(routes.len(), 2);
+
+        assert_eq!(routes[0].method(), None);
+        assert_eq!(routes[0].pattern(), "/api/health");
+        assert_eq!(routes[0].router(), "/api");
+        assert!(routes[0].middleware().is_empty());
+
+        assert_eq!(routes[1].method(), Some("GET"));
+        assert_eq!(routes[1].pattern(), "/api/users/:id");
+        assert_eq!(routes[1].name(), Some("user"));
+        assert_eq!(routes[1].router(), "/api/users");
+        assert_eq!(routes[1].middleware(), &["LoggerMiddleware"]);
+
+        assert_eq!(
+            table.to_string(),
+            "METHOD  PATTERN         NAME  ROUTER      MIDDLEWARE\n\
+             *       /api/health     -     /api        -\n\
+             GET     /api/users/:id  user  /api/users  LoggerMiddleware\n"
+        );
+    }
+
+    #[test]
+    fn test_conflicts_detect_shadowed_routes() {
+        let mut router = Router::new("/");
+        router.get("/users/:id", |_req| Box::pin(async move { Ok("id") }));
+        router.get("/users/:name", |_req| Box::pin(async move { Ok("name") }));
+        router.post("/users/:name", |_req| Box::pin(async move { Ok("post") }));
+        router.get(r"/items/:id(\d+)", |_req| Box::pin(async move { Ok("id") }));
+        router.get("/items/:slug", |_req| Box::pin(async move { Ok("slug") }));
+        router.get("/posts/:year", |_req| Box::pin(async move { Ok("year") }));
+        router.get("/posts/:y/:m?", |_req| Box::pin(async move { Ok("month") }));
+        router.get("/files", |_req| Box::pin(async move { Ok("get") }));
+        router.any("/files", |_req| Box::pin(async move { Ok("any") }));
+
+        let conflicts: Vec<String> = router.conflicts().iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                "GET /users/:name (router /) is shadowed by GET /users/:id (router /)",
+                "GET /posts/:y/:m? (router /) is shadowed by GET /posts/:year (router /)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflicts_detect_duplicate_names() {
+        let mut router = Router::new("/");
+        router
+            .get("/a", |_req| Box::pin(async move { Ok("a") }))
+            .name("page");
+        router
+            .get("/b", |_req| Box::pin(async move { Ok("b") }))
+            .name("page");
+
+        let conflicts = router.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "Route name \"page\" is used by both GET /a (router /) and GET /b (router /)"
+        );
+    }
+}
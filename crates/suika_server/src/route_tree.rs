@@ -0,0 +1,376 @@
+use crate::path_pattern::{PathPattern, Segment};
+use regex::Regex;
+
+/// A piece of a path pattern as stored in the tree. Static pieces keep their
+/// slashes so that adjacent segments can share a single compressed edge.
+enum Token {
+    Static(String),
+    Param {
+        name: String,
+        constraint: Option<String>,
+    },
+    Wildcard(String),
+}
+
+struct ParamEdge {
+    name: String,
+    constraint: Option<String>,
+    matcher: Option<Regex>,
+    node: Node,
+}
+
+struct WildcardEdge {
+    name: String,
+    endpoints: Vec<usize>,
+}
+
+#[derive(Default)]
+struct Node {
+    prefix: String,
+    children: Vec<Node>,
+    params: Vec<ParamEdge>,
+    wildcards: Vec<WildcardEdge>,
+    endpoints: Vec<usize>,
+}
+
+/// A compressed radix tree mapping path patterns to route indices.
+///
+/// At every node static children are tried first, then parameters, then
+/// wildcards, so `/users/me` wins over `/users/:id`, which wins over
+/// `/users/*rest`, regardless of registration order. Parameter constraints
+/// are only evaluated once the tree reaches the parameter's position.
+#[derive(Default)]
+pub(crate) struct RouteTree {
+    root: Node,
+}
+
+impl RouteTree {
+    /// Inserts a pattern, storing `index` at every endpoint it can end at.
+    /// Optional parameters are expanded into one path with and one without
+    /// the parameter.
+    pub(crate) fn insert(&mut self, pattern: &PathPattern, index: usize) {
+        for segments in expand_optional(pattern.segments()) {
+            let tokens = tokenize(&segments);
+            insert_tokens(&mut self.root, &tokens, index);
+        }
+    }
+
+    /// Walks every endpoint matching `path` in precedence order, calling
+    /// `visit` with the route indices stored there and the captured
+    /// parameters. The walk stops as soon as `visit` returns `true`.
+    pub(crate) fn visit<F>(&self, path: &str, mut visit: F) -> bool
+    where
+        F: FnMut(&[usize], &[(String, String)]) -> bool,
+    {
+        let mut params = Vec::new();
+        lookup(&self.root, path, &mut params, &mut visit)
+    }
+}
+
+pub(crate) fn expand_optional(segments: &[Segment]) -> Vec<Vec<Segment>> {
+    let mut variants: Vec<Vec<Segment>> = vec![Vec::new()];
+    for segment in segments {
+        match segment {
+            Segment::Param {
+                name,
+                constraint,
+                optional: true,
+            } => {
+                let required = Segment::Param {
+                    name: name.clone(),
+                    constraint: constraint.clone(),
+                    optional: false,
+                };
+                let mut with_param = variants.clone();
+                for variant in &mut with_param {
+                    variant.push(required.clone());
+                }
+                variants.extend(with_param);
+            }
+            _ => {
+                for variant in &mut variants {
+                    variant.push(segment.clone());
+                }
+            }
+        }
+    }
+    variants
+}
+
+fn tokenize(segments: &[Segment]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+
+    for segment in segments {
+        match segment {
+            Segment::Static(value) => {
+                text.push('/');
+                text.push_str(value);
+            }
+            Segment::Param {
+                name, constraint, ..
+            } => {
+                text.push('/');
+                tokens.push(Token::Static(std::mem::take(&mut text)));
+                tokens.push(Token::Param {
+                    name: name.clone(),
+                    constraint: constraint.clone(),
+                });
+            }
+            Segment::Wildcard(name) => {
+                if !text.is_empty() {
+                    tokens.push(Token::Static(std::mem::take(&mut text)));
+                }
+                tokens.push(Token::Wildcard(name.clone()));
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        tokens.push(Token::Static(text));
+    } else if tokens.is_empty() {
+        tokens.push(Token::Static("/".to_string()));
+    }
+    tokens
+}
+
+fn insert_tokens(node: &mut Node, tokens: &[Token], index: usize) {
+    match tokens.split_first() {
+        None => node.endpoints.push(index),
+        Some((Token::Static(text), rest)) => insert_static(node, text, rest, index),
+        Some((Token::Param { name, constraint }, rest)) => {
+            let position = node
+                .params
+                .iter()
+                .position(|edge| edge.name == *name && edge.constraint == *constraint);
+            let position = position.unwrap_or_else(|| {
+                node.params.push(ParamEdge {
+                    name: name.clone(),
+                    constraint: constraint.clone(),
+                    matcher: constraint
+                        .as_ref()
+                        .map(|c| Regex::new(&format!("^(?:{})$", c)).expect("Invalid constraint")),
+                    node: Node::default(),
+                });
+                node.params.len() - 1
+            });
+            insert_tokens(&mut node.params[position].node, rest, index);
+        }
+        Some((Token::Wildcard(name), _)) => {
+            match node.wildcards.iter_mut().find(|edge| edge.name == *name) {
+                Some(edge) => edge.endpoints.push(index),
+                None => node.wildcards.push(WildcardEdge {
+                    name: name.clone(),
+                    endpoints: vec![index],
+                }),
+            }
+        }
+    }
+}
+
+fn insert_static(node: &mut Node, text: &str, rest: &[Token], index: usize) {
+    if text.is_empty() {
+        return insert_tokens(node, rest, index);
+    }
+
+    let first = text.chars().next();
+    let position = node
+        .children
+        .iter()
+        .position(|child| child.prefix.chars().next() == first);
+
+    let Some(position) = position else {
+        node.children.push(Node {
+            prefix: text.to_string(),
+            ..Node::default()
+        });
+        let child = node.children.last_mut().unwrap();
+        return insert_tokens(child, rest, index);
+    };
+
+    let child = &mut node.children[position];
+    let common = common_prefix_len(&child.prefix, text);
+    if common < child.prefix.len() {
+        let suffix = child.prefix.split_off(common);
+        let split = Node {
+            prefix: suffix,
+            children: std::mem::take(&mut child.children),
+            params: std::mem::take(&mut child.params),
+            wildcards: std::mem::take(&mut child.wildcards),
+            endpoints: std::mem::take(&mut child.endpoints),
+        };
+        child.children.push(split);
+    }
+    insert_static(child, &text[common..], rest, index);
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map(|((i, _), _)| i)
+        .unwrap_or_else(|| a.len().min(b.len()))
+}
+
+fn lookup<F>(node: &Node, path: &str, params: &mut Vec<(String, String)>, visit: &mut F) -> bool
+where
+    F: FnMut(&[usize], &[(String, String)]) -> bool,
+{
+    if (path.is_empty() || path == "/")
+        && !node.endpoints.is_empty()
+        && visit(&node.endpoints, params)
+    {
+        return true;
+    }
+
+    for child in &node.children {
+        if let Some(rest) = path.strip_prefix(child.prefix.as_str()) {
+            if lookup(child, rest, params, visit) {
+                return true;
+            }
+        }
+    }
+
+    let value = path.split('/').next().unwrap_or_default();
+    if !value.is_empty() {
+        for edge in &node.params {
+            if edge.matcher.as_ref().is_some_and(|m| !m.is_match(value)) {
+                continue;
+            }
+            params.push((edge.name.clone(), value.to_string()));
+            let found = lookup(&edge.node, &path[value.len()..], params, visit);
+            params.pop();
+            if found {
+                return true;
+            }
+        }
+    }
+
+    for edge in &node.wildcards {
+        let captured = if path.is_empty() {
+            None
+        } else if let Some(rest) = path.strip_prefix('/') {
+            Some(rest)
+        } else {
+            continue;
+        };
+
+        if let Some(rest) = captured {
+            params.push((edge.name.clone(), rest.to_string()));
+        }
+        let found = visit(&edge.endpoints, params);
+        if captured.is_some() {
+            params.pop();
+        }
+        if found {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(patterns: &[&str]) -> RouteTree {
+        let mut tree = RouteTree::default();
+        for (index, pattern) in patterns.iter().enumerate() {
+            tree.insert(&PathPattern::parse(pattern).unwrap(), index);
+        }
+        tree
+    }
+
+    fn first_match(tree: &RouteTree, path: &str) -> Option<(usize, Vec<(String, String)>)> {
+        let mut found = None;
+        tree.visit(path, |endpoints, params| {
+            found = Some((endpoints[0], params.to_vec()));
+            true
+        });
+        found
+    }
+
+    fn param(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_static_routes_share_prefixes() {
+        let tree = build(&["/users", "/user", "/users/active", "/", "/uploads"]);
+
+        assert_eq!(first_match(&tree, "/users"), Some((0, vec![])));
+        assert_eq!(first_match(&tree, "/user"), Some((1, vec![])));
+        assert_eq!(first_match(&tree, "/users/active/"), Some((2, vec![])));
+        assert_eq!(first_match(&tree, "/"), Some((3, vec![])));
+        assert_eq!(first_match(&tree, "/uploads"), Some((4, vec![])));
+        assert_eq!(first_match(&tree, "/use"), None);
+        assert_eq!(first_match(&tree, "/users/inactive"), None);
+    }
+
+    #[test]
+    fn test_precedence_is_static_then_param_then_wildcard() {
+        let tree = build(&["/files/*rest", "/files/:name", "/files/readme"]);
+
+        assert_eq!(first_match(&tree, "/files/readme"), Some((2, vec![])));
+        assert_eq!(
+            first_match(&tree, "/files/logo.png"),
+            Some((1, vec![param("name", "logo.png")]))
+        );
+        assert_eq!(
+            first_match(&tree, "/files/docs/a.txt"),
+            Some((0, vec![param("rest", "docs/a.txt")]))
+        );
+    }
+
+    #[test]
+    fn test_backtracks_into_lower_precedence_branches() {
+        let tree = build(&["/users/me/settings", "/users/:id/posts"]);
+
+        assert_eq!(
+            first_match(&tree, "/users/me/posts"),
+            Some((1, vec![param("id", "me")]))
+        );
+    }
+
+    #[test]
+    fn test_constraints_are_checked_at_param_nodes() {
+        let tree = build(&[r"/items/:id(\d+)", "/items/:slug"]);
+
+        assert_eq!(
+            first_match(&tree, "/items/42"),
+            Some((0, vec![param("id", "42")]))
+        );
+        assert_eq!(
+            first_match(&tree, "/items/blue-shoes"),
+            Some((1, vec![param("slug", "blue-shoes")]))
+        );
+    }
+
+    #[test]
+    fn test_optional_params() {
+        let tree = build(&["/posts/:year/:month?"]);
+
+        assert_eq!(
+            first_match(&tree, "/posts/2024"),
+            Some((0, vec![param("year", "2024")]))
+        );
+        assert_eq!(
+            first_match(&tree, "/posts/2024/05"),
+            Some((0, vec![param("year", "2024"), param("month", "05")]))
+        );
+        assert_eq!(first_match(&tree, "/posts"), None);
+    }
+
+    #[test]
+    fn test_wildcard_without_rest() {
+        let tree = build(&["/static/*path"]);
+
+        assert_eq!(first_match(&tree, "/static"), Some((0, vec![])));
+        assert_eq!(
+            first_match(&tree, "/static/"),
+            Some((0, vec![param("path", "")]))
+        );
+        assert_eq!(first_match(&tree, "/statics"), None);
+    }
+}
@@ -1,25 +1,236 @@
 use crate::error::HttpError;
+use crate::into_response::IntoResponse;
 use crate::middleware::{Middleware, MiddlewareFuture, Next};
+use crate::normalize::{has_trailing_slash, toggle_trailing_slash, TrailingSlash};
+use crate::path_pattern::{PathPattern, Segment};
 use crate::request::Request;
-use crate::response::Response;
+use crate::resource::{run_action, Resource};
+use crate::response::{RedirectKind, Response};
+use crate::route_table::{RouteConflict, RouteInfo, RouteTable};
+use crate::route_tree::RouteTree;
+use futures::future::BoxFuture;
 use regex::Regex;
 use std::collections::HashMap;
-use std::pin::Pin;
 use std::sync::Arc;
+use suika_templates::TemplateEngine;
+use suika_utils::{build_url, percent_encode};
+
+/// A shared handler function invoked when a route matches.
+pub type RouteHandler =
+    Arc<dyn for<'a> Fn(&'a mut Request, &'a mut Response) -> MiddlewareFuture<'a> + Send + Sync>;
+
+/// A future returned by handlers that produce a value implementing `IntoResponse`.
+pub type ResponseFuture<'a, T = Response> = BoxFuture<'a, Result<T, HttpError>>;
+
+/// The pattern a route was registered with, including the router's base path.
+#[derive(Debug, Clone)]
+pub enum RouteSource {
+    /// An Express-style path pattern, matched against the whole path.
+    Path(PathPattern),
+    /// A raw regex, matched as written.
+    Regex(Regex),
+}
+
+impl RouteSource {
+    /// Returns the pattern or regex the route was registered with.
+    pub fn as_str(&self) -> &str {
+        match self {
+            RouteSource::Path(path_pattern) => path_pattern.as_str(),
+            RouteSource::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    fn prefixed(&self, prefix: &str) -> RouteSource {
+        match self {
+            RouteSource::Path(path_pattern) => RouteSource::Path(
+                PathPattern::parse(&full_pattern(prefix, path_pattern.as_str()))
+                    .unwrap_or_else(|e| panic!("{}", e)),
+            ),
+            RouteSource::Regex(pattern) => RouteSource::Regex(
+                Regex::new(&format!(
+                    "{}{}",
+                    prefix.trim_end_matches('/'),
+                    pattern.as_str()
+                ))
+                .expect("Invalid regex pattern"),
+            ),
+        }
+    }
+
+    fn url_for(&self, params: &HashMap<&str, &str>) -> Result<String, HttpError> {
+        let RouteSource::Path(path_pattern) = self else {
+            return Err(HttpError::InternalServerError(
+                "Cannot build a URL for a regex route".to_string(),
+            ));
+        };
+        let path = path_pattern
+            .to_path(params)
+            .map_err(|e| HttpError::InternalServerError(e.to_string()))?;
+
+        let names: Vec<&str> = path_pattern.param_names().collect();
+        let query: HashMap<String, String> = params
+            .iter()
+            .filter(|(key, _)| !names.contains(key))
+            .map(|(key, value)| (percent_encode(key), percent_encode(value)))
+            .collect();
+        let query = query
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        Ok(build_url(&path, &query))
+    }
+}
 
 /// Represents a route in the router.
 pub struct Route {
-    pub method: Option<String>,
-    pub pattern: Regex,
-    pub handler: Arc<
-        dyn for<'a> Fn(&'a mut Request, &'a mut Response) -> MiddlewareFuture<'a> + Send + Sync,
-    >,
+    name: Option<String>,
+    method: Option<String>,
+    source: RouteSource,
+    router: String,
+    handler: RouteHandler,
+    middleware: Vec<Arc<dyn Middleware + Send + Sync>>,
+}
+
+impl Route {
+    /// Returns the name of the route, if it has one.
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the HTTP method of the route, or `None` if it accepts every method.
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+
+    /// Returns the pattern the route was registered with.
+    pub fn source(&self) -> &RouteSource {
+        &self.source
+    }
+
+    /// Returns the handler of the route.
+    pub fn handler(&self) -> &RouteHandler {
+        &self.handler
+    }
+
+    /// Adds a middleware that only runs for this route.
+    ///
+    /// Route middleware runs after the middleware of the routers the route
+    /// belongs to, in the order it was added, and wraps the handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `mw` - The middleware to run around the handler.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::LoggerMiddleware;
+    /// use suika_server::router::Router;
+    /// use std::sync::Arc;
+    ///
+    /// let mut router = Router::new("/");
+    /// router
+    ///     .get("/reports", |_req| Box::pin(async move { Ok("reports") }))
+    ///     .use_middleware(Arc::new(LoggerMiddleware));
+    /// ```
+    pub fn use_middleware(&mut self, mw: Arc<dyn Middleware + Send + Sync>) -> &mut Self {
+        self.middleware.push(mw);
+        self
+    }
+
+    /// Names the route so that URLs for it can be built with `Router::url_for`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the route.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/");
+    /// router
+    ///     .get("/items/:id", |_req| Box::pin(async move { Ok("item") }))
+    ///     .name("item");
+    /// ```
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    fn allows(&self, method: &str) -> bool {
+        self.method
+            .as_ref()
+            .is_none_or(|m| m.eq_ignore_ascii_case(method))
+    }
+
+    /// Returns `true` if the trailing `/` of the path agrees with the route's
+    /// pattern. Wildcard and regex routes decide for themselves.
+    fn matches_trailing_slash(&self, path: &str) -> bool {
+        match &self.source {
+            RouteSource::Path(path_pattern) => {
+                matches!(path_pattern.segments().last(), Some(Segment::Wildcard(_)))
+                    || has_trailing_slash(path_pattern.as_str()) == has_trailing_slash(path)
+            }
+            RouteSource::Regex(_) => true,
+        }
+    }
+}
+
+/// Runs a route handler as the last middleware of a route's stack.
+struct RouteEndpoint {
+    handler: RouteHandler,
+}
+
+impl Middleware for RouteEndpoint {
+    fn handle<'a>(
+        &'a self,
+        req: &'a mut Request,
+        res: &'a mut Response,
+        _next: Next<'a>,
+    ) -> MiddlewareFuture<'a> {
+        (self.handler)(req, res)
+    }
+}
+
+/// A handler for requests matching no route under a path prefix.
+struct Fallback {
+    prefix: String,
+    handler: RouteHandler,
+    middleware: Vec<Arc<dyn Middleware + Send + Sync>>,
+}
+
+impl Fallback {
+    fn covers(&self, path: &str) -> bool {
+        self.prefix == "/"
+            || path
+                .strip_prefix(self.prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+}
+
+/// A route matched by `Router::find`, with the parameters captured from the path.
+pub struct RouteMatch<'r> {
+    pub route: &'r Route,
+    pub params: HashMap<String, String>,
 }
 
 /// A router for handling HTTP requests and routing them to appropriate handlers.
 ///
 /// The `Router` can handle routes with or without parameters, and it supports mounting sub-routers.
 ///
+/// Path pattern routes are stored in a radix tree, so matching a request costs
+/// roughly the length of its path rather than the number of routes. Static
+/// segments take precedence over parameters, which take precedence over
+/// wildcards; among otherwise identical patterns the first registered route
+/// wins. Raw regex routes are tried in registration order after the tree.
+///
+/// When a path matches but no route accepts the request method, the router
+/// answers `405 Method Not Allowed` with an `Allow` header. `OPTIONS`
+/// requests for such paths are answered with `204 No Content` and the same
+/// `Allow` header, unless an `OPTIONS` route is registered for the path.
+///
 /// # Examples
 ///
 /// ```
@@ -67,8 +278,8 @@ pub struct Route {
 ///
 ///     router.add_route(Some("GET"), "/test", |req, res| {
 ///         Box::pin(async move {
-///             res.set_status(200).await;
-///             res.body("Test route".to_string()).await;
+///             res.set_status(200);
+///             res.body("Test route".to_string());
 ///             Ok(())
 ///         })
 ///     });
@@ -77,7 +288,7 @@ pub struct Route {
 ///         "GET /api/test HTTP/1.1\r\n\r\n",
 ///         Arc::new(Mutex::new(HashMap::new())),
 ///     ).unwrap();
-/// 
+///
 ///     let mut res = Response::new(None);
 ///
 ///     let next_middleware = MockNextMiddleware::new();
@@ -86,9 +297,8 @@ pub struct Route {
 ///
 ///     router.handle(&mut req, &mut res, next.clone()).await.unwrap();
 ///
-///     let inner = res.get_inner().await;
-///     assert_eq!(inner.status_code(), Some(200));
-///     assert_eq!(inner.body(), &Some(Body::Text("Test route".to_string())));
+///     assert_eq!(res.status(), Some(200));
+///     assert_eq!(res.get_body(), Some(&Body::Text("Test route".to_string())));
 ///
 ///     let next_called = *next_middleware.called.lock().await;
 ///     assert!(!next_called);
@@ -96,8 +306,12 @@ pub struct Route {
 /// ```
 pub struct Router {
     pub base_path: String,
-    pub routes: Vec<Route>,
-    pub sub_routers: Vec<Router>,
+    routes: Vec<Route>,
+    middleware: Vec<Arc<dyn Middleware + Send + Sync>>,
+    tree: RouteTree,
+    regex_routes: Vec<usize>,
+    trailing_slash: TrailingSlash,
+    fallbacks: Vec<Fallback>,
 }
 
 impl Router {
@@ -118,18 +332,64 @@ impl Router {
         Self {
             base_path: base_path.to_string(),
             routes: Vec::new(),
-            sub_routers: Vec::new(),
+            middleware: Vec::new(),
+            tree: RouteTree::default(),
+            regex_routes: Vec::new(),
+            trailing_slash: TrailingSlash::default(),
+            fallbacks: Vec::new(),
         }
     }
 
+    /// Sets how a trailing `/` in request paths is treated.
+    ///
+    /// With `TrailingSlash::MatchBoth`, the default, `/todo` and `/todo/`
+    /// both match a route registered as `/todo`. With `Strict`, only the form
+    /// the route was registered with matches, and with `Redirect` requests
+    /// for the other form are redirected to it. Wildcard routes match either
+    /// form. The policy applies to every route of the router, including
+    /// mounted ones: the policy of a mounted or grouped router is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The trailing-slash policy.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::normalize::TrailingSlash;
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/");
+    /// router.trailing_slash(TrailingSlash::Strict);
+    /// router.get("/todo", |_req| Box::pin(async move { Ok("todo") }));
+    ///
+    /// assert!(router.find("GET", "/todo").is_some());
+    /// assert!(router.find("GET", "/todo/").is_none());
+    /// ```
+    pub fn trailing_slash(&mut self, policy: TrailingSlash) -> &mut Self {
+        self.trailing_slash = policy;
+        self
+    }
+
     /// Adds a route to the router.
     ///
+    /// The pattern uses the Express-style syntax of `PathPattern`: named
+    /// parameters (`/users/:id`), constrained parameters (`/items/:id(\d+)`),
+    /// optional parameters (`/posts/:year/:month?`) and a trailing wildcard
+    /// (`/static/*path`). It is prefixed with the router's base path and
+    /// matched against the whole request path. Use `add_regex_route` to match
+    /// with a raw regex instead.
+    ///
     /// # Arguments
     ///
     /// * `method` - The HTTP method for the route (e.g., "GET", "POST").
-    /// * `pattern` - The URL pattern for the route, which can include named parameters.
+    /// * `pattern` - The path pattern for the route, which can include named parameters.
     /// * `handler` - The handler function for the route.
     ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is not a valid `PathPattern`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -141,36 +401,42 @@ impl Router {
     ///
     /// let mut router = Router::new("/api");
     ///
-    /// router.add_route(Some("GET"), "/test", |req, res| {
+    /// router.add_route(Some("GET"), "/test/:id", |req, res| {
     ///     Box::pin(async move {
-    ///         res.set_status(200).await;
-    ///         res.body("Test route".to_string()).await;
+    ///         let id = req.param("id").unwrap_or_default().to_string();
+    ///         res.set_status(200);
+    ///         res.body(format!("Test route {}", id));
     ///         Ok(())
     ///     })
     /// });
     /// ```
-    pub fn add_route<F>(&mut self, method: Option<&str>, pattern: &str, handler: F)
+    pub fn add_route<F>(&mut self, method: Option<&str>, pattern: &str, handler: F) -> &mut Route
     where
         F: for<'a> Fn(&'a mut Request, &'a mut Response) -> MiddlewareFuture<'a>
             + Send
             + Sync
             + 'static,
     {
-        let full_pattern = format!("{}{}", self.base_path.trim_end_matches('/'), pattern);
-        let rgx = Regex::new(&full_pattern).expect("Invalid regex pattern");
-
-        self.routes.push(Route {
-            method: method.map(|m| m.to_string()),
-            pattern: rgx,
-            handler: Arc::new(handler),
-        });
+        let full_pattern = full_pattern(&self.base_path, pattern);
+        let path_pattern = PathPattern::parse(&full_pattern).unwrap_or_else(|e| panic!("{}", e));
+        self.push_route(method, RouteSource::Path(path_pattern), Arc::new(handler))
     }
 
-    /// Mounts a sub-router onto this router.
+    /// Adds a route matched with a raw regex.
+    ///
+    /// The regex is prefixed with the router's base path and is not anchored
+    /// at the end, so it should usually end with `$`. Named capture groups are
+    /// available as request parameters.
     ///
     /// # Arguments
     ///
-    /// * `sub_router` - The sub-router to mount.
+    /// * `method` - The HTTP method for the route (e.g., "GET", "POST").
+    /// * `pattern` - The regex for the route, which can include named capture groups.
+    /// * `handler` - The handler function for the route.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is not a valid regex.
     ///
     /// # Examples
     ///
@@ -178,155 +444,972 @@ impl Router {
     /// use suika_server::router::Router;
     ///
     /// let mut router = Router::new("/api");
-    /// let sub_router = Router::new("/sub");
     ///
-    /// router.mount(sub_router);
+    /// router.add_regex_route(Some("GET"), r"/items/(?P<id>[0-9a-f]{8})$", |req, res| {
+    ///     Box::pin(async move {
+    ///         res.set_status(200);
+    ///         Ok(())
+    ///     })
+    /// });
     /// ```
-    pub fn mount(&mut self, mut sub_router: Router) {
-        let combined = format!(
-            "{}{}",
-            self.base_path.trim_end_matches('/'),
-            sub_router.base_path
-        );
-        sub_router.base_path = combined;
-        self.sub_routers.push(sub_router);
+    pub fn add_regex_route<F>(
+        &mut self,
+        method: Option<&str>,
+        pattern: &str,
+        handler: F,
+    ) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request, &'a mut Response) -> MiddlewareFuture<'a>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let full_pattern = format!("{}{}", self.base_path.trim_end_matches('/'), pattern);
+        let rgx = Regex::new(&full_pattern).expect("Invalid regex pattern");
+        self.push_route(method, RouteSource::Regex(rgx), Arc::new(handler))
     }
 
-    /// Handles an incoming HTTP request by matching it to a route.
+    /// Adds a route whose handler returns a value instead of modifying the response.
     ///
-    /// This method is called internally by the `Router`'s `Middleware` implementation.
+    /// The returned value is converted with `IntoResponse` into the response
+    /// being built for the request, so handlers can return strings, bytes,
+    /// JSON values, templates, `(status, value)` tuples or a whole `Response`.
+    /// Errors are turned into error responses, as with `add_route`.
     ///
     /// # Arguments
     ///
-    /// * `req` - A mutable reference to the incoming request.
-    /// * `res` - A mutable reference to the response to be sent.
+    /// * `method` - The HTTP method for the route (e.g., "GET", "POST").
+    /// * `pattern` - The URL pattern for the route, which can include named parameters.
+    /// * `handler` - The handler function for the route.
     ///
-    /// # Returns
+    /// # Examples
     ///
-    /// A future that resolves to `Result<bool, HttpError>`, indicating whether a route was matched.
-    fn handle_internal<'a>(
-        &'a self,
-        req: &'a mut Request,
-        res: &'a mut Response,
-    ) -> Pin<Box<dyn futures::Future<Output = Result<bool, HttpError>> + Send + 'a>> {
-        Box::pin(async move {
-            for route in &self.routes {
-                if let Some(ref route_method) = route.method {
-                    if route_method.to_uppercase() != req.method().to_uppercase() {
-                        continue;
-                    }
-                }
-                if let Some(caps) = route.pattern.captures(req.path()) {
-                    let mut params = HashMap::new();
-                    for name in route.pattern.capture_names().flatten() {
-                        if let Some(value) = caps.name(name) {
-                            params.insert(name.to_string(), value.as_str().to_string());
-                        }
-                    }
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/api");
+    ///
+    /// router.route(Some("GET"), r"/items/:id(\d+)", |req| {
+    ///     Box::pin(async move {
+    ///         let id = req.param("id").unwrap_or_default().to_string();
+    ///         Ok(format!("Item {}", id))
+    ///     })
+    /// });
+    ///
+    /// router.route(Some("POST"), "/items", |_req| {
+    ///     Box::pin(async move { Ok((201, "Created")) })
+    /// });
+    /// ```
+    pub fn route<F, T>(&mut self, method: Option<&str>, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        let handler = into_route_handler(handler);
+        self.add_route(method, pattern, move |req, res| handler(req, res))
+    }
+
+    /// Adds a `GET` route whose handler returns a value, like `route`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/api");
+    /// router.get("/users/:id", |req| {
+    ///     Box::pin(async move { Ok(format!("User {}", req.param("id").unwrap_or_default())) })
+    /// });
+    /// ```
+    pub fn get<F, T>(&mut self, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.route(Some("GET"), pattern, handler)
+    }
 
-                    req.set_params(params);
+    /// Adds a `POST` route whose handler returns a value, like `route`.
+    pub fn post<F, T>(&mut self, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.route(Some("POST"), pattern, handler)
+    }
 
-                    if let Err(e) = (route.handler)(req, res).await {
-                        res.error(e).await;
-                        return Ok(true);
-                    }
-                    return Ok(true);
-                }
-            }
+    /// Adds a `PUT` route whose handler returns a value, like `route`.
+    pub fn put<F, T>(&mut self, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.route(Some("PUT"), pattern, handler)
+    }
 
-            for subr in &self.sub_routers {
-                if subr.handle_internal(req, res).await? {
-                    return Ok(true);
-                }
-            }
-            Ok(false)
-        })
+    /// Adds a `PATCH` route whose handler returns a value, like `route`.
+    pub fn patch<F, T>(&mut self, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.route(Some("PATCH"), pattern, handler)
     }
-}
 
-impl Middleware for Router {
-    /// Handles an incoming HTTP request by routing it to the appropriate handler.
+    /// Adds a `DELETE` route whose handler returns a value, like `route`.
+    pub fn delete<F, T>(&mut self, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.route(Some("DELETE"), pattern, handler)
+    }
+
+    /// Adds a route matching every method whose handler returns a value, like `route`.
+    pub fn any<F, T>(&mut self, pattern: &str, handler: F) -> &mut Route
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.route(None, pattern, handler)
+    }
+
+    /// Sets the handler for requests under this router's base path that
+    /// match no route.
     ///
-    /// # Arguments
+    /// The response status defaults to 404, and the handler's return value
+    /// is converted with `IntoResponse`, as with `route`. The router's
+    /// middleware runs around it. When the router is mounted, the fallback
+    /// stays scoped to the router's path: the fallback with the longest
+    /// matching prefix handles the request. Paths matching a route for
+    /// another method are still answered with `405 Method Not Allowed`.
     ///
-    /// * `req` - A mutable reference to the incoming request.
-    /// * `res` - A mutable reference to the response.
-    /// * `next` - The next middleware in the stack.
+    /// Without a fallback, unmatched requests are passed to the next
+    /// middleware.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A future that resolves to a `Result<(), HttpError>`.
+    /// * `handler` - The handler for unmatched requests.
     ///
     /// # Examples
     ///
     /// ```
-    /// use suika_server::request::Request;
-    /// use suika_server::response::{Response, Body};
-    /// use suika_server::middleware::{Middleware, Next, MiddlewareFuture};
     /// use suika_server::router::Router;
-    /// use regex::Regex;
-    /// use std::sync::{Arc, Mutex};
-    /// use tokio::sync::Mutex as TokioMutex;
-    /// use std::collections::HashMap;
     ///
+    /// let mut api = Router::new("/api");
+    /// api.fallback(|req| {
+    ///     Box::pin(async move { Ok(format!("No API endpoint at {}", req.path())) })
+    /// });
     ///
-    /// #[derive(Clone)]
-    /// struct MockNextMiddleware {
-    ///     called: Arc<TokioMutex<bool>>,
-    /// }
-    ///
-    /// impl MockNextMiddleware {
-    ///     fn new() -> Self {
-    ///         Self {
-    ///             called: Arc::new(TokioMutex::new(false)),
-    ///         }
-    ///     }
-    /// }
-    ///
-    /// impl Middleware for MockNextMiddleware {
-    ///     fn handle<'a>(
-    ///         &'a self,
-    ///         _req: &'a mut Request,
-    ///         _res: &'a mut Response,
-    ///         _next: Next<'a>,
-    ///     ) -> MiddlewareFuture<'a> {
-    ///         let called = Arc::clone(&self.called);
-    ///         Box::pin(async move {
-    ///             let mut called_lock = called.lock().await;
-    ///             *called_lock = true;
-    ///             Ok(())
-    ///         })
-    ///     }
-    /// }
+    /// let mut router = Router::new("/");
+    /// router.mount(api);
+    /// router.fallback(|_req| Box::pin(async move { Ok("Page not found") }));
+    /// ```
+    pub fn fallback<F, T>(&mut self, handler: F) -> &mut Self
+    where
+        F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+        T: IntoResponse + Send + 'static,
+    {
+        self.set_fallback(Fallback {
+            prefix: join_paths(&self.base_path, "/"),
+            handler: into_route_handler(handler),
+            middleware: Vec::new(),
+        });
+        self
+    }
+
+    /// Adds a middleware that runs for every route of this router.
     ///
-    /// #[tokio::main]
-    /// async fn main() {
-    ///     let mut router = Router::new("/api");
+    /// Router middleware only runs when one of the router's routes matches,
+    /// including routes of mounted sub-routers, in the order it was added.
+    /// It runs before the middleware of mounted sub-routers and routes.
     ///
-    ///     router.add_route(Some("GET"), "/test", |req, res| {
-    ///         Box::pin(async move {
-    ///             res.set_status(200).await;
-    ///             res.body("Test route".to_string()).await;
-    ///             Ok(())
-    ///         })
-    ///     });
+    /// # Arguments
     ///
-    ///     let mut req = Request::new(
-    ///         "GET /api/test HTTP/1.1\r\n\r\n", 
-    ///         Arc::new(Mutex::new(HashMap::new()))
-    ///     ).unwrap();
-    /// 
-    ///     let mut res = Response::new(None);
+    /// * `mw` - The middleware to run around the router's handlers.
     ///
-    ///     let next_middleware = MockNextMiddleware::new();
-    ///     let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(next_middleware.clone())];
-    ///     let next = Next::new(middleware_stack.as_slice());
+    /// # Examples
     ///
-    ///     router.handle(&mut req, &mut res, next.clone()).await.unwrap();
+    /// ```
+    /// use suika_server::middleware::LoggerMiddleware;
+    /// use suika_server::router::Router;
+    /// use std::sync::Arc;
     ///
-    ///     let inner = res.get_inner().await;
-    ///     assert_eq!(inner.status_code(), Some(200));
-    ///     assert_eq!(inner.body(), &Some(Body::Text("Test route".to_string())));
+    /// let mut router = Router::new("/api");
+    /// router.use_middleware(Arc::new(LoggerMiddleware));
+    /// ```
+    pub fn use_middleware(&mut self, mw: Arc<dyn Middleware + Send + Sync>) -> &mut Self {
+        self.middleware.push(mw);
+        self
+    }
+
+    /// Adds a group of routes sharing a path prefix and middleware.
+    ///
+    /// The group is a `Router` with the given base path that is mounted onto
+    /// this router once `build` returns, so middleware added to it only runs
+    /// for the group's routes. Like every mounted route, the group's routes
+    /// follow this router's trailing-slash policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The path prefix of the group, relative to this router.
+    /// * `build` - A function adding routes and middleware to the group.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::LoggerMiddleware;
+    /// use suika_server::router::Router;
+    /// use std::sync::Arc;
+    ///
+    /// let mut router = Router::new("/");
+    /// router.group("/admin", |admin| {
+    ///     admin.use_middleware(Arc::new(LoggerMiddleware));
+    ///     admin.get("/dashboard", |_req| Box::pin(async move { Ok("dashboard") }));
+    /// });
+    /// ```
+    pub fn group<F>(&mut self, prefix: &str, build: F) -> &mut Self
+    where
+        F: FnOnce(&mut Router),
+    {
+        let mut group = Router::new(prefix);
+        build(&mut group);
+        self.mount(group);
+        self
+    }
+
+    /// Adds the RESTful routes of a resource.
+    ///
+    /// Registers the resource's supported actions under `path`, named after
+    /// the static segments of the full path and the action, e.g.
+    /// `todos.index`:
+    ///
+    /// | Action    | Method         | Path             |
+    /// |-----------|----------------|------------------|
+    /// | `index`   | `GET`          | `/todos`         |
+    /// | `create`  | `POST`         | `/todos`         |
+    /// | `show`    | `GET`          | `/todos/:id`     |
+    /// | `update`  | `PUT`, `PATCH` | `/todos/:id`     |
+    /// | `destroy` | `DELETE`       | `/todos/:id`     |
+    ///
+    /// Requests for unsupported actions are answered with `405 Method Not
+    /// Allowed` and an `Allow` header listing the supported methods.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the resource's collection.
+    /// * `resource` - The resource handling the actions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the path is not a valid `PathPattern`.
+    pub fn resource<R>(&mut self, path: &str, resource: R) -> &mut Self
+    where
+        R: Resource,
+    {
+        self.resource_with(path, resource, |_| {})
+    }
+
+    /// Adds the RESTful routes of a resource, like `resource`, along with
+    /// routes nested under a single entry.
+    ///
+    /// The nested routes are added to a router based at the entry's path,
+    /// e.g. `/todos/:todo_id`, so nested resources can read the parent id
+    /// from the request parameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the resource's collection.
+    /// * `resource` - The resource handling the actions.
+    /// * `build` - A closure adding the nested routes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::resource::{Resource, ResourceAction};
+    /// use suika_server::router::Router;
+    ///
+    /// struct Todos;
+    ///
+    /// impl Resource for Todos {
+    ///     fn actions(&self) -> Vec<ResourceAction> {
+    ///         ResourceAction::ALL.to_vec()
+    ///     }
+    ///
+    ///     fn id_param(&self) -> &'static str {
+    ///         "todo_id"
+    ///     }
+    /// }
+    ///
+    /// struct Comments;
+    ///
+    /// impl Resource for Comments {
+    ///     fn actions(&self) -> Vec<ResourceAction> {
+    ///         ResourceAction::ALL.to_vec()
+    ///     }
+    /// }
+    ///
+    /// let mut router = Router::new("/");
+    /// router.resource_with("/todos", Todos, |todo| {
+    ///     todo.resource("/comments", Comments);
+    /// });
+    ///
+    /// assert!(router.find("DELETE", "/todos/1/comments/2").is_some());
+    /// ```
+    pub fn resource_with<R, F>(&mut self, path: &str, resource: R, build: F) -> &mut Self
+    where
+        R: Resource,
+        F: FnOnce(&mut Router),
+    {
+        let collection = join_paths("/", path);
+        let member = format!(
+            "{}/:{}",
+            collection.trim_end_matches('/'),
+            resource.id_param()
+        );
+        let name = resource_name(&full_pattern(&self.base_path, &collection));
+        let actions = resource.actions();
+        let resource = Arc::new(resource);
+
+        for action in actions {
+            let pattern = if action.is_member() {
+                &member
+            } else {
+                &collection
+            };
+            for method in action.methods() {
+                let resource = Arc::clone(&resource);
+                let route = self.add_route(Some(method), pattern, move |req, res| {
+                    let resource = Arc::clone(&resource);
+                    Box::pin(async move { run_action(resource.as_ref(), action, req, res).await })
+                });
+                if *method != "PATCH" {
+                    route.name(&format!("{}.{}", name, action.as_str()));
+                }
+            }
+        }
+
+        let mut nested = Router::new(&full_pattern(&self.base_path, &member));
+        build(&mut nested);
+        self.merge(nested, "/");
+        self
+    }
+
+    /// Mounts a sub-router onto this router.
+    ///
+    /// The routes of the sub-router are prefixed with this router's base path
+    /// and merged into this router, so they are matched by the same tree.
+    /// They follow this router's trailing-slash policy; the sub-router's own
+    /// policy is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `sub_router` - The sub-router to mount.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/api");
+    /// let sub_router = Router::new("/sub");
+    ///
+    /// router.mount(sub_router);
+    /// ```
+    pub fn mount(&mut self, sub_router: Router) {
+        let prefix = self.base_path.clone();
+        self.merge(sub_router, &prefix);
+    }
+
+    /// Merges the routes and fallbacks of a sub-router, prefixed with
+    /// `prefix`, into this router. The sub-router's trailing-slash policy is
+    /// dropped.
+    fn merge(&mut self, sub_router: Router, prefix: &str) {
+        for route in sub_router.routes {
+            let source = route.source.prefixed(prefix);
+            let router = join_paths(prefix, &route.router);
+            let middleware = sub_router
+                .middleware
+                .iter()
+                .chain(&route.middleware)
+                .cloned()
+                .collect();
+            self.routes.push(Route {
+                source,
+                router,
+                middleware,
+                ..route
+            });
+            self.index_route(self.routes.len() - 1);
+        }
+
+        for fallback in sub_router.fallbacks {
+            let middleware = sub_router
+                .middleware
+                .iter()
+                .chain(&fallback.middleware)
+                .cloned()
+                .collect();
+            self.set_fallback(Fallback {
+                prefix: join_paths(prefix, &fallback.prefix),
+                handler: fallback.handler,
+                middleware,
+            });
+        }
+    }
+
+    /// Returns the routes of this router, including mounted ones, in
+    /// registration order.
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Finds the route handling a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The HTTP method of the request.
+    /// * `path` - The path of the request.
+    ///
+    /// # Returns
+    ///
+    /// The matched route and its parameters, or `None` if no route matches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/api");
+    /// router.route(Some("GET"), "/users/:id", |_req| Box::pin(async move { Ok("user") }));
+    ///
+    /// let matched = router.find("GET", "/api/users/7").unwrap();
+    /// assert_eq!(matched.params.get("id"), Some(&"7".to_string()));
+    /// assert!(router.find("POST", "/api/users/7").is_none());
+    /// ```
+    pub fn find(&self, method: &str, path: &str) -> Option<RouteMatch<'_>> {
+        let mut found = None;
+        self.tree.visit(path, |endpoints, params| {
+            let route = endpoints
+                .iter()
+                .map(|&index| &self.routes[index])
+                .find(|route| route.allows(method) && self.matches_trailing_slash(route, path));
+            if let Some(route) = route {
+                found = Some(RouteMatch {
+                    route,
+                    params: params.iter().cloned().collect(),
+                });
+            }
+            found.is_some()
+        });
+        if found.is_some() {
+            return found;
+        }
+
+        for &index in &self.regex_routes {
+            let route = &self.routes[index];
+            let RouteSource::Regex(pattern) = &route.source else {
+                continue;
+            };
+            if !route.allows(method) {
+                continue;
+            }
+            if let Some(caps) = pattern.captures(path) {
+                let params = pattern
+                    .capture_names()
+                    .flatten()
+                    .filter_map(|name| {
+                        caps.name(name)
+                            .map(|value| (name.to_string(), value.as_str().to_string()))
+                    })
+                    .collect();
+                return Some(RouteMatch { route, params });
+            }
+        }
+        None
+    }
+
+    /// Returns every method registered for routes matching a path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the request.
+    ///
+    /// # Returns
+    ///
+    /// The sorted, upper-cased methods, or `None` if no route matches the
+    /// path. Routes registered without a method allow every method and are
+    /// not listed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/");
+    /// router.route(Some("GET"), "/items", |_req| Box::pin(async move { Ok("list") }));
+    /// router.route(Some("POST"), "/items", |_req| Box::pin(async move { Ok("create") }));
+    ///
+    /// assert_eq!(
+    ///     router.allowed_methods("/items"),
+    ///     Some(vec!["GET".to_string(), "POST".to_string()])
+    /// );
+    /// assert_eq!(router.allowed_methods("/other"), None);
+    /// ```
+    pub fn allowed_methods(&self, path: &str) -> Option<Vec<String>> {
+        let mut matched = false;
+        let mut methods = Vec::new();
+        let mut collect = |route: &Route| {
+            matched = true;
+            if let Some(method) = &route.method {
+                methods.push(method.to_uppercase());
+            }
+        };
+
+        self.tree.visit(path, |endpoints, _| {
+            for &index in endpoints {
+                if self.matches_trailing_slash(&self.routes[index], path) {
+                    collect(&self.routes[index]);
+                }
+            }
+            false
+        });
+        for &index in &self.regex_routes {
+            if let RouteSource::Regex(pattern) = &self.routes[index].source {
+                if pattern.is_match(path) {
+                    collect(&self.routes[index]);
+                }
+            }
+        }
+
+        if !matched {
+            return None;
+        }
+        methods.sort();
+        methods.dedup();
+        Some(methods)
+    }
+
+    /// Lists the effective routes of this router, including mounted ones.
+    ///
+    /// Every route is listed with its method, full pattern, name, the full
+    /// base path of the router it was registered on, and the middleware
+    /// wrapping it, including this router's middleware.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::middleware::LoggerMiddleware;
+    /// use suika_server::router::Router;
+    /// use std::sync::Arc;
+    ///
+    /// let mut router = Router::new("/");
+    /// router.use_middleware(Arc::new(LoggerMiddleware));
+    /// router.group("/admin", |admin| {
+    ///     admin
+    ///         .get("/dashboard", |_req| Box::pin(async move { Ok("dashboard") }))
+    ///         .name("dashboard");
+    /// });
+    ///
+    /// assert_eq!(
+    ///     router.route_table().to_string(),
+    ///     "METHOD  PATTERN           NAME       ROUTER  MIDDLEWARE\n\
+    ///      GET     /admin/dashboard  dashboard  /admin  LoggerMiddleware\n"
+    /// );
+    /// ```
+    pub fn route_table(&self) -> RouteTable {
+        let routes = self
+            .routes
+            .iter()
+            .map(|route| RouteInfo {
+                method: route.method.clone(),
+                source: route.source.clone(),
+                name: route.name.clone(),
+                router: route.router.clone(),
+                middleware: self
+                    .middleware
+                    .iter()
+                    .chain(&route.middleware)
+                    .map(|mw| mw.name())
+                    .collect(),
+            })
+            .collect();
+        RouteTable::new(routes)
+    }
+
+    /// Finds shadowed routes and duplicate route names.
+    ///
+    /// Call it once all routes are registered, e.g. to fail at startup
+    /// instead of silently never reaching a route. See
+    /// `RouteTable::conflicts` for the rules.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    ///
+    /// let mut router = Router::new("/");
+    /// router.any("/items/:id", |_req| Box::pin(async move { Ok("any") }));
+    /// router.get("/items/:slug", |_req| Box::pin(async move { Ok("never") }));
+    ///
+    /// assert_eq!(router.conflicts().len(), 1);
+    /// ```
+    pub fn conflicts(&self) -> Vec<RouteConflict> {
+        self.route_table().conflicts()
+    }
+
+    /// Builds the URL of a named route.
+    ///
+    /// Parameters used by the route's pattern are percent-encoded into the
+    /// path, and the remaining ones are appended as a query string.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name given to the route with `Route::name`.
+    /// * `params` - The parameter values, by name.
+    ///
+    /// # Returns
+    ///
+    /// The URL, or an `HttpError` if no route has the name, the route is a
+    /// regex route, or the parameters do not fit its pattern.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::router::Router;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut router = Router::new("/shop");
+    /// router
+    ///     .get(r"/items/:id(\d+)", |_req| Box::pin(async move { Ok("item") }))
+    ///     .name("item");
+    ///
+    /// let params = HashMap::from([("id", "42"), ("ref", "home page")]);
+    /// assert_eq!(
+    ///     router.url_for("item", &params).unwrap(),
+    ///     "/shop/items/42?ref=home%20page"
+    /// );
+    /// assert!(router.url_for("missing", &HashMap::new()).is_err());
+    /// ```
+    pub fn url_for(&self, name: &str, params: &HashMap<&str, &str>) -> Result<String, HttpError> {
+        self.routes
+            .iter()
+            .find(|route| route.name.as_deref() == Some(name))
+            .ok_or_else(|| unknown_route(name))?
+            .source
+            .url_for(params)
+    }
+
+    /// Returns a snapshot of the named routes that can build URLs without
+    /// borrowing the router.
+    ///
+    /// The snapshot is cheap to clone and can be moved into handlers or
+    /// installed into a template engine. Routes added afterwards are not
+    /// included.
+    pub fn urls(&self) -> RouteUrls {
+        let mut routes = HashMap::new();
+        for route in &self.routes {
+            if let Some(name) = &route.name {
+                routes
+                    .entry(name.clone())
+                    .or_insert_with(|| route.source.clone());
+            }
+        }
+        RouteUrls {
+            routes: Arc::new(routes),
+        }
+    }
+
+    /// Returns the path to redirect a request to under the
+    /// `TrailingSlash::Redirect` policy, if the path only matches a route
+    /// with its trailing `/` added or removed.
+    pub(crate) fn trailing_slash_redirect(&self, method: &str, path: &str) -> Option<String> {
+        if self.trailing_slash != TrailingSlash::Redirect || path == "/" {
+            return None;
+        }
+        let canonical = toggle_trailing_slash(path);
+        (self.find(method, &canonical).is_some() || self.allowed_methods(&canonical).is_some())
+            .then_some(canonical)
+    }
+
+    /// Returns `true` if the router answers a request itself instead of
+    /// passing it to the next middleware.
+    pub(crate) fn handles(&self, method: &str, path: &str) -> bool {
+        self.find(method, path).is_some()
+            || self.allowed_methods(path).is_some()
+            || self.trailing_slash_redirect(method, path).is_some()
+            || self.find_fallback(path).is_some()
+    }
+
+    fn find_fallback(&self, path: &str) -> Option<&Fallback> {
+        self.fallbacks
+            .iter()
+            .filter(|fallback| fallback.covers(path))
+            .max_by_key(|fallback| fallback.prefix.len())
+    }
+
+    fn set_fallback(&mut self, fallback: Fallback) {
+        match self
+            .fallbacks
+            .iter_mut()
+            .find(|existing| existing.prefix == fallback.prefix)
+        {
+            Some(existing) => *existing = fallback,
+            None => self.fallbacks.push(fallback),
+        }
+    }
+
+    /// Runs a handler wrapped in the router's middleware and the given
+    /// route middleware, turning handler errors into error responses.
+    async fn run_handler(
+        &self,
+        handler: &RouteHandler,
+        middleware: &[Arc<dyn Middleware + Send + Sync>],
+        req: &mut Request,
+        res: &mut Response,
+    ) {
+        let result = if self.middleware.is_empty() && middleware.is_empty() {
+            handler(req, res).await
+        } else {
+            let mut stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+                self.middleware.iter().chain(middleware).cloned().collect();
+            stack.push(Arc::new(RouteEndpoint {
+                handler: Arc::clone(handler),
+            }));
+            Next::new(&stack).run(req, res).await
+        };
+
+        if let Err(e) = result {
+            res.error(e);
+        }
+    }
+
+    fn matches_trailing_slash(&self, route: &Route, path: &str) -> bool {
+        self.trailing_slash == TrailingSlash::MatchBoth || route.matches_trailing_slash(path)
+    }
+
+    fn push_route(
+        &mut self,
+        method: Option<&str>,
+        source: RouteSource,
+        handler: RouteHandler,
+    ) -> &mut Route {
+        self.routes.push(Route {
+            name: None,
+            method: method.map(|m| m.to_string()),
+            source,
+            router: join_paths(&self.base_path, "/"),
+            handler,
+            middleware: Vec::new(),
+        });
+        let index = self.routes.len() - 1;
+        self.index_route(index);
+        &mut self.routes[index]
+    }
+
+    fn index_route(&mut self, index: usize) {
+        match &self.routes[index].source {
+            RouteSource::Path(path_pattern) => self.tree.insert(path_pattern, index),
+            RouteSource::Regex(_) => self.regex_routes.push(index),
+        }
+    }
+}
+
+/// The named routes of a router, used to build URLs for them.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::router::Router;
+/// use suika_templates::TemplateEngine;
+/// use std::collections::HashMap;
+///
+/// let mut router = Router::new("/");
+/// router
+///     .get("/users/:id", |_req| Box::pin(async move { Ok("user") }))
+///     .name("user");
+///
+/// let urls = router.urls();
+/// let params = HashMap::from([("id", "7")]);
+/// assert_eq!(urls.url_for("user", &params).unwrap(), "/users/7");
+///
+/// let mut engine = TemplateEngine::new();
+/// engine.add_template("link", r#"<a href="<%= url_for("user", id="7") %>">Me</a>"#);
+/// urls.install(&mut engine);
+///
+/// assert_eq!(
+///     engine.render("link", &HashMap::new()).unwrap(),
+///     r#"<a href="/users/7">Me</a>"#
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RouteUrls {
+    routes: Arc<HashMap<String, RouteSource>>,
+}
+
+impl RouteUrls {
+    /// Builds the URL of a named route, like `Router::url_for`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name given to the route with `Route::name`.
+    /// * `params` - The parameter values, by name.
+    pub fn url_for(&self, name: &str, params: &HashMap<&str, &str>) -> Result<String, HttpError> {
+        self.routes
+            .get(name)
+            .ok_or_else(|| unknown_route(name))?
+            .url_for(params)
+    }
+
+    /// Makes the routes available to templates through `url_for`.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The template engine to install the routes into.
+    pub fn install(&self, engine: &mut TemplateEngine) {
+        let urls = self.clone();
+        engine.set_url_for(move |name, args| {
+            let params = args
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            urls.url_for(name, &params).map_err(|e| e.to_string())
+        });
+    }
+}
+
+/// Wraps a handler returning a value into a handler writing it into the
+/// response with `IntoResponse`.
+fn into_route_handler<F, T>(handler: F) -> RouteHandler
+where
+    F: for<'a> Fn(&'a mut Request) -> ResponseFuture<'a, T> + Send + Sync + 'static,
+    T: IntoResponse + Send + 'static,
+{
+    let handler = Arc::new(handler);
+    Arc::new(move |req, res| {
+        let handler = Arc::clone(&handler);
+        Box::pin(async move {
+            let value = handler(req).await?;
+            value.into_response(res)
+        })
+    })
+}
+
+/// Prefixes a route pattern with a base path. A `/` pattern maps to the base
+/// path itself, while an explicit trailing `/` of other patterns is kept.
+fn full_pattern(base: &str, pattern: &str) -> String {
+    let base = base.trim_end_matches('/');
+    match pattern {
+        "" | "/" if !base.is_empty() => base.to_string(),
+        _ => format!("{}{}", base, pattern),
+    }
+}
+
+/// Joins a base path and a path, keeping a single `/` between them and no
+/// trailing `/` except for the root.
+fn join_paths(base: &str, path: &str) -> String {
+    let joined = format!("{}{}", base.trim_end_matches('/'), path);
+    match joined.trim_end_matches('/') {
+        "" => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Names a resource after the static segments of its path, e.g. `api.todos`
+/// for `/api/todos`.
+fn resource_name(path: &str) -> String {
+    path.split('/')
+        .filter(|segment| !segment.is_empty() && !segment.starts_with([':', '*']))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn unknown_route(name: &str) -> HttpError {
+    HttpError::InternalServerError(format!("No route named {:?}", name))
+}
+
+impl Middleware for Router {
+    /// Handles an incoming HTTP request by routing it to the appropriate handler.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A mutable reference to the incoming request.
+    /// * `res` - A mutable reference to the response.
+    /// * `next` - The next middleware in the stack.
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to a `Result<(), HttpError>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::request::Request;
+    /// use suika_server::response::{Response, Body};
+    /// use suika_server::middleware::{Middleware, Next, MiddlewareFuture};
+    /// use suika_server::router::Router;
+    /// use regex::Regex;
+    /// use std::sync::{Arc, Mutex};
+    /// use tokio::sync::Mutex as TokioMutex;
+    /// use std::collections::HashMap;
+    ///
+    ///
+    /// #[derive(Clone)]
+    /// struct MockNextMiddleware {
+    ///     called: Arc<TokioMutex<bool>>,
+    /// }
+    ///
+    /// impl MockNextMiddleware {
+    ///     fn new() -> Self {
+    ///         Self {
+    ///             called: Arc::new(TokioMutex::new(false)),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// impl Middleware for MockNextMiddleware {
+    ///     fn handle<'a>(
+    ///         &'a self,
+    ///         _req: &'a mut Request,
+    ///         _res: &'a mut Response,
+    ///         _next: Next<'a>,
+    ///     ) -> MiddlewareFuture<'a> {
+    ///         let called = Arc::clone(&self.called);
+    ///         Box::pin(async move {
+    ///             let mut called_lock = called.lock().await;
+    ///             *called_lock = true;
+    ///             Ok(())
+    ///         })
+    ///     }
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut router = Router::new("/api");
+    ///
+    ///     router.add_route(Some("GET"), "/test", |req, res| {
+    ///         Box::pin(async move {
+    ///             res.set_status(200);
+    ///             res.body("Test route".to_string());
+    ///             Ok(())
+    ///         })
+    ///     });
+    ///
+    ///     let mut req = Request::new(
+    ///         "GET /api/test HTTP/1.1\r\n\r\n",
+    ///         Arc::new(Mutex::new(HashMap::new()))
+    ///     ).unwrap();
+    ///
+    ///     let mut res = Response::new(None);
+    ///
+    ///     let next_middleware = MockNextMiddleware::new();
+    ///     let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> = vec![Arc::new(next_middleware.clone())];
+    ///     let next = Next::new(middleware_stack.as_slice());
+    ///
+    ///     router.handle(&mut req, &mut res, next.clone()).await.unwrap();
+    ///
+    ///     assert_eq!(res.status(), Some(200));
+    ///     assert_eq!(res.get_body(), Some(&Body::Text("Test route".to_string())));
     ///
     ///     let next_called = *next_middleware.called.lock().await;
     ///     assert!(!next_called);
@@ -339,12 +1422,53 @@ impl Middleware for Router {
         mut next: Next<'a>,
     ) -> MiddlewareFuture<'a> {
         Box::pin(async move {
-            let matched_route = self.handle_internal(req, res).await;
-            if let Err(e) = matched_route {
-                res.error(e).await;
-            } else if !matched_route.unwrap_or(false) {
-                next.run(req, res).await?;
-            }
+            let Some(matched) = self.find(req.method(), req.path()) else {
+                let Some(mut allowed) = self.allowed_methods(req.path()) else {
+                    if let Some(canonical) = self.trailing_slash_redirect(req.method(), req.path())
+                    {
+                        let query = req
+                            .query_params()
+                            .iter()
+                            .map(|(key, value)| (key.as_str(), value.as_str()))
+                            .collect();
+                        res.redirect(
+                            &build_url(&canonical, &query),
+                            RedirectKind::PermanentRedirect,
+                        )?;
+                        return Ok(());
+                    }
+                    let Some(fallback) = self.find_fallback(req.path()) else {
+                        return next.run(req, res).await;
+                    };
+                    res.set_status(404);
+                    self.run_handler(&fallback.handler, &fallback.middleware, req, res)
+                        .await;
+                    return Ok(());
+                };
+                if !allowed.iter().any(|m| m == "OPTIONS") {
+                    allowed.push("OPTIONS".to_string());
+                }
+                let allow = allowed.join(", ");
+
+                if req.method().eq_ignore_ascii_case("OPTIONS") {
+                    res.no_content().header("Allow", &allow);
+                } else {
+                    res.error(HttpError::MethodNotAllowed(
+                        "Method Not Allowed".to_string(),
+                    ))
+                    .header("Allow", &allow);
+                }
+                return Ok(());
+            };
+
+            let mut params = req.params().clone();
+            params.extend(matched.params);
+            req.set_params(params);
+            let route = matched.route;
+            #[cfg(feature = "tracing")]
+            tracing::Span::current().record("route", route.source.as_str());
+            self.run_handler(&route.handler, &route.middleware, req, res)
+                .await;
             Ok(())
         })
     }
@@ -355,6 +1479,7 @@ mod tests {
     use super::*;
     use crate::middleware::{Middleware, Next};
     use crate::request::Request;
+    use crate::resource::ResourceAction;
     use crate::response::{Body, Response};
     use std::sync::{Arc, Mutex};
     use tokio::sync::Mutex as TokioMutex;
@@ -390,19 +1515,56 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_router_handles_route() {
+    async fn test_router_handles_route() {
+        let mut router = Router::new("/api");
+
+        router.add_route(Some("GET"), "/test", |_req, res| {
+            Box::pin(async move {
+                res.set_status(200);
+                res.body("Test route".to_string());
+                Ok(())
+            })
+        });
+
+        let mut req = Request::new(
+            "GET /api/test HTTP/1.1\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+
+        let next_middleware = MockNextMiddleware::new();
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(next_middleware.clone())];
+        let next = Next::new(middleware_stack.as_slice());
+
+        router
+            .handle(&mut req, &mut res, next.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(res.get_body(), Some(&Body::Text("Test route".to_string())));
+
+        let next_called = *next_middleware.called.lock().await;
+        assert!(!next_called);
+    }
+
+    #[tokio::test]
+    async fn test_router_handles_route_with_params() {
         let mut router = Router::new("/api");
 
-        router.add_route(Some("GET"), "/test", |_req, res| {
+        router.add_route(Some("GET"), r"/test/:id(\d+)", |req, res| {
             Box::pin(async move {
-                res.set_status(200).await;
-                res.body("Test route".to_string()).await;
+                let id = req.param("id").expect("Expected id parameter");
+                res.set_status(200);
+                res.body(format!("Test route with id: {}", id));
                 Ok(())
             })
         });
 
         let mut req = Request::new(
-            "GET /api/test HTTP/1.1\r\n\r\n",
+            "GET /api/test/123 HTTP/1.1\r\n\r\n",
             Arc::new(Mutex::new(HashMap::new())),
         )
         .unwrap();
@@ -418,49 +1580,70 @@ mod tests {
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
-        assert_eq!(inner.body(), &Some(Body::Text("Test route".to_string())));
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text("Test route with id: 123".to_string()))
+        );
 
         let next_called = *next_middleware.called.lock().await;
         assert!(!next_called);
     }
 
     #[tokio::test]
-    async fn test_router_handles_route_with_params() {
+    async fn test_router_handles_returned_values() {
         let mut router = Router::new("/api");
 
-        router.add_route(Some("GET"), "/test/(?P<id>\\d+)", |req, res| {
+        router.route(Some("GET"), r"/items/:id(\d+)", |req| {
             Box::pin(async move {
-                let id = req.param("id").expect("Expected id parameter");
-                res.set_status(200).await;
-                res.body(format!("Test route with id: {}", id)).await;
-                Ok(())
+                let id = req.param("id").expect("Expected id parameter").to_string();
+                Ok(format!("Item {}", id))
             })
         });
 
+        router.route(Some("GET"), "/missing", |_req| {
+            Box::pin(
+                async move { Err::<String, _>(HttpError::NotFound("Missing item".to_string())) },
+            )
+        });
+
+        let next_middleware = MockNextMiddleware::new();
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(next_middleware.clone())];
+
         let mut req = Request::new(
-            "GET /api/test/123 HTTP/1.1\r\n\r\n",
+            "GET /api/items/7 HTTP/1.1\r\n\r\n",
             Arc::new(Mutex::new(HashMap::new())),
         )
         .unwrap();
         let mut res = Response::new(None);
+        router
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
+            .await
+            .unwrap();
 
-        let next_middleware = MockNextMiddleware::new();
-        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
-            vec![Arc::new(next_middleware.clone())];
-        let next = Next::new(middleware_stack.as_slice());
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(
+            res.get_header("Content-Type"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(res.get_body(), Some(&Body::Text("Item 7".to_string())));
 
+        let mut req = Request::new(
+            "GET /api/missing HTTP/1.1\r\n\r\n",
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
         router
-            .handle(&mut req, &mut res, next.clone())
+            .handle(&mut req, &mut res, Next::new(middleware_stack.as_slice()))
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
+        assert_eq!(res.status(), Some(404));
         assert_eq!(
-            inner.body(),
-            &Some(Body::Text("Test route with id: 123".to_string()))
+            res.get_body(),
+            Some(&Body::Text("Missing item".to_string()))
         );
 
         let next_called = *next_middleware.called.lock().await;
@@ -474,8 +1657,8 @@ mod tests {
 
         sub_router.add_route(Some("GET"), "/test", |_req, res| {
             Box::pin(async move {
-                res.set_status(200).await;
-                res.body("Sub router test route".to_string()).await;
+                res.set_status(200);
+                res.body("Sub router test route".to_string());
                 Ok(())
             })
         });
@@ -499,11 +1682,10 @@ mod tests {
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
+        assert_eq!(res.status(), Some(200));
         assert_eq!(
-            inner.body(),
-            &Some(Body::Text("Sub router test route".to_string()))
+            res.get_body(),
+            Some(&Body::Text("Sub router test route".to_string()))
         );
 
         let next_called = *next_middleware.called.lock().await;
@@ -516,8 +1698,8 @@ mod tests {
 
         router.add_route(Some("GET"), "/test", |_req, res| {
             Box::pin(async move {
-                res.set_status(200).await;
-                res.body("Test route".to_string()).await;
+                res.set_status(200);
+                res.body("Test route".to_string());
                 Ok(())
             })
         });
@@ -542,4 +1724,594 @@ mod tests {
         let next_called = *next_middleware.called.lock().await;
         assert!(next_called);
     }
+
+    async fn dispatch(router: &Router, path: &str) -> (Response, bool) {
+        dispatch_method(router, "GET", path).await
+    }
+
+    async fn dispatch_method(router: &Router, method: &str, path: &str) -> (Response, bool) {
+        let mut req = Request::new(
+            &format!("{} {} HTTP/1.1\r\n\r\n", method, path),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+
+        let next_middleware = MockNextMiddleware::new();
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(next_middleware.clone())];
+        let next = Next::new(middleware_stack.as_slice());
+
+        router.handle(&mut req, &mut res, next).await.unwrap();
+
+        let next_called = *next_middleware.called.lock().await;
+        (res, next_called)
+    }
+
+    #[tokio::test]
+    async fn test_router_path_patterns_are_anchored() {
+        let mut router = Router::new("/");
+
+        router.route(Some("GET"), "/todo", |_req| {
+            Box::pin(async move { Ok("todo") })
+        });
+        router.route(Some("GET"), "/static/*path", |req| {
+            Box::pin(async move { Ok(req.param("path").unwrap_or_default().to_string()) })
+        });
+
+        let (res, next_called) = dispatch(&router, "/todo").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("todo".to_string())));
+        assert!(!next_called);
+
+        let (_, next_called) = dispatch(&router, "/todo/").await;
+        assert!(!next_called);
+
+        let (_, next_called) = dispatch(&router, "/todos/archive").await;
+        assert!(next_called);
+
+        let (res, next_called) = dispatch(&router, "/static/css/site.css").await;
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text("css/site.css".to_string()))
+        );
+        assert!(!next_called);
+    }
+
+    #[tokio::test]
+    async fn test_router_handles_regex_route() {
+        let mut router = Router::new("/api");
+
+        router.add_regex_route(Some("GET"), r"/hex/(?P<id>[0-9a-f]+)$", |req, res| {
+            Box::pin(async move {
+                let id = req.param("id").expect("Expected id parameter").to_string();
+                res.set_status(200);
+                res.body(id);
+                Ok(())
+            })
+        });
+
+        let (res, next_called) = dispatch(&router, "/api/hex/beef").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("beef".to_string())));
+        assert!(!next_called);
+
+        let (_, next_called) = dispatch(&router, "/api/hex/xyz").await;
+        assert!(next_called);
+    }
+
+    #[tokio::test]
+    async fn test_router_mounts_nested_sub_routers() {
+        let mut router = Router::new("/api");
+        let mut users = Router::new("/users");
+        let mut posts = Router::new("/posts");
+
+        posts.route(Some("GET"), "/:post_id", |req| {
+            Box::pin(async move { Ok(req.param("post_id").unwrap_or_default().to_string()) })
+        });
+        users.mount(posts);
+        router.mount(users);
+
+        let (res, next_called) = dispatch(&router, "/api/users/posts/9").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("9".to_string())));
+        assert!(!next_called);
+
+        let (_, next_called) = dispatch(&router, "/users/posts/9").await;
+        assert!(next_called);
+    }
+
+    #[tokio::test]
+    async fn test_router_answers_method_not_allowed() {
+        let mut router = Router::new("/");
+        router.route(Some("GET"), "/items/:id", |_req| {
+            Box::pin(async move { Ok("item") })
+        });
+        router.route(Some("DELETE"), "/items/:id", |_req| {
+            Box::pin(async move { Ok("deleted") })
+        });
+        router.route(Some("PUT"), "/items/special", |_req| {
+            Box::pin(async move { Ok("special") })
+        });
+
+        let (res, next_called) = dispatch_method(&router, "POST", "/items/1").await;
+        assert_eq!(res.status(), Some(405));
+        assert_eq!(res.get_header("Allow"), Some("DELETE, GET, OPTIONS"));
+        assert!(!next_called);
+
+        let (res, _) = dispatch_method(&router, "POST", "/items/special").await;
+        assert_eq!(res.status(), Some(405));
+        assert_eq!(res.get_header("Allow"), Some("DELETE, GET, PUT, OPTIONS"));
+
+        let (_, next_called) = dispatch_method(&router, "POST", "/other").await;
+        assert!(next_called);
+    }
+
+    #[tokio::test]
+    async fn test_router_answers_options() {
+        let mut router = Router::new("/");
+        router.route(Some("GET"), "/items", |_req| {
+            Box::pin(async move { Ok("items") })
+        });
+        router.route(Some("GET"), "/custom", |_req| {
+            Box::pin(async move { Ok("custom") })
+        });
+        router.route(Some("OPTIONS"), "/custom", |_req| {
+            Box::pin(async move { Ok((200, "custom options")) })
+        });
+
+        let (res, next_called) = dispatch_method(&router, "OPTIONS", "/items").await;
+        assert_eq!(res.status(), Some(204));
+        assert_eq!(res.get_header("Allow"), Some("GET, OPTIONS"));
+        assert_eq!(res.get_body(), None);
+        assert!(!next_called);
+
+        let (res, _) = dispatch_method(&router, "OPTIONS", "/custom").await;
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text("custom options".to_string()))
+        );
+    }
+
+    struct RecordingMiddleware {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Middleware for RecordingMiddleware {
+        fn handle<'a>(
+            &'a self,
+            req: &'a mut Request,
+            res: &'a mut Response,
+            mut next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                self.log
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} before", self.name));
+                let result = next.run(req, res).await;
+                self.log
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} after", self.name));
+                result
+            })
+        }
+    }
+
+    fn recording(name: &'static str, log: &Arc<Mutex<Vec<String>>>) -> Arc<RecordingMiddleware> {
+        Arc::new(RecordingMiddleware {
+            name,
+            log: Arc::clone(log),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_router_method_shortcuts() {
+        let mut router = Router::new("/");
+        router.get("/item", |_req| Box::pin(async move { Ok("get") }));
+        router.post("/item", |_req| Box::pin(async move { Ok("post") }));
+        router.put("/item", |_req| Box::pin(async move { Ok("put") }));
+        router.patch("/item", |_req| Box::pin(async move { Ok("patch") }));
+        router.delete("/item", |_req| Box::pin(async move { Ok("delete") }));
+        router.any("/anything", |_req| Box::pin(async move { Ok("any") }));
+
+        for method in ["GET", "POST", "PUT", "PATCH", "DELETE"] {
+            let (res, _) = dispatch_method(&router, method, "/item").await;
+            assert_eq!(res.get_body(), Some(&Body::Text(method.to_lowercase())));
+        }
+
+        let (res, _) = dispatch_method(&router, "PROPFIND", "/anything").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("any".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_router_runs_scoped_middleware_in_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut router = Router::new("/");
+        router.use_middleware(recording("router", &log));
+
+        router.get("/public", |_req| Box::pin(async move { Ok("public") }));
+        router.group("/admin", |admin| {
+            admin.use_middleware(recording("group", &log));
+            admin
+                .get("/dashboard", |_req| {
+                    Box::pin(async move { Ok("dashboard") })
+                })
+                .use_middleware(recording("route", &log));
+        });
+
+        let (res, _) = dispatch(&router, "/admin/dashboard").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("dashboard".to_string())));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "router before",
+                "group before",
+                "route before",
+                "route after",
+                "group after",
+                "router after",
+            ]
+        );
+
+        log.lock().unwrap().clear();
+        let (res, _) = dispatch(&router, "/public").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("public".to_string())));
+        assert_eq!(*log.lock().unwrap(), vec!["router before", "router after"]);
+
+        log.lock().unwrap().clear();
+        let (_, next_called) = dispatch(&router, "/missing").await;
+        assert!(next_called);
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_router_builds_urls_for_named_routes() {
+        let mut router = Router::new("/shop");
+        router.group("/items", |items| {
+            items
+                .get(r"/:id(\d+)/:tab?", |_req| {
+                    Box::pin(async move { Ok("item") })
+                })
+                .name("item");
+        });
+        router
+            .get("/files/*path", |_req| Box::pin(async move { Ok("file") }))
+            .name("file");
+        router
+            .add_regex_route(Some("GET"), r"/legacy/\d+$", |_req, _res| {
+                Box::pin(async move { Ok(()) })
+            })
+            .name("legacy");
+
+        let params = HashMap::from([("id", "42")]);
+        assert_eq!(router.url_for("item", &params).unwrap(), "/shop/items/42");
+
+        let params = HashMap::from([("id", "42"), ("tab", "q&a"), ("sort", "new first")]);
+        assert_eq!(
+            router.url_for("item", &params).unwrap(),
+            "/shop/items/42/q%26a?sort=new%20first"
+        );
+
+        let params = HashMap::from([("path", "docs/read me.txt")]);
+        assert_eq!(
+            router.url_for("file", &params).unwrap(),
+            "/shop/files/docs/read%20me.txt"
+        );
+
+        assert!(router
+            .url_for("item", &HashMap::from([("id", "x")]))
+            .is_err());
+        assert!(router.url_for("legacy", &HashMap::new()).is_err());
+        assert!(router.url_for("missing", &HashMap::new()).is_err());
+
+        let urls = router.urls();
+        assert_eq!(
+            urls.url_for("item", &HashMap::from([("id", "1")])).unwrap(),
+            "/shop/items/1"
+        );
+        assert_eq!(router.routes()[0].get_name(), Some("item"));
+    }
+
+    #[tokio::test]
+    async fn test_router_trailing_slash_policies() {
+        fn build(policy: TrailingSlash) -> Router {
+            let mut router = Router::new("/api");
+            router.trailing_slash(policy);
+            router.get("/", |_req| Box::pin(async move { Ok("root") }));
+            router.get("/todo", |_req| Box::pin(async move { Ok("todo") }));
+            router.get("/dir/", |_req| Box::pin(async move { Ok("dir") }));
+            router.get("/files/*path", |_req| Box::pin(async move { Ok("files") }));
+            router
+        }
+
+        let router = build(TrailingSlash::MatchBoth);
+        for path in [
+            "/api",
+            "/api/",
+            "/api/todo",
+            "/api/todo/",
+            "/api/dir",
+            "/api/dir/",
+        ] {
+            let (res, next_called) = dispatch(&router, path).await;
+            assert!(!next_called, "{}", path);
+            assert_eq!(res.status(), Some(200), "{}", path);
+        }
+
+        let router = build(TrailingSlash::Strict);
+        for (path, matches) in [
+            ("/api", true),
+            ("/api/", false),
+            ("/api/todo", true),
+            ("/api/todo/", false),
+            ("/api/dir", false),
+            ("/api/dir/", true),
+            ("/api/files/a/", true),
+        ] {
+            let (_, next_called) = dispatch(&router, path).await;
+            assert_eq!(!next_called, matches, "{}", path);
+        }
+
+        let router = build(TrailingSlash::Redirect);
+        let (res, _) = dispatch(&router, "/api/todo/?page=2").await;
+        assert_eq!(res.status(), Some(308));
+        assert_eq!(res.get_header("Location"), Some("/api/todo?page=2"));
+
+        let (res, _) = dispatch(&router, "/api/dir").await;
+        assert_eq!(res.status(), Some(308));
+        assert_eq!(res.get_header("Location"), Some("/api/dir/"));
+
+        let (res, _) = dispatch(&router, "/api/todo").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("todo".to_string())));
+
+        let (_, next_called) = dispatch(&router, "/api/missing/").await;
+        assert!(next_called);
+    }
+
+    #[tokio::test]
+    async fn test_router_mounted_routes_follow_parent_trailing_slash_policy() {
+        let mut router = Router::new("/");
+        router.trailing_slash(TrailingSlash::Strict);
+        let mut sub_router = Router::new("/sub");
+        sub_router.trailing_slash(TrailingSlash::MatchBoth);
+        sub_router.get("/todo", |_req| Box::pin(async move { Ok("todo") }));
+        router.mount(sub_router);
+        router.group("/group", |group| {
+            group.trailing_slash(TrailingSlash::Redirect);
+            group.get("/todo", |_req| Box::pin(async move { Ok("todo") }));
+        });
+
+        for (path, matches) in [
+            ("/sub/todo", true),
+            ("/sub/todo/", false),
+            ("/group/todo", true),
+            ("/group/todo/", false),
+        ] {
+            let (_, next_called) = dispatch(&router, path).await;
+            assert_eq!(!next_called, matches, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router_matches_normalized_paths() {
+        let mut router = Router::new("/");
+        router.get("/public/test.js", |_req| Box::pin(async move { Ok("js") }));
+
+        for path in [
+            "/public//test.js",
+            "/public/./css/../test.js",
+            "//public/test.js",
+        ] {
+            let (res, _) = dispatch(&router, path).await;
+            assert_eq!(
+                res.get_body(),
+                Some(&Body::Text("js".to_string())),
+                "{}",
+                path
+            );
+        }
+    }
+    #[tokio::test]
+    async fn test_router_runs_scoped_fallbacks() {
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let mut api = Router::new("/api");
+        api.use_middleware(recording("api", &log));
+        api.get("/users", |_req| Box::pin(async move { Ok("users") }));
+        api.fallback(|req| Box::pin(async move { Ok(format!("no api {}", req.path())) }));
+
+        let mut router = Router::new("/");
+        router.get("/", |_req| Box::pin(async move { Ok("home") }));
+        router.mount(api);
+        router.fallback(|_req| Box::pin(async move { Ok("no page") }));
+
+        let (res, next_called) = dispatch(&router, "/api/posts").await;
+        assert_eq!(res.status(), Some(404));
+        assert_eq!(
+            res.get_body(),
+            Some(&Body::Text("no api /api/posts".to_string()))
+        );
+        assert!(!next_called);
+        assert_eq!(*log.lock().unwrap(), vec!["api before", "api after"]);
+
+        let (res, _) = dispatch(&router, "/apis").await;
+        assert_eq!(res.status(), Some(404));
+        assert_eq!(res.get_body(), Some(&Body::Text("no page".to_string())));
+
+        let (res, _) = dispatch_method(&router, "POST", "/api/users").await;
+        assert_eq!(res.status(), Some(405));
+    }
+
+    #[tokio::test]
+    async fn test_router_without_fallback_calls_next() {
+        let mut api = Router::new("/api");
+        api.fallback(|_req| Box::pin(async move { Ok("no api") }));
+
+        let mut router = Router::new("/");
+        router.mount(api);
+
+        let (res, next_called) = dispatch(&router, "/other").await;
+        assert_eq!(res.status(), None);
+        assert!(next_called);
+
+        let (res, next_called) = dispatch(&router, "/api/other").await;
+        assert_eq!(res.status(), Some(404));
+        assert!(!next_called);
+    }
+    struct Items {
+        actions: Vec<ResourceAction>,
+        id_param: &'static str,
+    }
+
+    impl Resource for Items {
+        fn actions(&self) -> Vec<ResourceAction> {
+            self.actions.clone()
+        }
+
+        fn id_param(&self) -> &'static str {
+            self.id_param
+        }
+
+        fn index<'a>(
+            &'a self,
+            req: &'a mut Request,
+            res: &'a mut Response,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                let parent = req.param("todo_id").unwrap_or_default().to_string();
+                res.text(format!("index {}", parent));
+                Ok(())
+            })
+        }
+
+        fn show<'a>(&'a self, req: &'a mut Request, res: &'a mut Response) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.text(format!(
+                    "show {}",
+                    req.param(self.id_param).unwrap_or_default()
+                ));
+                Ok(())
+            })
+        }
+
+        fn update<'a>(
+            &'a self,
+            req: &'a mut Request,
+            res: &'a mut Response,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.text(format!(
+                    "update {}",
+                    req.param(self.id_param).unwrap_or_default()
+                ));
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router_resource_routes_only_listed_actions() {
+        struct Unlisted;
+
+        impl Resource for Unlisted {
+            fn index<'a>(
+                &'a self,
+                _req: &'a mut Request,
+                res: &'a mut Response,
+            ) -> MiddlewareFuture<'a> {
+                Box::pin(async move {
+                    res.text("unlisted".to_string());
+                    Ok(())
+                })
+            }
+        }
+
+        let mut router = Router::new("/");
+        router.resource("/unlisted", Unlisted);
+        router.resource(
+            "/items",
+            Items {
+                actions: vec![ResourceAction::Index, ResourceAction::Show],
+                id_param: "id",
+            },
+        );
+
+        let (_, next_called) = dispatch(&router, "/unlisted").await;
+        assert!(next_called);
+
+        for (method, path) in [
+            ("POST", "/items"),
+            ("PUT", "/items/1"),
+            ("PATCH", "/items/1"),
+            ("DELETE", "/items/1"),
+        ] {
+            let (res, next_called) = dispatch_method(&router, method, path).await;
+            assert_eq!(res.status(), Some(405), "{} {}", method, path);
+            assert_eq!(res.get_header("Allow"), Some("GET, OPTIONS"));
+            assert!(!next_called);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router_routes_resources() {
+        let mut router = Router::new("/api");
+        router.resource_with(
+            "/todos",
+            Items {
+                actions: vec![
+                    ResourceAction::Index,
+                    ResourceAction::Show,
+                    ResourceAction::Update,
+                ],
+                id_param: "todo_id",
+            },
+            |todo| {
+                todo.resource(
+                    "/comments",
+                    Items {
+                        actions: vec![ResourceAction::Index, ResourceAction::Show],
+                        id_param: "id",
+                    },
+                );
+            },
+        );
+
+        let (res, _) = dispatch(&router, "/api/todos/7").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("show 7".to_string())));
+
+        let (res, _) = dispatch_method(&router, "PATCH", "/api/todos/7").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("update 7".to_string())));
+
+        let (res, _) = dispatch(&router, "/api/todos/7/comments").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("index 7".to_string())));
+
+        let (res, _) = dispatch(&router, "/api/todos/7/comments/3").await;
+        assert_eq!(res.get_body(), Some(&Body::Text("show 3".to_string())));
+
+        let (res, next_called) =
+            dispatch_method(&router, "DELETE", "/api/todos/7/comments/3").await;
+        assert_eq!(res.status(), Some(405));
+        assert_eq!(res.get_header("Allow"), Some("GET, OPTIONS"));
+        assert!(!next_called);
+
+        let (res, _) = dispatch_method(&router, "DELETE", "/api/todos/7").await;
+        assert_eq!(res.status(), Some(405));
+
+        let no_params = HashMap::new();
+        assert_eq!(
+            router.url_for("api.todos.index", &no_params).unwrap(),
+            "/api/todos"
+        );
+        assert_eq!(
+            router
+                .url_for(
+                    "api.todos.comments.show",
+                    &HashMap::from([("todo_id", "7"), ("id", "3")])
+                )
+                .unwrap(),
+            "/api/todos/7/comments/3"
+        );
+        assert!(router.conflicts().is_empty());
+    }
 }
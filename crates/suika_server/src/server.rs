@@ -1,3 +1,6 @@
+use crate::diagnostics::{error, info};
+use crate::error::HttpError;
+use crate::error_handler::ErrorHandler;
 use crate::middleware::{Middleware, Next};
 use crate::request::Request;
 use crate::response::Response;
@@ -15,6 +18,7 @@ pub struct Server {
     address: String,
     middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>>,
     template_engine: Option<TemplateEngine>,
+    error_handler: Option<ErrorHandler>,
     modules: Arc<Mutex<HashMap<String, Arc<dyn std::any::Any + Send + Sync>>>>,
     shutdown_signal: Option<Arc<Mutex<Option<oneshot::Sender<()>>>>>,
 }
@@ -38,6 +42,7 @@ impl Server {
             address: address.to_string(),
             middleware_stack: Vec::new(),
             template_engine: None,
+            error_handler: None,
             modules: Arc::new(Mutex::new(HashMap::new())),
             shutdown_signal: None,
         }
@@ -99,6 +104,38 @@ impl Server {
         self.template_engine = Some(engine);
     }
 
+    /// Sets the hook called with the error of every failed request.
+    ///
+    /// The hook runs after the middleware stack when the response was set to
+    /// an error with `Response::error`, including errors returned by
+    /// middleware and route handlers, and for requests no middleware
+    /// answered, which fail with `HttpError::NotFound`. It is skipped when
+    /// the response's final status is not an error, e.g. when a handler set
+    /// an error and then answered successfully. It can replace the plain text
+    /// error body, e.g. with `ErrorPages`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The hook, called with the error, the request and the response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_server::server::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080");
+    /// server.use_error_handler(|error, req, res| {
+    ///     let (_, message) = error.to_tuple();
+    ///     res.text(format!("{} ({})", message, req.path()));
+    /// });
+    /// ```
+    pub fn use_error_handler<F>(&mut self, handler: F)
+    where
+        F: Fn(&HttpError, &Request, &mut Response) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(handler));
+    }
+
     /// Adds a module to the server.
     ///
     /// # Arguments
@@ -145,6 +182,7 @@ impl Server {
         let address = self.address.clone();
         let middleware_stack = self.middleware_stack.clone();
         let template_engine = self.template_engine.clone();
+        let error_handler = self.error_handler.clone();
         let modules = Arc::clone(&self.modules);
 
         if let Some(handle) = existing_runtime {
@@ -153,6 +191,7 @@ impl Server {
                     address,
                     middleware_stack,
                     template_engine,
+                    error_handler,
                     modules,
                     shutdown_rx,
                 )
@@ -174,6 +213,7 @@ impl Server {
                     address,
                     middleware_stack,
                     template_engine,
+                    error_handler,
                     modules,
                     shutdown_rx,
                 )
@@ -189,12 +229,14 @@ impl Server {
     /// * `address` - The address on which the server will listen for incoming connections.
     /// * `middleware_stack` - A vector of middleware to be applied to each request.
     /// * `template_engine` - An optional template engine for rendering responses.
+    /// * `error_handler` - An optional hook for rendering error responses.
     /// * `modules` - A collection of modules that can be used by the server.
     /// * `shutdown_rx` - A receiver for the shutdown signal.
     async fn run_server(
         address: String,
         middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>>,
         template_engine: Option<TemplateEngine>,
+        error_handler: Option<ErrorHandler>,
         modules: Arc<Mutex<HashMap<String, Arc<dyn std::any::Any + Send + Sync>>>>,
         mut shutdown_rx: oneshot::Receiver<()>,
     ) {
@@ -202,18 +244,19 @@ impl Server {
             .await
             .expect("Failed to bind address");
 
-        println!("Server running on {}", address);
+        info!("Server running on {}", address);
 
         loop {
             tokio::select! {
                 _ = &mut shutdown_rx => {
-                    println!("Server shutting down");
+                    info!("Server shutting down");
                     break;
                 }
-                Ok((mut stream, _)) = listener.accept() => {
+                Ok((mut stream, remote_addr)) = listener.accept() => {
                     let mw_stack = middleware_stack.clone();
                     let tmpl_engine = template_engine.clone().map(Arc::new);
                     let modules = Arc::clone(&modules);
+                    let error_handler = error_handler.clone();
 
                     tokio::spawn(async move {
                         let mut buffer = [0; 1024];
@@ -221,29 +264,73 @@ impl Server {
                             if size > 0 {
                                 let request_str = String::from_utf8_lossy(&buffer[..size]);
                                 let mut req = Request::new(&request_str, Arc::clone(&modules)).unwrap();
+                                req.set_remote_addr(remote_addr);
                                 let mut res = Response::new(tmpl_engine.clone());
 
-                                let mut next = Next::new(&mw_stack);
-                                if let Err(e) = next.run(&mut req, &mut res).await {
-                                    res.error(e).await;
-                                }
-
-                                let status = res.status().await;
-                                if status == None {
-                                    res.set_status(404).await;
-                                    res.body("404 Not Found".to_string()).await;
-                                }
-
+                                Server::respond(&mw_stack, error_handler.as_ref(), &mut req, &mut res).await;
                                 res.send(&mut stream).await.unwrap();
                             }
                         }
                     });
                 }
-                else => eprintln!("Failed to accept connection"),
+                else => error!("Failed to accept connection"),
             }
         }
     }
 
+    /// Handles a request, within a `request` span recording its method,
+    /// path, route, status, latency and trace context with the `tracing`
+    /// feature.
+    async fn respond(
+        middleware_stack: &[Arc<dyn Middleware + Send + Sync>],
+        error_handler: Option<&ErrorHandler>,
+        req: &mut Request,
+        res: &mut Response,
+    ) {
+        #[cfg(feature = "tracing")]
+        let (span, start) = (request_span(req), std::time::Instant::now());
+
+        let handled = Server::handle_request(middleware_stack, error_handler, req, res);
+        #[cfg(feature = "tracing")]
+        let handled = tracing::Instrument::instrument(handled, span.clone());
+        handled.await;
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("status", res.status().unwrap_or_default());
+            span.record("latency_ms", start.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// Runs a request through the middleware stack, answering `404 Not Found`
+    /// when no middleware set a status, lets the error handler rewrite
+    /// responses that end with an error status and runs the response's finish
+    /// callbacks.
+    pub(crate) async fn handle_request(
+        middleware_stack: &[Arc<dyn Middleware + Send + Sync>],
+        error_handler: Option<&ErrorHandler>,
+        req: &mut Request,
+        res: &mut Response,
+    ) {
+        let mut next = Next::new(middleware_stack);
+        if let Err(e) = next.run(req, res).await {
+            res.error(e);
+        }
+
+        if res.status().is_none() {
+            res.error(HttpError::NotFound("Not Found".to_string()));
+        }
+
+        let failed = res.status().is_some_and(|status| status >= 400);
+        if let (Some(error_handler), Some(error), true) =
+            (error_handler, res.get_error().cloned(), failed)
+        {
+            error_handler(&error, req, res);
+        }
+
+        res.finish();
+    }
+
     /// Stops the running server by sending a shutdown signal.
     ///
     /// # Examples
@@ -276,12 +363,34 @@ impl Server {
     }
 }
 
+/// Creates the span of a request, continuing the trace of its `traceparent`
+/// header.
+#[cfg(feature = "tracing")]
+fn request_span(req: &Request) -> tracing::Span {
+    use crate::trace_context::TraceContext;
+    use tracing::field::Empty;
+
+    let context = TraceContext::from_request(req);
+    tracing::info_span!(
+        "request",
+        method = req.method(),
+        path = req.path(),
+        route = Empty,
+        status = Empty,
+        latency_ms = Empty,
+        request_id = Empty,
+        trace_id = context.as_ref().map(TraceContext::trace_id),
+        parent_id = context.as_ref().map(TraceContext::parent_id),
+        sampled = context.as_ref().map(TraceContext::sampled),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::middleware::{Middleware, MiddlewareFuture, Next};
     use crate::request::Request;
-    use crate::response::Response;
+    use crate::response::{Body, Response};
     use std::sync::Arc;
     use tokio::io::{AsyncReadExt, AsyncWriteExt};
     use tokio::net::TcpStream;
@@ -315,8 +424,8 @@ mod tests {
                     let mut called_lock = called.lock().await;
                     *called_lock = true;
                 }
-                res.set_status(200).await;
-                res.body("Mock response".to_string()).await;
+                res.set_status(200);
+                res.body("Mock response".to_string());
                 next.run(req, res).await
             })
         }
@@ -374,4 +483,78 @@ mod tests {
 
         server.stop();
     }
+    #[tokio::test]
+    async fn test_server_runs_error_handler() {
+        let address = "127.0.0.1:8083";
+        let mut server = Server::new(address);
+        server.use_error_handler(|error, req, res| {
+            let (status, message) = error.to_tuple();
+            res.body(format!("Custom {} {} at {}", status, message, req.path()));
+        });
+        let runtime_handle = tokio::runtime::Handle::current();
+
+        server.run(Some(&runtime_handle));
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(b"GET /missing HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 1024];
+        let size = stream.read(&mut buffer).await.unwrap();
+        let response_str = String::from_utf8_lossy(&buffer[..size]);
+
+        assert!(
+            response_str.starts_with("HTTP/1.1 404"),
+            "Response: {}",
+            response_str
+        );
+        assert!(
+            response_str.contains("Custom 404 Not Found at /missing"),
+            "Response: {}",
+            response_str
+        );
+
+        server.stop();
+    }
+
+    struct RecoveringHandler;
+
+    impl Middleware for RecoveringHandler {
+        fn handle<'a>(
+            &'a self,
+            _req: &'a mut Request,
+            res: &'a mut Response,
+            _next: Next<'a>,
+        ) -> MiddlewareFuture<'a> {
+            Box::pin(async move {
+                res.error(HttpError::BadRequest("Invalid cache".to_string()));
+                res.set_status(200).body("Recovered".to_string());
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_handler_skips_successful_responses() {
+        let error_handler: ErrorHandler = Arc::new(|_error, _req, res| {
+            res.body("Error page".to_string());
+        });
+        let middleware_stack: Vec<Arc<dyn Middleware + Send + Sync>> =
+            vec![Arc::new(RecoveringHandler)];
+        let mut req = Request::new(
+            "GET / HTTP/1.1\r\n\r\n",
+            Arc::new(std::sync::Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+        let mut res = Response::new(None);
+
+        Server::handle_request(&middleware_stack, Some(&error_handler), &mut req, &mut res).await;
+
+        assert_eq!(res.status(), Some(200));
+        assert_eq!(res.get_body(), Some(&Body::Text("Recovered".to_string())));
+    }
 }
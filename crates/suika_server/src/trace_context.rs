@@ -0,0 +1,178 @@
+use crate::request::Request;
+use std::fmt;
+
+/// A W3C Trace Context, carried by the `traceparent` header.
+///
+/// With the `tracing` feature, the server records the trace context of each
+/// request in the request's span, so events can be correlated with the
+/// caller's trace. Outgoing requests can propagate it with `child` and
+/// `to_header`.
+///
+/// # Examples
+///
+/// ```
+/// use suika_server::trace_context::TraceContext;
+///
+/// let context =
+///     TraceContext::parse("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+///
+/// assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+/// assert_eq!(context.parent_id(), "00f067aa0ba902b7");
+/// assert!(context.sampled());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: String,
+    parent_id: String,
+    flags: u8,
+}
+
+impl TraceContext {
+    /// Parses a `traceparent` header value.
+    ///
+    /// Returns `None` if the value is malformed, uses the invalid version
+    /// `ff`, or has an all-zero trace or parent id. Values of future versions
+    /// are accepted if they start with the version 00 fields.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The value of the `traceparent` header.
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+
+        if !is_hex(version, 2) || version.eq_ignore_ascii_case("ff") {
+            return None;
+        }
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if is_zero(trace_id) || is_zero(parent_id) {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_ascii_lowercase(),
+            parent_id: parent_id.to_ascii_lowercase(),
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        })
+    }
+
+    /// Reads the trace context from a request's `traceparent` header.
+    pub fn from_request(req: &Request) -> Option<Self> {
+        req.get_header("traceparent").and_then(Self::parse)
+    }
+
+    /// Returns the 32 hex digit id of the trace.
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// Returns the 16 hex digit id of the caller's span.
+    pub fn parent_id(&self) -> &str {
+        &self.parent_id
+    }
+
+    /// Returns the trace flags.
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// Returns `true` if the caller may have recorded the trace.
+    pub fn sampled(&self) -> bool {
+        self.flags & 0x01 == 0x01
+    }
+
+    /// Returns a context for a call made from the span `span_id` of the
+    /// same trace.
+    ///
+    /// # Arguments
+    ///
+    /// * `span_id` - The 16 hex digit id of the calling span.
+    pub fn child(&self, span_id: &str) -> Self {
+        Self {
+            trace_id: self.trace_id.clone(),
+            parent_id: span_id.to_ascii_lowercase(),
+            flags: self.flags,
+        }
+    }
+
+    /// Formats the context as a version 00 `traceparent` header value.
+    pub fn to_header(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            self.trace_id, self.parent_id, self.flags
+        )
+    }
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+fn is_zero(value: &str) -> bool {
+    value.bytes().all(|b| b == b'0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const HEADER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_parse_traceparent() {
+        let context = TraceContext::parse(HEADER).unwrap();
+        assert_eq!(context.flags(), 1);
+        assert_eq!(context.to_header(), HEADER);
+
+        let upper = TraceContext::parse(&HEADER.to_ascii_uppercase()).unwrap();
+        assert_eq!(upper, context);
+
+        let future = format!("cc-{}-{}-00-extra", context.trace_id(), context.parent_id());
+        assert!(!TraceContext::parse(&future).unwrap().sampled());
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e47zz-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceContext::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_trace_context_from_request() {
+        let req = Request::new(
+            &format!("GET / HTTP/1.1\r\nTraceparent: {}\r\n\r\n", HEADER),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+        .unwrap();
+
+        let context = TraceContext::from_request(&req).unwrap();
+        assert_eq!(
+            context.child("B7AD6B7169203331").to_header(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-b7ad6b7169203331-01"
+        );
+    }
+}
@@ -115,7 +115,14 @@ impl<'a> TemplateParser<'a> {
             if c == '%' && self.chars.as_str().starts_with(">") {
                 self.next_char();
                 self.next_char();
-                return Ok(Some(TemplateToken::Variable(var_name.trim().to_string())));
+                let var_name = var_name.trim();
+                if let Some(args) = var_name
+                    .strip_prefix("url_for(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                {
+                    return self.parse_url_for(args).map(Some);
+                }
+                return Ok(Some(TemplateToken::Variable(var_name.to_string())));
             }
             var_name.push(c);
             self.next_char();
@@ -123,6 +130,23 @@ impl<'a> TemplateParser<'a> {
         Err("Unexpected end of input in variable".to_string())
     }
 
+    fn parse_url_for(&self, args: &str) -> Result<TemplateToken, String> {
+        let mut parts = split_args(args)?.into_iter();
+        let name = parts
+            .next()
+            .and_then(|name| unquote(&name).map(str::to_string))
+            .ok_or_else(|| format!("url_for expects a quoted route name: {}", args))?;
+
+        let mut params = Vec::new();
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid url_for argument: {}", part))?;
+            params.push((key.trim().to_string(), value.trim().to_string()));
+        }
+        Ok(TemplateToken::UrlFor(name, params))
+    }
+
     fn parse_template_directive(&mut self) -> Result<Option<TemplateToken>, String> {
         match self.current_char {
             Some('=') => {
@@ -141,14 +165,14 @@ impl<'a> TemplateParser<'a> {
                 self.next_char();
                 self.next_char();
                 directive = directive.trim().to_string();
-                if directive.starts_with("if ") {
-                    return Ok(Some(TemplateToken::If(directive[3..].to_string())));
+                if let Some(condition) = directive.strip_prefix("if ") {
+                    return Ok(Some(TemplateToken::If(condition.to_string())));
                 } else if directive == "else" {
                     return Ok(Some(TemplateToken::Else));
                 } else if directive == "endif" {
                     return Ok(Some(TemplateToken::EndIf));
-                } else if directive.starts_with("for ") {
-                    let parts: Vec<&str> = directive[4..].split_whitespace().collect();
+                } else if let Some(rest) = directive.strip_prefix("for ") {
+                    let parts: Vec<&str> = rest.split_whitespace().collect();
                     if parts.len() == 3 && parts[1] == "in" {
                         return Ok(Some(TemplateToken::For(
                             parts[0].to_string(),
@@ -158,18 +182,12 @@ impl<'a> TemplateParser<'a> {
                     return Err(format!("Invalid for directive: {}", directive));
                 } else if directive == "endfor" {
                     return Ok(Some(TemplateToken::EndFor));
-                } else if directive.starts_with("extend ") {
-                    return Ok(Some(TemplateToken::Extend(
-                        directive[7..].trim().to_string(),
-                    )));
-                } else if directive.starts_with("include ") {
-                    return Ok(Some(TemplateToken::Include(
-                        directive[8..].trim().to_string(),
-                    )));
-                } else if directive.starts_with("block ") {
-                    return Ok(Some(TemplateToken::Block(
-                        directive[6..].trim().to_string(),
-                    )));
+                } else if let Some(name) = directive.strip_prefix("extend ") {
+                    return Ok(Some(TemplateToken::Extend(name.trim().to_string())));
+                } else if let Some(name) = directive.strip_prefix("include ") {
+                    return Ok(Some(TemplateToken::Include(name.trim().to_string())));
+                } else if let Some(name) = directive.strip_prefix("block ") {
+                    return Ok(Some(TemplateToken::Block(name.trim().to_string())));
                 } else if directive == "endblock" {
                     return Ok(Some(TemplateToken::EndBlock));
                 } else {
@@ -183,6 +201,38 @@ impl<'a> TemplateParser<'a> {
     }
 }
 
+/// Splits a comma-separated argument list, ignoring commas inside quotes.
+fn split_args(args: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in args.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' if !in_quotes => parts.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("Unclosed quote in arguments: {}", args));
+    }
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_string());
+    }
+    Ok(parts)
+}
+
+/// Returns the contents of a double-quoted string, or `None` if the value is
+/// not quoted.
+pub(crate) fn unquote(value: &str) -> Option<&str> {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,6 +261,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_url_for() {
+        let mut parser =
+            TemplateParser::new(r#"<a href="<%= url_for("item", id=item.id, q="a, b") %>">"#);
+        let tokens = parser.parse().unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                TemplateToken::Text("<a href=\"".to_string()),
+                TemplateToken::UrlFor(
+                    "item".to_string(),
+                    vec![
+                        ("id".to_string(), "item.id".to_string()),
+                        ("q".to_string(), "\"a, b\"".to_string()),
+                    ]
+                ),
+                TemplateToken::Text("\">".to_string()),
+            ]
+        );
+
+        assert!(TemplateParser::new("<%= url_for(item) %>").parse().is_err());
+        assert!(TemplateParser::new(r#"<%= url_for("item", id) %>"#)
+            .parse()
+            .is_err());
+    }
+
     #[test]
     fn test_parse_if() {
         let mut parser = TemplateParser::new("<% if is_member %>Welcome!<% endif %>");
@@ -3,15 +3,32 @@
 //! The `TemplateEngine` struct provides methods to manage and render templates with
 //! various directives and context values.
 
+use super::parser::unquote;
 use super::{TemplateParser, TemplateToken, TemplateValue};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 use suika_utils::minify_html;
 
-#[derive(Debug, Clone)]
+/// A function building the URL of a named route from its arguments, used by
+/// `url_for` in templates.
+pub type UrlForFn = dyn Fn(&str, &[(String, String)]) -> Result<String, String> + Send + Sync;
+
+#[derive(Clone)]
+struct UrlFor(Arc<UrlForFn>);
+
+impl fmt::Debug for UrlFor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UrlFor")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct TemplateEngine {
     templates: HashMap<String, String>,
+    url_for: Option<UrlFor>,
 }
 
 impl TemplateEngine {
@@ -27,6 +44,7 @@ impl TemplateEngine {
     pub fn new() -> Self {
         Self {
             templates: HashMap::new(),
+            url_for: None,
         }
     }
 
@@ -49,6 +67,37 @@ impl TemplateEngine {
         self.templates.insert(name.to_string(), content.to_string());
     }
 
+    /// Sets the function used to build URLs for `url_for` calls in templates.
+    ///
+    /// Templates call it as `<%= url_for("name", key=value) %>`, where each
+    /// value is either a quoted literal or the name of a context variable.
+    ///
+    /// # Arguments
+    ///
+    /// * `url_for` - A function taking the route name and its arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use suika_templates::template_engine::TemplateEngine;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut engine = TemplateEngine::new();
+    /// engine.set_url_for(|name, args| {
+    ///     let query: Vec<String> = args.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    ///     Ok(format!("/{}?{}", name, query.join("&")))
+    /// });
+    /// engine.add_template("link", r#"<%= url_for("search", q="rust") %>"#);
+    ///
+    /// assert_eq!(engine.render("link", &HashMap::new()).unwrap(), "/search?q=rust");
+    /// ```
+    pub fn set_url_for<F>(&mut self, url_for: F)
+    where
+        F: Fn(&str, &[(String, String)]) -> Result<String, String> + Send + Sync + 'static,
+    {
+        self.url_for = Some(UrlFor(Arc::new(url_for)));
+    }
+
     /// Loads templates from the specified directory.
     ///
     /// # Arguments
@@ -227,6 +276,9 @@ impl TemplateEngine {
                 TemplateToken::Variable(name) => {
                     self.process_variable(name, context, &mut output)?
                 }
+                TemplateToken::UrlFor(name, args) => {
+                    self.process_url_for(name, args, context, &mut output)?
+                }
                 TemplateToken::If(condition) => {
                     i = self.process_if(condition, tokens, context, &mut output, i)?
                 }
@@ -259,6 +311,33 @@ impl TemplateEngine {
         Ok(())
     }
 
+    fn process_url_for(
+        &self,
+        name: &str,
+        args: &[(String, String)],
+        context: &HashMap<String, TemplateValue>,
+        output: &mut String,
+    ) -> Result<(), String> {
+        let url_for = self
+            .url_for
+            .as_ref()
+            .ok_or_else(|| "url_for is not configured".to_string())?;
+
+        let mut values = Vec::with_capacity(args.len());
+        for (key, arg) in args {
+            let value = match unquote(arg) {
+                Some(literal) => literal.to_string(),
+                None => self
+                    .resolve_variable(arg, context)
+                    .ok_or_else(|| format!("Variable '{}' not found for url_for", arg))?,
+            };
+            values.push((key.clone(), value));
+        }
+
+        output.push_str(&(url_for.0)(name, &values)?);
+        Ok(())
+    }
+
     fn process_if(
         &self,
         condition: &str,
@@ -398,6 +477,33 @@ mod tests {
         assert_eq!(result, "Hello, World!");
     }
 
+    #[test]
+    fn test_render_url_for() {
+        let mut engine = TemplateEngine::new();
+        engine.add_template(
+            "link",
+            r#"<a href="<%= url_for("item", id=item.id, tab="reviews") %>">Item</a>"#,
+        );
+
+        let mut item = HashMap::new();
+        item.insert("id".to_string(), TemplateValue::String("42".to_string()));
+        let mut context = HashMap::new();
+        context.insert("item".to_string(), TemplateValue::Object(item));
+
+        assert!(engine.render("link", &context).is_err());
+
+        engine.set_url_for(|name, args| match name {
+            "item" => Ok(format!("/items/{}/{}", args[0].1, args[1].1)),
+            _ => Err(format!("No route named {}", name)),
+        });
+        let result = engine
+            .render("link", &context)
+            .expect("Failed to render template");
+        assert_eq!(result, r#"<a href="/items/42/reviews">Item</a>"#);
+
+        assert!(engine.render("link", &HashMap::new()).is_err());
+    }
+
     #[test]
     fn test_render_conditional_true() {
         let mut engine = TemplateEngine::new();
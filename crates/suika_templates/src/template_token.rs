@@ -12,6 +12,9 @@
 pub enum TemplateToken {
     Text(String),
     Variable(String),
+    /// A `url_for("name", key=value)` call. Argument values are either quoted
+    /// literals or variable names.
+    UrlFor(String, Vec<(String, String)>),
     If(String),
     Else,
     EndIf,
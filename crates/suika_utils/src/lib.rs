@@ -146,6 +146,37 @@ pub fn build_url(base: &str, params: &HashMap<&str, &str>) -> String {
     url
 }
 
+/// Percent-encodes a string for use in a URL path segment or query component.
+///
+/// Unreserved characters (`A-Z`, `a-z`, `0-9`, `-`, `.`, `_` and `~`) are
+/// kept as they are, every other byte is written as `%XX`.
+///
+/// # Arguments
+///
+/// * `input` - A string slice that holds the text to encode.
+///
+/// # Returns
+///
+/// A String containing the encoded text.
+///
+/// # Examples
+///
+/// ```
+/// use suika_utils::percent_encode;
+/// assert_eq!(percent_encode("caf\u{e9} & bar/1"), "caf%C3%A9%20%26%20bar%2F1");
+/// ```
+pub fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
 /// Parses a URL into its components: scheme, host, path, and query parameters.
 ///
 /// # Arguments
@@ -363,6 +394,13 @@ mod tests {
         assert_eq!(url, "https://example.com?age=30&name=John");
     }
 
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("hello-world_1.0~"), "hello-world_1.0~");
+        assert_eq!(percent_encode("a b/c?d=e&f"), "a%20b%2Fc%3Fd%3De%26f");
+        assert_eq!(percent_encode("\u{fc}"), "%C3%BC");
+    }
+
     #[test]
     fn test_parse_url() {
         let url = "https://example.com/path?name=John&age=30";
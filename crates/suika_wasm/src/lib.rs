@@ -68,10 +68,8 @@ const JS_FILE: &str = include_str!("../wasm/suika_ui.js");
 ///     let next = Next::new(middleware_stack.as_slice());
 ///
 ///     wasm_file_middleware.handle(&mut req, &mut res, next.clone()).await.unwrap();
-///     let inner = res.get_inner().await;
-///
-///     assert_eq!(inner.status_code(), Some(200));
-///     assert_eq!(inner.headers().get("Content-Type"), Some(&"application/wasm".to_string()));
+///     assert_eq!(res.status(), Some(200));
+///     assert_eq!(res.headers().get("Content-Type"), Some(&"application/wasm".to_string()));
 /// }
 /// ```
 pub struct WasmFileMiddleware {
@@ -170,10 +168,9 @@ impl Middleware for WasmFileMiddleware {
     ///     let next = Next::new(middleware_stack.as_slice());
     ///
     ///     wasm_file_middleware.handle(&mut req, &mut res, next.clone()).await.unwrap();
-    ///     let inner = res.get_inner().await;
     ///
-    ///     assert_eq!(inner.status_code(), Some(200));
-    ///     assert_eq!(inner.headers().get("Content-Type"), Some(&"application/javascript".to_string()));
+    ///     assert_eq!(res.status(), Some(200));
+    ///     assert_eq!(res.headers().get("Content-Type"), Some(&"application/javascript".to_string()));
     /// }
     /// ```
     fn handle<'a>(
@@ -188,24 +185,22 @@ impl Middleware for WasmFileMiddleware {
 
         Box::pin(async move {
             if path == format!("{}/suika_ui_bg.wasm", url_prefix) {
-                res.header("Content-Type", "application/wasm").await;
+                res.header("Content-Type", "application/wasm");
                 res.header(
                     "Cache-Control",
                     &format!("public, max-age={}", cache_duration),
-                )
-                .await;
-                res.set_status(200).await;
-                res.body_bytes(WASM_BINARY.to_vec()).await;
+                );
+                res.set_status(200);
+                res.body_bytes(WASM_BINARY.to_vec());
                 Ok(())
             } else if path == format!("{}/suika_ui.js", url_prefix) {
-                res.header("Content-Type", "application/javascript").await;
+                res.header("Content-Type", "application/javascript");
                 res.header(
                     "Cache-Control",
                     &format!("public, max-age={}", cache_duration),
-                )
-                .await;
-                res.set_status(200).await;
-                res.body(JS_FILE.to_string()).await;
+                );
+                res.set_status(200);
+                res.body(JS_FILE.to_string());
                 Ok(())
             } else {
                 next.run(req, res).await
@@ -275,13 +270,12 @@ mod tests {
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
+        assert_eq!(res.status(), Some(200));
         assert_eq!(
-            inner.headers().get("Content-Type"),
+            res.headers().get("Content-Type"),
             Some(&"application/wasm".to_string())
         );
-        assert_eq!(inner.body(), &Some(Body::Binary(WASM_BINARY.to_vec())));
+        assert_eq!(res.get_body(), Some(&Body::Binary(WASM_BINARY.to_vec())));
 
         let next_called = *next_middleware.called.lock().await;
         assert!(!next_called);
@@ -308,13 +302,12 @@ mod tests {
             .await
             .unwrap();
 
-        let inner = res.get_inner().await;
-        assert_eq!(inner.status_code(), Some(200));
+        assert_eq!(res.status(), Some(200));
         assert_eq!(
-            inner.headers().get("Content-Type"),
+            res.headers().get("Content-Type"),
             Some(&"application/javascript".to_string())
         );
-        assert_eq!(inner.body(), &Some(Body::Text(JS_FILE.to_string())));
+        assert_eq!(res.get_body(), Some(&Body::Text(JS_FILE.to_string())));
 
         let next_called = *next_middleware.called.lock().await;
         assert!(!next_called);